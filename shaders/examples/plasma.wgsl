@@ -0,0 +1,69 @@
+// Plasma example: the classic demoscene effect, from summed sine waves
+
+struct Time {
+    time: u32;
+    strobe: u32;
+    seconds: u32;
+    subsecond: f32;
+    just_reloaded: u32;
+    beat_phase: f32;
+    beat: u32;
+    frames_since_reload: u32;
+    samples: u32;
+    frame: u32;
+    delta: f32;
+};
+
+[[group(0), binding(0)]]
+var<uniform> time: Time;
+
+struct VertexInput {
+    [[location(0)]] position: vec3<f32>;
+    [[location(1)]] tex_coords: vec2<f32>;
+    [[location(2)]] normal: vec3<f32>;
+};
+
+struct VertexOutput {
+    [[builtin(position)]] clip_position: vec4<f32>;
+    // 0..1 across the screen, with y pointing up
+    [[location(0)]] tex_coords: vec2<f32>;
+};
+
+[[stage(vertex)]]
+fn vs_main(model: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.tex_coords = model.tex_coords;
+    out.clip_position = vec4<f32>(model.position, 1.0);
+    return out;
+}
+
+// for --fullscreen-tri
+[[stage(vertex)]]
+fn vs_fullscreen([[builtin(vertex_index)]] index: u32) -> VertexOutput {
+    let corner = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.tex_coords = corner;
+    out.clip_position = vec4<f32>(corner * 2.0 - 1.0, 0.0, 1.0);
+    return out;
+}
+
+// seconds since start, wrapped every hour to keep precision
+fn seconds() -> f32 {
+    return f32(time.seconds % 3600u) + time.subsecond;
+}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    let t = seconds();
+    let uv = in.tex_coords * 8.0;
+    // each wave moves in its own direction, and they interfere where they overlap
+    var value = sin(uv.x + t);
+    value = value + sin((uv.y + t) * 0.5);
+    value = value + sin((uv.x + uv.y + t) * 0.5);
+    let centre = uv + vec2<f32>(sin(t / 3.0), cos(t / 2.0)) * 4.0;
+    value = value + sin(length(centre) + t);
+    // map the sum through phase-shifted cosines for a smooth palette
+    let phase = value * 3.14159 * 0.5;
+    let colour = 0.5 + 0.5 * cos(vec3<f32>(phase, phase + 2.094, phase + 4.189));
+    return vec4<f32>(colour, 1.0);
+}
@@ -0,0 +1,102 @@
+// Raymarched sphere example: a lit sphere drawn with a signed distance field
+// steps along a ray from the camera until it gets close to the surface
+
+struct Time {
+    time: u32;
+    strobe: u32;
+    seconds: u32;
+    subsecond: f32;
+    just_reloaded: u32;
+    beat_phase: f32;
+    beat: u32;
+    frames_since_reload: u32;
+    samples: u32;
+    frame: u32;
+    delta: f32;
+};
+
+[[group(0), binding(0)]]
+var<uniform> time: Time;
+
+struct VertexInput {
+    [[location(0)]] position: vec3<f32>;
+    [[location(1)]] tex_coords: vec2<f32>;
+    [[location(2)]] normal: vec3<f32>;
+};
+
+struct VertexOutput {
+    [[builtin(position)]] clip_position: vec4<f32>;
+    // 0..1 across the screen, with y pointing up
+    [[location(0)]] tex_coords: vec2<f32>;
+};
+
+[[stage(vertex)]]
+fn vs_main(model: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.tex_coords = model.tex_coords;
+    out.clip_position = vec4<f32>(model.position, 1.0);
+    return out;
+}
+
+// for --fullscreen-tri
+[[stage(vertex)]]
+fn vs_fullscreen([[builtin(vertex_index)]] index: u32) -> VertexOutput {
+    let corner = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.tex_coords = corner;
+    out.clip_position = vec4<f32>(corner * 2.0 - 1.0, 0.0, 1.0);
+    return out;
+}
+
+// seconds since start, wrapped every hour to keep precision
+fn seconds() -> f32 {
+    return f32(time.seconds % 3600u) + time.subsecond;
+}
+
+let MAX_STEPS: i32 = 64;
+let MAX_DISTANCE: f32 = 20.0;
+// how close counts as touching the surface
+let EPSILON: f32 = 0.001;
+
+// distance from a point to the nearest surface in the scene
+fn scene(point: vec3<f32>) -> f32 {
+    // a sphere of radius 1 bobbing up and down
+    let centre = vec3<f32>(0.0, 0.25 * sin(seconds()), 0.0);
+    return length(point - centre) - 1.0;
+}
+
+// surface normal, from the gradient of the distance field
+fn normal(point: vec3<f32>) -> vec3<f32> {
+    let offset = vec2<f32>(EPSILON, 0.0);
+    return normalize(vec3<f32>(
+        scene(point + offset.xyy) - scene(point - offset.xyy),
+        scene(point + offset.yxy) - scene(point - offset.yxy),
+        scene(point + offset.yyx) - scene(point - offset.yyx)
+    ));
+}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    // a ray from a camera in front of the sphere, through this pixel
+    let origin = vec3<f32>(0.0, 0.0, 3.0);
+    let direction = normalize(vec3<f32>(in.tex_coords * 2.0 - 1.0, -1.5));
+
+    var distance = 0.0;
+    for (var step = 0; step < MAX_STEPS; step = step + 1) {
+        let point = origin + direction * distance;
+        let nearest = scene(point);
+        if (nearest < EPSILON) {
+            // diffuse lighting from a light circling the sphere
+            let t = seconds();
+            let light = normalize(vec3<f32>(cos(t), 1.0, sin(t)));
+            let diffuse = max(dot(normal(point), light), 0.0);
+            return vec4<f32>(vec3<f32>(0.9, 0.5, 0.3) * (0.1 + 0.9 * diffuse), 1.0);
+        }
+        distance = distance + nearest;
+        if (distance > MAX_DISTANCE) {
+            break;
+        }
+    }
+    // missed, so draw the background
+    return vec4<f32>(vec3<f32>(0.1, 0.2, 0.3) * in.tex_coords.y, 1.0);
+}
@@ -1,7 +1,46 @@
 // Vertex shader
 
+// the built-in uniforms share group 0, one binding each,
+// except the --tile cell which is in group 1 because it changes between draws
+// the bindings below are the defaults, and --uniforms picks a subset and order instead,
+// e.g. with `--uniforms mouse,time` the mouse is binding 0 and the time is binding 1
+// shaders from before the uniforms shared a group gave each one its own, in the order
+// time, mouse, camera, frame times, tile, lod, custom, with --texture images and pass inputs
+// in group 7; move each uniform to `group(0)` at its binding below, the tile to `group(1)`,
+// and the images and inputs to `group(2)`, keeping their bindings
+
 struct Time {
     time: u32;
+    // frame number modulo --strobe-period
+    strobe: u32;
+    // high precision time for long-running shaders
+    // whole seconds since start, plus the fraction of the current second
+    // e.g. `f32(time.seconds % 3600u) + time.subsecond` for an hour-long loop
+    // that stays precise however long the program runs
+    seconds: u32;
+    subsecond: f32;
+    // 1 for the first frame after the shader is loaded or reloaded, then 0
+    // e.g. for clearing feedback or reseeding a simulation
+    just_reloaded: u32;
+    // 0..1 through the current beat at --bpm, or the tempo tapped in with T
+    // 0 on the beat, so e.g. `exp(-8.0 * time.beat_phase)` pulses on each one
+    beat_phase: f32;
+    // beats since --beat-offset, or since the last tap
+    beat: u32;
+    // frames drawn since the shader was loaded or reloaded, starting at 0
+    // e.g. to blend `1.0 / f32(time.frames_since_reload + 1u)` of each new frame into
+    // a progressive render, and stop once it's converged
+    frames_since_reload: u32;
+    // frames --accumulate has averaged together, including this one
+    // it goes back to 1 when the mouse is used, a custom uniform or texture changes,
+    // the window resizes or the shader reloads
+    // e.g. to jitter each sample differently, or stop when there's enough
+    samples: u32;
+    // frames drawn since start, 0 on the first, which starts over along with time
+    frame: u32;
+    // seconds of time since the last frame, 0 while paused with Space
+    // e.g. for moving things at a steady speed however fast frames are drawn
+    delta: f32;
 };
 
 [[group(0), binding(0)]]
@@ -9,18 +48,212 @@ struct Time {
 var<uniform> time: Time;
 
 struct Mouse {
+    // 0..1 across the window, with y going up
     position: vec2<f32>;
+    // in physical pixels from the top left, like `[[builtin(position)]]`, fraction included
+    // times resolution.scale when rendering at a --scale
+    physical: vec2<f32>;
+    // in logical pixels from the top left, physical divided by the window's scale factor
+    logical: vec2<f32>;
+    // seconds since the mouse last moved, clicked or scrolled, e.g. for fading to a screensaver
+    idle_time: f32;
+    // left clicks in quick succession, 1 for a single click, 2 for a double click, and so on
+    // drops back to 0 when no click follows quickly enough
+    click_count: u32;
+    // seconds the left, right and middle buttons have been held down, or 0 while they're up
+    hold_time: vec3<f32>;
+    // bits 0, 1 and 2 are set while the left, right and middle buttons are down
+    buttons: u32;
+    // starts at 1, multiplied by 1.1 per line scrolled up and divided per line down
+    // reset with Z, e.g. for zooming into fractals with `uv / mouse.zoom`
+    zoom: f32;
+    // physical pixels the cursor has travelled inside the window, reset with L
+    // e.g. for trails that change along their length rather than over time
+    path_length: f32;
+    // where the last left click landed, 0..1 like position, e.g. for ripples from each click
+    // with mouse.buttons and click_count, this covers what Shadertoy's iMouse gives
+    click_position: vec2<f32>;
+    // 1 while the cursor is over the window and 0 once it leaves, e.g. for fading in hints
+    // position keeps where the cursor was last seen while it's away
+    hovering: u32;
 };
 
-[[group(1), binding(0)]]
+[[group(0), binding(1)]]
 var<uniform> mouse: Mouse;
 
+// orbit camera, dragged with the left mouse button and zoomed with the scroll wheel
+struct Camera {
+    view_proj: mat4x4<f32>;
+};
+
+[[group(0), binding(2)]]
+var<uniform> camera: Camera;
+
+// the last 64 frame times in seconds, as a ring buffer
+// frame time `i` is `frame_times.deltas[i / 4u][i % 4u]`
+// for example, to scale down work when frames are slow
+struct FrameTimes {
+    deltas: array<vec4<f32>, 16>;
+    // index of the most recent frame time
+    newest: u32;
+};
+
+[[group(0), binding(3)]]
+var<uniform> frame_times: FrameTimes;
+
+// which cell of the --tile grid is being drawn
+// useful for varying a seed or time offset between cells
+struct Tile {
+    // column and row
+    cell: vec2<u32>;
+    // number of columns and rows
+    grid: vec2<u32>;
+    // cell number, counting along rows from the top left
+    index: u32;
+};
+
+[[group(1), binding(0)]]
+var<uniform> tile: Tile;
+
+// for sampling pass inputs and --texture images at lower detail, with --mipmaps
+struct Lod {
+    // from --lod-bias, to pass to textureSampleBias
+    bias: f32;
+    // number of mip levels in pass inputs
+    levels: u32;
+};
+
+[[group(0), binding(4)]]
+var<uniform> lod: Lod;
+
+// --params floats, set with --param, nudged while running with Tab, [ and ], or set from
+// outside, e.g. over OSC with --osc or by MIDI controllers with --midi
+// slot `i` is named by the `i`th of --custom-uniforms, and read with `custom.values[i / 4u][i % 4u]`
+// the array needs one vec4 per four slots, rounded up, so 4 for the default of 16
+// (DEFAULT_PARAMS in src/config.rs), and a shader run with more --params declares more
+struct Custom {
+    values: array<vec4<f32>, 4>;
+};
+
+[[group(0), binding(5)]]
+var<uniform> custom: Custom;
+
+// which graphics API is running the shader, for working around backend quirks
+struct Adapter {
+    // 0 Vulkan, 1 Metal, 2 DX12, 3 OpenGL, 4 DX11, 5 browser WebGPU
+    backend: u32;
+    // widest or tallest texture the GPU can make, in pixels
+    max_texture_dimension: u32;
+};
+
+[[group(0), binding(6)]]
+var<uniform> adapter: Adapter;
+
+// a random number that changes every frame, in the same sequence for the same --seed
+// e.g. `rand(vec4<u32>(pixel, 0u, 0u), rng.value)` for noise that's repeatable between runs
+struct Rng {
+    value: u32;
+};
+
+[[group(0), binding(7)]]
+var<uniform> rng: Rng;
+
+struct Display {
+    // refresh rate of the window's monitor in Hz, 60 if it can't be told, or 0 without a window
+    // e.g. for blinking at a whole fraction of the refresh rate
+    refresh_rate: f32;
+};
+
+[[group(0), binding(8)]]
+var<uniform> display: Display;
+
+struct Window {
+    // top left of the window's contents on the virtual desktop, in physical pixels
+    // several windows can add this to their pixel position to draw one scene across them
+    position: vec2<f32>;
+    // size of the box around every monitor, in physical pixels
+    desktop_size: vec2<f32>;
+};
+
+[[group(0), binding(9)]]
+var<uniform> window: Window;
+
+struct Resolution {
+    // size rendered at in physical pixels, the window's unless --scale shrinks it
+    size: vec2<f32>;
+    // the same as integers, for exact pixel grid maths like `vec2<u32>(uv * size)`
+    res_px: vec2<u32>;
+    // the --scale, which --adaptive-scale changes while running
+    // window positions like mouse.physical times this are in the same pixels as position.xy
+    scale: f32;
+};
+
+[[group(0), binding(10)]]
+var<uniform> resolution: Resolution;
+
+struct Date {
+    // local year, month counting from 0 and day of the month, like Shadertoy's iDate.xyz
+    ymd: vec3<f32>;
+    // local seconds since midnight, with a fraction so second hands can sweep smoothly
+    seconds: f32;
+};
+
+[[group(0), binding(11)]]
+var<uniform> date: Date;
+
+struct Keyboard {
+    // a bit for each key that's held, by winit's key code `k`: bit `k % 32u` of
+    // `pressed[k / 128u][(k / 32u) % 4u]`, like Shadertoy's keyboard texture
+    // 0 to 9 are the number keys 1 to 0, 10 to 35 are A to Z, and 70 to 73 are the arrows
+    // left, up, right and down, so e.g. W is held if `(pressed[0].y & (1u << 0u)) != 0u`
+    pressed: array<vec4<u32>, 2>;
+};
+
+[[group(0), binding(12)]]
+var<uniform> keyboard: Keyboard;
+
+struct Audio {
+    // the --audio file at the current time, all 0 without one
+    // magnitude of 512 frequencies from 0 up to half the sample rate, 0..1 from -80dB to 0dB,
+    // packed four to a vec4, so frequency `i` is `spectrum[i / 4u][i % 4u]`
+    spectrum: array<vec4<f32>, 128>;
+    // the latest 512 samples, oldest first, from -1 to 1, packed the same way
+    waveform: array<vec4<f32>, 128>;
+};
+
+[[group(0), binding(13)]]
+var<uniform> audio: Audio;
+
+// --storage adds a buffer the fragment shader can write, which keeps its contents between frames
+// its size is in bytes, so it's declared as a runtime-sized array, e.g. of particles
+//struct Simulation {
+//    values: array<vec4<f32>>;
+//};
+//[[group(0), binding(16)]]
+//var<storage, read_write> simulation: Simulation;
+// with --compute, it's filled by a compute shader each frame before the passes are drawn, so
+// here it can only be read, as `var<storage, read>`
+// the compute shader's `cs_main` gets the same uniforms, and the buffer to write, like this:
+//[[stage(compute), workgroup_size(64)]]
+//fn cs_main([[builtin(global_invocation_id)]] id: vec3<u32>) {
+//    simulation.values[id.x] = simulation.values[id.x] + vec4<f32>(time.delta);
+//}
+
+// images from --texture go in group 2, after the uniforms
+// the `k`th is at binding `32 + 2k`, with its sampler at `32 + 2k + 1`
+//[[group(2), binding(32)]]
+//var channel0: texture_2d<f32>;
+//[[group(2), binding(33)]]
+//var channel0_sampler: sampler;
+
 // input for the vertex buffer
 struct VertexInput {
     // position relative to parent
     [[location(0)]] position: vec3<f32>;
     // UV, or where on the texture this will be
     [[location(1)]] tex_coords: vec2<f32>;
+    // surface normal, for lighting
+    [[location(2)]] normal: vec3<f32>;
 };
 
 // output of vertex shader
@@ -43,11 +276,27 @@ fn vs_main(
     // `let` variables are immutable and infer their type
     // just passes the data through
     out.tex_coords = model.tex_coords;
-    //out.clip_position = camera.view_proj * model_matrix * vec4<f32>(model.position, 1.0);
+    // for 3D geometry, transform into clip space with the camera
+    //out.clip_position = camera.view_proj * vec4<f32>(model.position, 1.0);
     out.clip_position = vec4<f32>(model.position, 1.0);
     return out;
 }
 
+// used instead of vs_main with --fullscreen-tri
+// one triangle big enough to cover the screen, made from the vertex index without a vertex buffer
+[[stage(vertex)]]
+fn vs_fullscreen(
+    [[builtin(vertex_index)]] index: u32,
+) -> VertexOutput {
+    // (0, 0), (2, 0) and (0, 2), counter-clockwise
+    // the screen is the 0..1 corner, and the rest gets clipped
+    let corner = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.tex_coords = corner;
+    out.clip_position = vec4<f32>(corner * 2.0 - 1.0, 0.0, 1.0);
+    return out;
+}
+
 
 // hash function for u32s
 fn pcg32_hash(input: u32) -> u32 {
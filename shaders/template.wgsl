@@ -0,0 +1,121 @@
+// Starter shader written by --init
+// run it with `shader-party PATH`, then press Enter to reload it after each edit
+
+// the built-in uniforms are all in group 0, at the bindings --uniforms gave them
+// these match the --uniforms this file was made with, so run it with the same ones
+
+struct Time {
+    // milliseconds since start
+    time: u32;
+    // frame number modulo --strobe-period
+    strobe: u32;
+    // whole seconds since start, and the fraction of the current second, for precision
+    seconds: u32;
+    subsecond: f32;
+    // 1 for the first frame after the shader is loaded or reloaded
+    just_reloaded: u32;
+    // 0..1 through the current beat, and beats counted, at --bpm
+    beat_phase: f32;
+    beat: u32;
+    // frames drawn since the last reload, starting at 0
+    frames_since_reload: u32;
+    // frames averaged by --accumulate so far
+    samples: u32;
+    // frames drawn since start, and seconds since the last one, 0 while paused
+    frame: u32;
+    delta: f32;
+};
+
+[[group(0), binding(TIME_BINDING)]]
+var<uniform> time: Time;
+
+struct Mouse {
+    // 0..1 across the window, with y going up
+    position: vec2<f32>;
+    // in physical pixels from the top left, like `[[builtin(position)]]`
+    physical: vec2<f32>;
+    // in logical pixels from the top left
+    logical: vec2<f32>;
+    // seconds since the mouse last moved, clicked or scrolled
+    idle_time: f32;
+    // left clicks in quick succession, 2 for a double click
+    click_count: u32;
+    // seconds the left, right and middle buttons have been held down
+    hold_time: vec3<f32>;
+    // bits 0, 1 and 2 are set while the left, right and middle buttons are down
+    buttons: u32;
+    // starts at 1, multiplied by 1.1 per line scrolled up, reset with Z
+    zoom: f32;
+    // physical pixels the cursor has travelled, reset with L
+    path_length: f32;
+    // where the last left click landed, like position
+    click_position: vec2<f32>;
+    // 1 while the cursor is over the window, 0 once it leaves
+    hovering: u32;
+};
+
+[[group(0), binding(MOUSE_BINDING)]]
+var<uniform> mouse: Mouse;
+
+struct Resolution {
+    // size rendered at in physical pixels, the window's unless --scale shrinks it
+    size: vec2<f32>;
+    res_px: vec2<u32>;
+    scale: f32;
+};
+
+[[group(0), binding(RESOLUTION_BINDING)]]
+var<uniform> resolution: Resolution;
+
+struct VertexInput {
+    [[location(0)]] position: vec3<f32>;
+    [[location(1)]] tex_coords: vec2<f32>;
+    [[location(2)]] normal: vec3<f32>;
+};
+
+struct VertexOutput {
+    [[builtin(position)]] clip_position: vec4<f32>;
+    // 0..1 across the screen, with y pointing up
+    [[location(0)]] tex_coords: vec2<f32>;
+};
+
+// draws the screen quad, and usually doesn't need changing
+[[stage(vertex)]]
+fn vs_main(model: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.tex_coords = model.tex_coords;
+    out.clip_position = vec4<f32>(model.position, 1.0);
+    return out;
+}
+
+// for --fullscreen-tri
+[[stage(vertex)]]
+fn vs_fullscreen([[builtin(vertex_index)]] index: u32) -> VertexOutput {
+    let corner = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.tex_coords = corner;
+    out.clip_position = vec4<f32>(corner * 2.0 - 1.0, 0.0, 1.0);
+    return out;
+}
+
+// seconds since start, wrapped every hour to keep precision
+fn seconds() -> f32 {
+    return f32(time.seconds % 3600u) + time.subsecond;
+}
+
+// runs once per pixel, and returns its colour
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    // stretch the coordinates so circles stay round in any window shape
+    let aspect = resolution.size.x / resolution.size.y;
+    let uv = vec2<f32>(in.tex_coords.x * aspect, in.tex_coords.y);
+
+    // a gradient that drifts over time
+    let colour = 0.5 + 0.5 * cos(seconds() + vec3<f32>(uv, uv.x) + vec3<f32>(0.0, 2.0, 4.0));
+
+    // a glow around the mouse
+    let to_mouse = uv - vec2<f32>(mouse.position.x * aspect, mouse.position.y);
+    let glow = 0.02 / max(dot(to_mouse, to_mouse), 0.001);
+
+    return vec4<f32>(colour + glow * 0.1, 1.0);
+}
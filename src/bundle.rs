@@ -0,0 +1,147 @@
+use std::{
+    env,
+    ffi::OsString,
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+    process,
+};
+use zip::ZipArchive;
+
+// the shader to run, unless the manifest says otherwise
+const DEFAULT_SHADER: &str = "shader.wgsl";
+// command line options for the bundle, read before the real command line
+const MANIFEST: &str = "bundle.args";
+// options taking a file in the bundle, which get resolved against where it's unpacked
+const PATH_OPTIONS: &[&str] = &[
+    "-p",
+    "--path",
+    "--texture",
+    "--texture-array",
+    "--volume",
+    "--video",
+    "--background-image",
+    "--reference",
+    "--layer",
+    "--feedback",
+    "--script",
+];
+
+/// Where a --bundle was unpacked, which is removed when this is dropped
+///
+/// Kept until the program exits, as the shader and its files are reloaded from there.
+#[derive(Debug)]
+pub(crate) struct UnpackedBundle {
+    directory: PathBuf,
+}
+
+impl Drop for UnpackedBundle {
+    fn drop(&mut self) {
+        log::info!("Removing unpacked bundle {}", self.directory.display());
+        if let Err(error) = fs::remove_dir_all(&self.directory) {
+            log::warn!(
+                "Failed removing unpacked bundle {}: {}",
+                self.directory.display(),
+                error
+            );
+        }
+    }
+}
+
+/// Unpack a --bundle zip and the command line options its manifest gives
+///
+/// The archive holds the shader, as `shader.wgsl` unless the manifest passes `--path`,
+/// and any textures, layers and scripts it uses.
+/// `bundle.args` in the archive lists command line options, whitespace separated,
+/// with `#` starting a comment to the end of the line.
+/// Relative paths in it are inside the archive.
+///
+/// Returns the arguments to parse in place of the command line: the manifest's options,
+/// followed by the command line's own, which take priority. Also returns the unpacked files,
+/// to be dropped once they're no longer needed.
+pub(crate) fn bundle_args(bundle: &str) -> (Vec<OsString>, UnpackedBundle) {
+    let unpacked = unpack(bundle)
+        .unwrap_or_else(|error| panic!("Failed unpacking bundle {}: {}", bundle, error));
+    let directory = &unpacked.directory;
+    log::info!("Unpacked bundle {} to {}", bundle, directory.display());
+    let manifest = match fs::read_to_string(directory.join(MANIFEST)) {
+        Ok(manifest) => manifest,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(error) => panic!(
+            "Failed reading {} in bundle {}: {}",
+            MANIFEST, bundle, error
+        ),
+    };
+    let words: Vec<_> = manifest
+        .lines()
+        .map(|line| line.split_once('#').map_or(line, |(code, _)| code))
+        .flat_map(str::split_whitespace)
+        .collect();
+    let mut args = env::args_os().take(1).collect::<Vec<_>>();
+    args.push("--path".into());
+    args.push(directory.join(DEFAULT_SHADER).into());
+    let mut takes_path = false;
+    for word in words {
+        let arg = if takes_path {
+            resolve(directory, word)
+        } else {
+            match word.split_once('=') {
+                Some((option, path)) if PATH_OPTIONS.contains(&option) => {
+                    let mut arg = OsString::from(format!("{}=", option));
+                    arg.push(resolve(directory, path));
+                    arg
+                }
+                _ => word.into(),
+            }
+        };
+        takes_path = PATH_OPTIONS.contains(&word);
+        args.push(arg);
+    }
+    args.extend(env::args_os().skip(1));
+    (args, unpacked)
+}
+
+// a path from the manifest, inside the bundle if it's relative
+fn resolve(directory: &Path, path: &str) -> OsString {
+    if Path::new(path).is_relative() {
+        directory.join(path).into()
+    } else {
+        path.into()
+    }
+}
+
+// extract the archive into a fresh temporary directory, removed again if that fails partway
+fn unpack(bundle: &str) -> Result<UnpackedBundle, String> {
+    let file = File::open(bundle).map_err(|error| error.to_string())?;
+    let mut archive = ZipArchive::new(file).map_err(|error| error.to_string())?;
+    let directory = env::temp_dir().join(format!("shader-party-bundle-{}", process::id()));
+    fs::create_dir_all(&directory).map_err(|error| error.to_string())?;
+    let unpacked = UnpackedBundle { directory };
+    let directory = &unpacked.directory;
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index).map_err(|error| error.to_string())?;
+        // entries with `..` or absolute paths would write outside the directory
+        let name = match entry.enclosed_name() {
+            Some(name) => name.to_owned(),
+            None => {
+                log::warn!(
+                    "Skipping {} in bundle, which points outside it",
+                    entry.name()
+                );
+                continue;
+            }
+        };
+        let path = directory.join(name);
+        if entry.is_dir() {
+            fs::create_dir_all(&path).map_err(|error| error.to_string())?;
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|error| error.to_string())?;
+        }
+        let mut output = File::create(&path).map_err(|error| error.to_string())?;
+        io::copy(&mut entry, &mut output)
+            .map_err(|error| format!("{}: {}", path.display(), error))?;
+    }
+    Ok(unpacked)
+}
@@ -1,8 +1,1311 @@
-use clap::Parser;
+use clap::{ArgGroup, Parser, ValueEnum};
+use serde::{Deserialize, Serialize, Serializer};
+use std::{fs, path::Path, time::Duration};
+use wgpu::{
+    AddressMode, Backends, BlendComponent, BlendFactor, BlendOperation, BlendState, Features,
+    FilterMode, PresentMode,
+};
 
-#[derive(Parser, Debug)]
+use super::examples;
+
+/// Shader to run without a --path, when there's no last one to run
+///
+/// The starter shader in shaders/, which is where the repo keeps its shaders, rather than
+/// beside the source in src/.
+pub(crate) const DEFAULT_PATH: &str = "./shaders/shader.wgsl";
+
+// custom uniform slots without --params
+// shaders/shader.wgsl declares its `Custom` array for this many, one vec4 per four slots,
+// so change its `array<vec4<f32>, 4>` with it
+const DEFAULT_PARAMS: usize = 16;
+
+// serialised in the same kebab-case names as the command line options, for --print-config
+#[derive(Parser, Debug, Serialize)]
 #[clap(author, about, long_about = None)]
+// what --size renders without a window
+#[clap(group(ArgGroup::new("offscreen").args(&["output", "gif"]).multiple(true)))]
+#[serde(rename_all = "kebab-case")]
 pub(crate) struct Config {
-    #[clap(short, long, value_parser, default_value = "./shaders/shader.wgsl")]
+    /// Shader file to run: WGSL, SPIR-V or a GLSL fragment shader, by its `.wgsl`, `.spv`,
+    /// `.frag` or `.glsl` extension. Dropping another shader file on the window switches to it.
+    /// Defaults to the last shader file run, or shaders/shader.wgsl the first time
+    #[clap(short, long, value_parser, default_value = DEFAULT_PATH)]
+    pub path: String,
+    /// Run a built-in example shader instead of a file, see --list-examples
+    #[clap(long, value_parser = parse_example, conflicts_with = "path")]
+    pub example: Option<String>,
+    /// Run a shader packaged in a zip with its textures, as `shader.wgsl` at the top
+    /// A `bundle.args` file in it gives command line options, with paths inside the zip,
+    /// which options given here override
+    #[clap(long, value_parser, conflicts_with = "example")]
+    #[serde(skip)]
+    pub bundle: Option<String>,
+    /// TOML file listing the shader's passes in order, each in its own file, instead of
+    /// splitting one shader file at `//!pass` markers. Each `[[pass]]` has a `shader` path
+    /// relative to the manifest, an optional `output` name, and the `inputs` it reads from
+    /// earlier passes' outputs. The last pass renders to the screen
+    #[clap(long, value_parser, conflicts_with_all = &["example", "bundle"])]
+    pub manifest: Option<String>,
+    /// Print the names of the built-in example shaders and exit
+    #[clap(long, action)]
+    #[serde(skip)]
+    pub list_examples: bool,
+    /// Parse and validate the shader, print any errors with line numbers, then exit
+    /// Doesn't open a window, and exits with 1 if the shader is invalid
+    #[clap(long, action)]
+    #[serde(skip)]
+    pub check: bool,
+    /// Print the settings in effect, with defaults filled in, as TOML and exit
+    #[clap(long, action)]
+    #[serde(skip)]
+    pub print_config: bool,
+    /// Write a starter shader to this path and exit, with uniforms declared at the bindings
+    /// --uniforms gives them. Won't overwrite an existing file
+    #[clap(long, value_parser)]
+    #[serde(skip)]
+    pub init: Option<String>,
+    /// Number of frames before the strobe uniform wraps back to 0
+    #[clap(long, value_parser = clap::value_parser!(u32).range(1..), default_value_t = 1)]
+    pub strobe_period: u32,
+    /// Comma separated uniforms to bind in group 0, at bindings 0, 1, 2... in the order given
+    /// Defaults to all of them, in the order documented in shaders/shader.wgsl
+    #[clap(
+        long,
+        value_parser = parse_uniforms,
+        default_value = "time,mouse,camera,frame-times,lod,custom,adapter,rng,display,window,resolution,date,keyboard,audio"
+    )]
+    pub uniforms: Uniforms,
+    /// Comma separated GPU features to request, e.g. `push-constants,polygon-mode-line`
+    #[clap(long, value_parser = parse_feature, value_delimiter = ',')]
+    #[serde(serialize_with = "serialize_features")]
+    pub features: Vec<Features>,
+    /// Turn off vsync and space frames evenly at this many per second instead, for high frame
+    /// rates without uneven pacing. Falls back to vsync where it can't be turned off
+    #[clap(long, value_parser = parse_fps)]
+    pub pace: Option<f64>,
+    /// How frames reach the screen: `fifo` waits for vsync, `mailbox` replaces frames still
+    /// waiting for it so the frame rate is uncapped without tearing, and `immediate` shows them
+    /// straight away, tearing. Falls back to `fifo` where the surface can't do the others
+    #[clap(long, value_enum, default_value_t = Present::Fifo, conflicts_with = "pace")]
+    pub present_mode: Present,
+    /// Draw at most this many frames per second, sleeping between them, e.g. to save battery.
+    /// Works with any --present-mode, which may cap the rate lower still
+    #[clap(long, value_parser = parse_fps)]
+    pub max_fps: Option<f64>,
+    /// Log every GPU validation error and keep running, instead of stopping at the first one
+    /// Also turns on the Vulkan validation layer in release builds, if it's installed
+    #[clap(long, action)]
+    pub validate: bool,
+    /// Draw with the platform's software renderer, like WARP or llvmpipe, for machines without
+    /// a usable GPU
+    #[clap(long, action)]
+    pub fallback_adapter: bool,
+    /// Graphics API to draw with, for tracking down driver bugs. `all` lets wgpu pick
+    #[clap(long, value_enum, default_value_t = GpuBackend::All)]
+    pub backend: GpuBackend,
+    /// Time each phase of the main loop on the CPU, and write them to this file on exit,
+    /// for opening in `chrome://tracing` or Perfetto
+    #[clap(long, value_parser)]
+    pub profile: Option<String>,
+    /// Render a single frame and then exit
+    #[clap(long, action)]
+    pub once: bool,
+    /// Hide the system cursor over the window, so only the shader shows, e.g. for installations
+    #[clap(long, action)]
+    pub no_cursor: bool,
+    /// Hide the system cursor once the mouse has been still for this many seconds, and show it
+    /// again when it moves
+    #[clap(long, value_parser = parse_seconds, conflicts_with = "no-cursor")]
+    #[serde(serialize_with = "serialize_seconds")]
+    pub hide_cursor_after: Option<Duration>,
+    /// Starting state for the random number uniform, which steps once per frame
+    #[clap(long, value_parser, default_value_t = 0)]
+    pub seed: u32,
+    /// Start the time uniforms this many seconds in, e.g. to skip to part of an animation
+    /// R goes back to this time too, as does reloading with --reset-time-on-reload
+    #[clap(long, value_parser = parse_seconds)]
+    #[serde(serialize_with = "serialize_seconds")]
+    pub start_time: Option<Duration>,
+    /// Wrap the time uniforms back to 0 after this many seconds, to keep them small enough
+    /// for f32 to be precise. The strobe counts frames, so it carries on as normal
+    #[clap(long, value_parser = parse_seconds)]
+    #[serde(serialize_with = "serialize_seconds")]
+    pub time_max: Option<Duration>,
+    /// How fast time plays compared to the clock, e.g. 0.5 for half speed, from 1/64 to 64
+    /// Page Down halves it and Page Up doubles it while running, and Home puts it back
+    #[clap(long, value_parser = parse_time_scale, default_value_t = 1.0)]
+    pub time_scale: f32,
+    /// Tempo of the time uniform's beat and beat_phase, for visuals that pulse with music
+    /// Tapping T on the beat sets the tempo live instead, with or without this
+    #[clap(long, value_parser = parse_bpm)]
+    pub bpm: Option<f64>,
+    /// Seconds into the time uniforms of a downbeat, to line beat_phase 0 up with a track
+    #[clap(long, value_parser, default_value_t = 0.0)]
+    pub beat_offset: f64,
+    /// Restart the animation when the shader is reloaded, instead of keeping it running
+    /// R restarts it without reloading, either way
+    #[clap(long, action, overrides_with = "reload-preserve-time")]
+    pub reset_time_on_reload: bool,
+    /// Keep the animation running when the shader is reloaded, as it does by default
+    /// The last of this and --reset-time-on-reload wins, e.g. to override one in a shell alias
+    #[clap(long, action, overrides_with = "reset-time-on-reload")]
+    pub reload_preserve_time: bool,
+    /// Reload the shader whenever its file, a --layer's, the --feedback or --compute shader,
+    /// or a file any of them `//!include`s is saved, as well as with Enter
+    #[clap(long, action, conflicts_with = "example")]
+    pub watch: bool,
+    /// Cover the screen with one triangle made in the `vs_fullscreen` entry point, instead of a quad
+    #[clap(long, action)]
+    pub fullscreen_tri: bool,
+    /// Vertex entry point of the shader's passes, instead of `vs_main`, or `vs_fullscreen`
+    /// with --fullscreen-tri
+    #[clap(long, value_parser)]
+    pub vertex_entry: Option<String>,
+    /// Fragment entry point of the shader's passes
+    #[clap(long, value_parser, default_value = "fs_main")]
+    pub fragment_entry: String,
+    /// Give the shader's passes a depth buffer, so a vertex stage drawing 3D geometry has
+    /// nearer triangles hide further ones. It's cleared to 1 at the start of each pass, and
+    /// fragments with a smaller depth are kept
+    #[clap(long, action)]
+    pub depth: bool,
+    /// OBJ file to draw the shader's passes on instead of the quad, with its positions, normals
+    /// and texture coordinates in the vertex buffer. Every model in it needs texture coordinates.
+    /// Positions go to `vs_main` as they are, so place them with the camera uniform
+    #[clap(long, value_parser, conflicts_with_all = &["fullscreen-tri", "quad-scale"])]
+    pub mesh: Option<String>,
+    /// Draw the quad, or the --mesh, as separate vertices for each triangle, without an index
+    /// buffer
+    #[clap(long, action)]
+    pub no_indices: bool,
+    /// Shrink the quad to this fraction of the window, centred, with background colour around it
+    /// tex_coords still go 0..1 across it. Change it while running with - and =
+    /// Has no effect with --fullscreen-tri
+    #[clap(long, value_parser = parse_scale, default_value_t = 1.0)]
+    pub quad_scale: f32,
+    /// Average the shader's frames together into a less and less noisy image, for path tracers
+    /// and other renders that converge. The time uniform's samples counts the frames averaged,
+    /// and it starts over when the mouse is used, a custom uniform or texture changes, the
+    /// window resizes or the shader reloads
+    #[clap(long, action, conflicts_with = "background-image")]
+    pub accumulate: bool,
+    /// Antialias the edges of the shader's geometry with this many samples per pixel, 1 or 4,
+    /// the only counts wgpu resolves
+    #[clap(
+        long,
+        value_parser = parse_samples,
+        default_value_t = 1,
+        conflicts_with_all = &["accumulate", "background-image"]
+    )]
+    pub msaa: u32,
+    /// With --msaa, cover as many of each pixel's samples as the shader's alpha says, which
+    /// antialiases alpha-tested cutouts like foliage or text, where the edge isn't geometry's
+    #[clap(long, action)]
+    pub alpha_to_coverage: bool,
+    /// Smooth jagged edges in the shader's output with FXAA
+    #[clap(long, action)]
+    pub fxaa: bool,
+    /// Render the shader at this fraction of the window's resolution, stretched to fill it,
+    /// for heavy shaders. The resolution uniform has the size rendered at, and the scale
+    #[clap(long, value_parser = parse_scale, default_value_t = 1.0)]
+    pub scale: f32,
+    /// Lower --scale while frames take longer than this frame rate allows, and raise it again
+    /// up to --scale when there's time to spare. With vsync, aim below the refresh rate,
+    /// since frames can't come faster than it to show there's room
+    #[clap(long, value_parser = parse_fps)]
+    pub adaptive_scale: Option<f64>,
+    /// Lowest --scale that --adaptive-scale goes down to
+    #[clap(long, value_parser = parse_scale, default_value_t = 0.25)]
+    pub min_scale: f32,
+    /// Go fullscreen at the monitor's native resolution and highest refresh rate, taking over
+    /// the display instead of going through the compositor, for lower latency in demos
+    /// Falls back to a borderless fullscreen window if the monitor lists no video modes
+    #[clap(long, action)]
+    pub exclusive_fullscreen: bool,
+    /// Start in a borderless fullscreen window on the current monitor
+    #[clap(long, action, conflicts_with = "exclusive-fullscreen")]
+    pub fullscreen: bool,
+    /// Starting width of the window in physical pixels, instead of the platform's default
+    #[clap(
+        long,
+        value_parser = clap::value_parser!(u32).range(1..),
+        requires = "height",
+        conflicts_with = "output"
+    )]
+    pub width: Option<u32>,
+    /// Starting height of the window in physical pixels, instead of the platform's default
+    #[clap(
+        long,
+        value_parser = clap::value_parser!(u32).range(1..),
+        requires = "width",
+        conflicts_with = "output"
+    )]
+    pub height: Option<u32>,
+    /// Title of the window
+    #[clap(long, value_parser, default_value = "shader-party")]
+    pub title: String,
+    /// Show the frame rate and frame time in the window title, averaged over the last 60 frames
+    #[clap(long, action)]
+    pub show_fps: bool,
+    /// Dither the final output to hide banding on 8-bit screens
+    #[clap(long, action)]
+    pub dither: bool,
+    /// Render the shader into a grid of ROWSxCOLUMNS cells, e.g. `2x3`
+    #[clap(long, value_parser = parse_tile, default_value = "1x1")]
+    pub tile: Tile,
+    /// Keep the shader at this aspect ratio as W:H, e.g. `16:9`, with bars of background colour
+    #[clap(long, value_parser = parse_aspect)]
+    pub aspect: Option<Aspect>,
+    /// Only draw the shader inside this rectangle of the window, as X,Y,WIDTH,HEIGHT in pixels
+    /// from the top left, leaving the rest background colour. It's clamped to fit the window
+    #[clap(long, value_parser = parse_scissor)]
+    pub scissor: Option<Scissor>,
+    /// How textures repeat when sampled outside 0..1
+    #[clap(long, value_enum, default_value_t = Wrap::Clamp)]
+    pub wrap: Wrap,
+    /// Image for the shader to sample, with an optional `:nearest` or `:linear` filter suffix
+    /// `.ktx2` files are uploaded in their own format, which can be compressed,
+    /// and `.exr` files as half floats, keeping HDR values
+    /// Repeat for more textures, e.g. `--texture sprites.png:nearest --texture photo.jpg`
+    /// Each is reloaded when its file changes, so edits show up as they're saved
+    #[clap(long, value_parser = parse_texture)]
+    pub texture: Vec<TextureInput>,
+    /// Directory of same-sized images to stack into an array texture, one layer per image in
+    /// name order, with an optional filter suffix like --texture. Bound after the --textures
+    #[clap(long, value_parser = parse_texture)]
+    pub texture_array: Vec<TextureInput>,
+    /// 3D texture for volume rendering, from a directory of same-sized slice images stacked
+    /// front to back in name order, or a `.raw` file of one byte per voxel with its size at the
+    /// end of its name, like `smoke_128x128x64.raw`. Optional filter suffix like --texture
+    /// Bound after the --texture-arrays, as `texture_3d<f32>`
+    #[clap(long, value_parser = parse_texture)]
+    pub volume: Vec<TextureInput>,
+    /// Video file to play as a texture, looping, with an optional filter suffix like --texture
+    /// Bound after the --volumes, and kept in step with the time uniform
+    /// Decoded by `ffmpeg`, which has to be installed along with `ffprobe`
+    #[clap(long, value_parser = parse_texture)]
+    pub video: Option<TextureInput>,
+    /// Audio file to play along with the shader, in the audio uniform as a spectrum and a
+    /// waveform of what's playing, for shaders that react to music. Follows the time uniform,
+    /// through pauses, resets and --time-max. WAV, FLAC, Ogg Vorbis or MP3
+    #[clap(long, value_parser)]
+    pub audio: Option<String>,
+    /// Bind a 64x64 tileable blue noise texture after the other channels, for dithering and
+    /// stochastic sampling with evenly spread noise instead of a hash function
+    /// Sampled with nearest filtering and repeating, its red channel is 0..1 ranks
+    #[clap(long, action)]
+    pub blue_noise: bool,
+    /// Image drawn under the shader's output, stretched to fill the window, with an optional
+    /// filter suffix like --texture. The shader's alpha decides how much of it shows through
+    #[clap(long, value_parser = parse_texture)]
+    pub background_image: Option<TextureInput>,
+    /// Image to compare the shader against, toggled with C, with an optional filter suffix
+    /// like --texture. It covers the window right of a split, dragged with the right mouse button
+    #[clap(long, value_parser = parse_texture)]
+    pub reference: Option<TextureInput>,
+    /// Another shader file to render on its own and composite over this one, as
+    /// PATH[:BLEND[:OPACITY]] with a --blend mode and an opacity from 0 to 1, e.g. `stars.wgsl:add:0.5`
+    /// Repeat for more layers, composited in order. Each gets the same uniforms and --textures,
+    /// and can have passes of its own
+    #[clap(long, value_parser = parse_layer)]
+    pub layer: Vec<LayerInput>,
+    /// TOML file listing layers to composite after any from --layer. Each `[[layer]]` has a
+    /// `path` relative to the file, and optionally a `blend` mode like `"add"` and an
+    /// `opacity` from 0 to 1, which default to the same as leaving them off --layer
+    #[clap(long, value_parser)]
+    pub layers: Option<String>,
+    /// Shader file for a feedback buffer, drawn before this one's passes and sampled in
+    /// `[[group(3)]]`, with the texture at binding 0 and a sampler at binding 1. It samples what
+    /// it drew last frame there, and this shader samples what it drew this frame
+    #[clap(long, value_parser)]
+    pub feedback: Option<String>,
+    /// Colour the window is cleared to behind the shader, as `#rrggbb` or linear `r,g,b` from
+    /// 0 to 1, e.g. `#202020` or `0.1,0.2,0.3`. Shows through transparency, around --quad-scale
+    /// and in --aspect's bars. Defaults to a dark blue
+    #[clap(long, value_parser = parse_clear_color)]
+    pub clear_color: Option<ClearColor>,
+    /// How the shader's output is blended over --background-image
+    #[clap(long, value_enum, default_value_t = Blend::Over)]
+    pub blend: Blend,
+    /// Anisotropic filtering level for linear filtered --textures, 1, 2, 4, 8 or 16,
+    /// to keep them sharp when sampled at grazing angles, as on surfaces in 3D scenes
+    /// Works best with textures that have mipmaps, like `.ktx2` files. Ignored if the GPU can't do it
+    #[clap(long, value_parser = parse_anisotropy, default_value_t = 1)]
+    pub anisotropy: u8,
+    /// Generate mipmaps for pass outputs and --texture images, so they can be sampled at lower
+    /// detail. Images that come with their own, like `.ktx2` files, keep them, and texture
+    /// arrays, volumes and compressed or unfilterable formats are left without
+    #[clap(long, action)]
+    pub mipmaps: bool,
+    /// Mip level bias, passed to shaders for use with `textureSampleBias`
+    #[clap(long, value_parser, default_value_t = 0.0)]
+    pub lod_bias: f32,
+    /// Number of custom float uniform slots
+    /// While running, Tab selects the next slot and Shift+Tab the previous one, and [ and ]
+    /// change the selected slot's value by 0.05, or by 0.005 with Shift held
+    #[clap(long, value_parser, default_value_t = DEFAULT_PARAMS)]
+    pub params: usize,
+    /// Starting value for a custom uniform slot, as INDEX=VALUE, e.g. `--param 0=0.5`
+    #[clap(long, value_parser = parse_param)]
+    pub param: Vec<Param>,
+    /// Comma separated names for the custom uniform slots, which external inputs can set
+    #[clap(long, value_parser, value_delimiter = ',')]
+    pub custom_uniforms: Vec<String>,
+    /// Start from a preset of custom uniform values, saved beside the shader in
+    /// `<shader>.presets.toml`. Shift and a number key saves the values to that slot while
+    /// running, and the number key on its own recalls them
+    #[clap(long, value_parser)]
+    pub preset: Option<String>,
+    /// Constant to add to the top of the shader, as NAME=VALUE, e.g. `--define QUALITY=2`
+    /// It's declared as `let NAME: T = VALUE;`, where T is `bool` for true or false, `u32` for
+    /// integers ending in `u`, `i32` for other integers, and `f32` for anything else
+    #[clap(long, value_parser = parse_define)]
+    pub define: Vec<Define>,
+    /// Size in bytes of a storage buffer the shader can read and write, which keeps its contents
+    /// between frames, e.g. for particles. It starts zeroed, and is zeroed again when R restarts
+    /// time, or on reload with --reset-time-on-reload
+    #[clap(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub storage: Option<u64>,
+    /// Compute shader to dispatch before each frame is drawn, with a `cs_main` entry point
+    /// It gets the uniforms in `[[group(0)]]` like the fragment shader, and the --storage buffer
+    /// at binding 16 as `var<storage, read_write>`, to fill for the fragment shader, which can
+    /// then only read it, as `var<storage, read>`
+    #[clap(long, value_parser, requires = "storage")]
+    pub compute: Option<String>,
+    /// How many workgroups the --compute shader is dispatched with, as X[,Y[,Z]]
+    /// Without this, it's enough along X for one invocation per 4 bytes of the --storage buffer,
+    /// at the `workgroup_size` the shader declares
+    #[clap(long, value_parser = parse_workgroups)]
+    pub workgroups: Option<Workgroups>,
+    /// Listen for OSC messages on this UDP port
+    /// A message to `/name` sets the custom uniform called `name` to its first argument
+    #[clap(long, value_parser)]
+    pub osc: Option<u16>,
+    /// Listen for MIDI control changes, which set custom uniforms to 0..1
+    #[clap(long, action)]
+    pub midi: bool,
+    /// Comma separated CC=SLOT pairs, mapping MIDI controllers to custom uniform slots, e.g. `74=0,71=1`
+    /// Without this, each controller sets the slot with the same number, if there is one
+    #[clap(long, value_parser = parse_midi_mapping, value_delimiter = ',')]
+    pub midi_map: Vec<MidiMapping>,
+    /// Rhai script run every frame, which can read `time`, `frame`, `mouse_x` and `mouse_y`,
+    /// and set custom uniforms with `set_param(slot, value)`, by number or by name
+    /// Reloading the shader reloads the script too
+    #[clap(long, value_parser)]
+    pub script: Option<String>,
+    /// Write every frame to this file as raw headerless pixels, or to stdout with `-`
+    /// Pixels are `rgba8`, or `rgba16f` with a float surface, rows top to bottom with no padding
+    /// e.g. `--raw - | ffmpeg -f rawvideo -pixel_format rgba -video_size WxH -i - out.mp4`
+    #[clap(long, value_parser)]
+    pub raw: Option<String>,
+    /// Make a named pipe at this path and write raw frames to it like --raw, for other programs
+    /// to use as a video source. Waits for a reader to open it before starting. Unix only
+    #[clap(long, value_parser, conflicts_with = "raw")]
+    pub pipe: Option<String>,
+    /// Show a live preview of the shader as the window's icon, updated twice a second
+    /// Needs an 8-bit surface format
+    #[clap(long, action)]
+    pub live_icon: bool,
+    /// Record a looping GIF of --duration seconds from --start-time to this file, then exit
+    /// Frames are spaced evenly in shader time rather than real time, so slow shaders loop smoothly
+    /// With --size it's recorded without opening a window
+    #[clap(long, value_parser, requires = "duration")]
+    pub gif: Option<String>,
+    /// Write what produced each frame captured by --raw, --pipe or --gif beside it, in
+    /// `<capture>.metadata.jsonl`: a JSON object per line with the shader, its git blob hash,
+    /// the frame number, time, mouse position and resolution. Not for --raw to stdout
+    #[clap(long, action)]
+    pub capture_metadata: bool,
+    /// Seconds of animation to record with --gif
+    #[clap(long, value_parser = parse_seconds, requires = "gif")]
+    #[serde(serialize_with = "serialize_seconds")]
+    pub duration: Option<Duration>,
+    /// Frames per second to record with --gif. GIFs time frames in hundredths of a second,
+    /// and many viewers slow down anything faster than 50
+    #[clap(long, value_parser = parse_fps, default_value_t = 30.0)]
+    pub fps: f64,
+    /// Render one frame to this PNG without opening a window, then exit
+    /// Exits with 1 if the shader fails to load or the file can't be written
+    #[clap(
+        long,
+        value_parser,
+        conflicts_with_all = &["watch", "raw", "pipe", "gif", "live-icon"]
+    )]
+    pub output: Option<String>,
+    /// Seconds into the time uniforms to render --output at, instead of 0 or --start-time
+    #[clap(long, value_parser = parse_at, requires = "output")]
+    #[serde(serialize_with = "serialize_seconds")]
+    pub at: Option<Duration>,
+    /// Size to render --output or --gif at without a window, as WxH, instead of 1280x720
+    #[clap(long, value_parser = parse_size, requires = "offscreen")]
+    pub size: Option<Size>,
+    /// How the colours of F12 screenshots and --output PNGs are encoded: `linear` ones are
+    /// encoded to sRGB for the PNG, `srgb` ones are saved as they are, and `auto` goes by the
+    /// frame's format, where float formats hold linear colours and 8-bit ones hold what's shown
+    #[clap(long, value_enum, default_value_t = Colorspace::Auto)]
+    pub screenshot_colorspace: Colorspace,
+}
+
+/// A built-in uniform that can share group 0, in its default binding order
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum UniformName {
+    Time,
+    Mouse,
+    Camera,
+    FrameTimes,
+    Lod,
+    Custom,
+    Adapter,
+    Rng,
+    Display,
+    Window,
+    Resolution,
+    Date,
+    Keyboard,
+    Audio,
+}
+
+/// The uniforms from --uniforms, each listed once, in binding order
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct Uniforms(Vec<UniformName>);
+
+impl std::ops::Deref for Uniforms {
+    type Target = [UniformName];
+
+    fn deref(&self) -> &[UniformName] {
+        &self.0
+    }
+}
+
+// a uniform can only have one binding
+fn parse_uniforms(uniforms: &str) -> Result<Uniforms, String> {
+    let mut names = Vec::new();
+    for text in uniforms.split(',').map(str::trim) {
+        let name = UniformName::from_str(text, false)?;
+        if names.contains(&name) {
+            return Err(format!(
+                "`{}` is listed more than once, but each uniform can only have one binding",
+                text
+            ));
+        }
+        names.push(name);
+    }
+    Ok(Uniforms(names))
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum Wrap {
+    Clamp,
+    Repeat,
+    Mirror,
+}
+
+impl Wrap {
+    pub(crate) fn address_mode(self) -> AddressMode {
+        match self {
+            Wrap::Clamp => AddressMode::ClampToEdge,
+            Wrap::Repeat => AddressMode::Repeat,
+            Wrap::Mirror => AddressMode::MirrorRepeat,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum Present {
+    Fifo,
+    Mailbox,
+    Immediate,
+}
+
+impl Present {
+    pub(crate) fn present_mode(self) -> PresentMode {
+        match self {
+            Present::Fifo => PresentMode::Fifo,
+            Present::Mailbox => PresentMode::Mailbox,
+            Present::Immediate => PresentMode::Immediate,
+        }
+    }
+}
+
+/// How a captured frame's colours are encoded, for saving them to sRGB PNGs
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum Colorspace {
+    Auto,
+    Srgb,
+    Linear,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum GpuBackend {
+    Vulkan,
+    Dx12,
+    Metal,
+    Gl,
+    All,
+}
+
+impl GpuBackend {
+    pub(crate) fn backends(self) -> Backends {
+        match self {
+            GpuBackend::Vulkan => Backends::VULKAN,
+            GpuBackend::Dx12 => Backends::DX12,
+            GpuBackend::Metal => Backends::METAL,
+            GpuBackend::Gl => Backends::GL,
+            GpuBackend::All => Backends::all(),
+        }
+    }
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            GpuBackend::Vulkan => "Vulkan",
+            GpuBackend::Dx12 => "DX12",
+            GpuBackend::Metal => "Metal",
+            GpuBackend::Gl => "OpenGL",
+            GpuBackend::All => "any",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum Blend {
+    // shader colour weighted by its alpha, the background showing through the rest
+    Over,
+    // shader colour weighted by its alpha, added to the background
+    Add,
+    // background multiplied by the shader colour, which can only darken it
+    Multiply,
+    // the inverse of multiplying the inverses, which can only lighten it
+    Screen,
+}
+
+impl Blend {
+    pub(crate) fn blend_state(self) -> BlendState {
+        let colour = match self {
+            Blend::Over => return BlendState::ALPHA_BLENDING,
+            Blend::Add => BlendComponent {
+                src_factor: BlendFactor::SrcAlpha,
+                dst_factor: BlendFactor::One,
+                operation: BlendOperation::Add,
+            },
+            Blend::Multiply => BlendComponent {
+                src_factor: BlendFactor::Dst,
+                dst_factor: BlendFactor::Zero,
+                operation: BlendOperation::Add,
+            },
+            Blend::Screen => BlendComponent {
+                src_factor: BlendFactor::One,
+                dst_factor: BlendFactor::OneMinusSrc,
+                operation: BlendOperation::Add,
+            },
+        };
+        BlendState {
+            color: colour,
+            // the surface is opaque, so alpha doesn't matter past blending
+            alpha: BlendComponent::OVER,
+        }
+    }
+}
+
+impl Config {
+    // add the layers from the --layers file after the ones from --layer, with their paths made
+    // relative to the working directory instead of the file
+    // the file is taken out, so --print-config lists its layers once, as --layer
+    pub(crate) fn load_layers(&mut self) -> Result<(), String> {
+        let path = match self.layers.take() {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let text = fs::read_to_string(&path)
+            .map_err(|error| format!("couldn't read layers {}: {}", path, error))?;
+        let file: LayerFile = toml::from_str(&text)
+            .map_err(|error| format!("couldn't parse layers {}: {}", path, error))?;
+        let directory = Path::new(&path).parent().unwrap_or_else(|| Path::new(""));
+        for mut layer in file.layer {
+            if !(0.0..=1.0).contains(&layer.opacity) {
+                return Err(format!(
+                    "layers {}: {} has opacity {}, which isn't between 0 and 1",
+                    path, layer.path, layer.opacity
+                ));
+            }
+            layer.path = directory.join(&layer.path).to_string_lossy().into_owned();
+            self.layer.push(layer);
+        }
+        Ok(())
+    }
+
+    // every setting, including defaults, as TOML
+    pub(crate) fn to_toml(&self) -> String {
+        // via a Value, which puts plain values before tables like TOML needs
+        let value = toml::Value::try_from(self).expect("Config should convert to TOML");
+        toml::to_string(&value).expect("Config should serialise as TOML")
+    }
+}
+
+fn parse_example(name: &str) -> Result<String, String> {
+    match examples::source(name) {
+        Some(_) => Ok(name.to_owned()),
+        None => Err(format!(
+            "no example called `{}`, try one of: {}",
+            name,
+            examples::names().collect::<Vec<_>>().join(", ")
+        )),
+    }
+}
+
+// in seconds, like the command line takes them
+fn serialize_seconds<S: Serializer>(
+    duration: &Option<Duration>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match duration {
+        Some(duration) => serializer.serialize_some(&duration.as_secs_f64()),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn parse_fps(fps: &str) -> Result<f64, String> {
+    match fps.trim().parse::<f64>() {
+        Ok(fps) if fps.is_finite() && fps > 0.0 => Ok(fps),
+        Ok(_) => Err("expected a positive number of frames per second".to_owned()),
+        Err(error) => Err(format!("{}", error)),
+    }
+}
+
+fn parse_bpm(bpm: &str) -> Result<f64, String> {
+    match bpm.trim().parse::<f64>() {
+        Ok(bpm) if bpm.is_finite() && bpm > 0.0 => Ok(bpm),
+        Ok(_) => Err("expected a positive number of beats per minute".to_owned()),
+        Err(error) => Err(format!("{}", error)),
+    }
+}
+
+fn parse_time_scale(scale: &str) -> Result<f32, String> {
+    match scale.trim().parse::<f32>() {
+        Ok(scale) if (1.0 / 64.0..=64.0).contains(&scale) => Ok(scale),
+        Ok(_) => Err("expected a speed from 1/64 to 64".to_owned()),
+        Err(error) => Err(format!("{}", error)),
+    }
+}
+
+fn parse_scale(scale: &str) -> Result<f32, String> {
+    match scale.trim().parse::<f32>() {
+        Ok(scale) if scale > 0.0 && scale <= 1.0 => Ok(scale),
+        Ok(_) => Err("expected a fraction of the window above 0, up to 1".to_owned()),
+        Err(error) => Err(format!("{}", error)),
+    }
+}
+
+fn parse_samples(samples: &str) -> Result<u32, String> {
+    match samples.trim().parse::<u32>() {
+        Ok(samples @ (1 | 4)) => Ok(samples),
+        Ok(_) => Err("expected 1 or 4 samples".to_owned()),
+        Err(error) => Err(format!("{}", error)),
+    }
+}
+
+fn parse_seconds(seconds: &str) -> Result<Duration, String> {
+    match seconds.trim().parse::<f64>() {
+        Ok(seconds) if seconds.is_finite() && seconds >= 0.001 => {
+            Duration::try_from_secs_f64(seconds).map_err(|error| error.to_string())
+        }
+        Ok(_) => Err("expected at least a millisecond".to_owned()),
+        Err(error) => Err(format!("{}", error)),
+    }
+}
+
+// any time from 0 on, to the nanosecond, unlike lengths of time
+fn parse_at(seconds: &str) -> Result<Duration, String> {
+    match seconds.trim().parse::<f64>() {
+        Ok(seconds) if seconds.is_finite() && seconds >= 0.0 => {
+            Duration::try_from_secs_f64(seconds).map_err(|error| error.to_string())
+        }
+        Ok(_) => Err("expected a number of seconds from 0 up".to_owned()),
+        Err(error) => Err(format!("{}", error)),
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct TextureInput {
+    pub path: String,
+    pub filter: Filter,
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum Filter {
+    // blocky, for pixel art
+    Nearest,
+    // smooth, for photos
+    Linear,
+}
+
+impl Filter {
+    pub(crate) fn filter_mode(self) -> FilterMode {
+        match self {
+            Filter::Nearest => FilterMode::Nearest,
+            Filter::Linear => FilterMode::Linear,
+        }
+    }
+}
+
+fn parse_anisotropy(level: &str) -> Result<u8, String> {
+    match level.parse() {
+        Ok(level @ (1 | 2 | 4 | 8 | 16)) => Ok(level),
+        _ => Err("expected 1, 2, 4, 8 or 16".to_owned()),
+    }
+}
+
+fn parse_texture(texture: &str) -> Result<TextureInput, String> {
+    // anything else after a colon is part of the path, like a Windows drive letter
+    let (path, filter) = match texture.rsplit_once(':') {
+        Some((path, "nearest")) => (path, Filter::Nearest),
+        Some((path, "linear")) => (path, Filter::Linear),
+        _ => (texture, Filter::Linear),
+    };
+    if path.is_empty() {
+        return Err("expected a path to an image".to_owned());
+    }
+    Ok(TextureInput {
+        path: path.to_owned(),
+        filter,
+    })
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct LayerInput {
     pub path: String,
+    #[serde(default = "default_layer_blend")]
+    pub blend: Blend,
+    #[serde(default = "default_layer_opacity")]
+    pub opacity: f32,
+}
+
+fn default_layer_blend() -> Blend {
+    Blend::Over
+}
+
+fn default_layer_opacity() -> f32 {
+    1.0
+}
+
+/// The layers listed in a --layers file
+#[derive(Debug, Deserialize)]
+struct LayerFile {
+    #[serde(default)]
+    layer: Vec<LayerInput>,
+}
+
+fn parse_layer(layer: &str) -> Result<LayerInput, String> {
+    // suffixes are only taken off when they parse, so colons can still be part of the path
+    let (path, opacity) = match layer.rsplit_once(':') {
+        Some((path, opacity)) => match opacity.parse::<f32>() {
+            Ok(opacity) => (path, opacity),
+            Err(_) => (layer, 1.0),
+        },
+        None => (layer, 1.0),
+    };
+    if !(0.0..=1.0).contains(&opacity) {
+        return Err(format!("opacity {} isn't between 0 and 1", opacity));
+    }
+    let (path, blend) = match path.rsplit_once(':') {
+        Some((rest, blend)) => match Blend::from_str(blend, true) {
+            Ok(blend) => (rest, blend),
+            Err(_) => (path, Blend::Over),
+        },
+        None => (path, Blend::Over),
+    };
+    if path.is_empty() {
+        return Err("expected a path to a shader".to_owned());
+    }
+    Ok(LayerInput {
+        path: path.to_owned(),
+        blend,
+        opacity,
+    })
+}
+
+/// A linear colour to clear to, from --clear-color
+#[derive(Clone, Copy, Debug, Serialize)]
+pub(crate) struct ClearColor {
+    pub r: f64,
+    pub g: f64,
+    pub b: f64,
+}
+
+fn parse_clear_color(colour: &str) -> Result<ClearColor, String> {
+    let colour = colour.trim();
+    let channels: Vec<f64> = if let Some(hex) = colour.strip_prefix('#') {
+        if hex.len() != 6 || !hex.is_ascii() {
+            return Err("expected six hex digits after #, e.g. #1a334d".to_owned());
+        }
+        (0..3)
+            .map(|index| {
+                u8::from_str_radix(&hex[index * 2..index * 2 + 2], 16)
+                    .map(|channel| srgb_to_linear(channel as f64 / 255.0))
+                    .map_err(|error| format!("{}", error))
+            })
+            .collect::<Result<_, _>>()?
+    } else {
+        colour
+            .split(',')
+            .map(|channel| match channel.trim().parse::<f64>() {
+                Ok(channel) if (0.0..=1.0).contains(&channel) => Ok(channel),
+                Ok(_) => Err("channels go from 0 to 1".to_owned()),
+                Err(error) => Err(format!("{}", error)),
+            })
+            .collect::<Result<_, _>>()?
+    };
+    match channels[..] {
+        [r, g, b] => Ok(ClearColor { r, g, b }),
+        _ => Err("expected #rrggbb or r,g,b".to_owned()),
+    }
+}
+
+// hex colours are written in sRGB, like on the web, but the clear colour is linear
+fn srgb_to_linear(channel: f64) -> f64 {
+    if channel <= 0.04045 {
+        channel / 12.92
+    } else {
+        ((channel + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub(crate) struct Tile {
+    pub rows: u32,
+    pub columns: u32,
+}
+
+fn parse_tile(tile: &str) -> Result<Tile, String> {
+    let (rows, columns) = tile
+        .split_once('x')
+        .ok_or("expected ROWSxCOLUMNS, e.g. 2x3")?;
+    let parse = |count: &str| match count.trim().parse() {
+        Ok(0) => Err("grid needs at least one row and column".to_owned()),
+        Ok(count) => Ok(count),
+        Err(error) => Err(format!("{}", error)),
+    };
+    Ok(Tile {
+        rows: parse(rows)?,
+        columns: parse(columns)?,
+    })
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub(crate) struct Aspect {
+    pub width: u32,
+    pub height: u32,
+}
+
+fn parse_aspect(aspect: &str) -> Result<Aspect, String> {
+    let (width, height) = aspect.split_once(':').ok_or("expected W:H, e.g. 16:9")?;
+    let parse = |side: &str| match side.trim().parse() {
+        Ok(0) => Err("aspect ratio sides must be nonzero".to_owned()),
+        Ok(side) => Ok(side),
+        Err(error) => Err(format!("{}", error)),
+    };
+    Ok(Aspect {
+        width: parse(width)?,
+        height: parse(height)?,
+    })
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub(crate) struct Size {
+    pub width: u32,
+    pub height: u32,
+}
+
+fn parse_size(size: &str) -> Result<Size, String> {
+    let (width, height) = size.split_once('x').ok_or("expected WxH, e.g. 1920x1080")?;
+    let parse = |side: &str| match side.trim().parse() {
+        Ok(0) => Err("size must be nonzero".to_owned()),
+        Ok(side) => Ok(side),
+        Err(error) => Err(format!("{}", error)),
+    };
+    Ok(Size {
+        width: parse(width)?,
+        height: parse(height)?,
+    })
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub(crate) struct Scissor {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub(crate) struct Workgroups {
+    pub x: u32,
+    pub y: u32,
+    pub z: u32,
+}
+
+fn parse_workgroups(workgroups: &str) -> Result<Workgroups, String> {
+    let counts = workgroups
+        .split(',')
+        .map(|count| count.trim().parse().map_err(|error| format!("{}", error)))
+        .collect::<Result<Vec<u32>, _>>()?;
+    if counts.contains(&0) {
+        return Err("workgroup counts must be nonzero".to_owned());
+    }
+    match counts[..] {
+        [x] => Ok(Workgroups { x, y: 1, z: 1 }),
+        [x, y] => Ok(Workgroups { x, y, z: 1 }),
+        [x, y, z] => Ok(Workgroups { x, y, z }),
+        _ => Err("expected X[,Y[,Z]], e.g. 64,1,1".to_owned()),
+    }
+}
+
+fn parse_scissor(scissor: &str) -> Result<Scissor, String> {
+    let numbers = scissor
+        .split(',')
+        .map(|number| number.trim().parse().map_err(|error| format!("{}", error)))
+        .collect::<Result<Vec<u32>, _>>()?;
+    match numbers[..] {
+        [_, _, 0, _] | [_, _, _, 0] => Err("scissor rectangle can't be empty".to_owned()),
+        [x, y, width, height] => Ok(Scissor {
+            x,
+            y,
+            width,
+            height,
+        }),
+        _ => Err("expected X,Y,WIDTH,HEIGHT, e.g. 0,0,256,256".to_owned()),
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub(crate) struct MidiMapping {
+    pub controller: u8,
+    pub slot: usize,
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub(crate) struct Param {
+    pub index: usize,
+    pub value: f32,
+}
+
+fn parse_param(param: &str) -> Result<Param, String> {
+    let (index, value) = param
+        .split_once('=')
+        .ok_or("expected INDEX=VALUE, e.g. 0=0.5")?;
+    Ok(Param {
+        index: index.trim().parse().map_err(|error| format!("{}", error))?,
+        value: value.trim().parse().map_err(|error| format!("{}", error))?,
+    })
+}
+
+/// A module-scope constant from --define, which is written in WGSL as `let`
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct Define {
+    pub name: String,
+    pub ty: &'static str,
+    // as a WGSL literal of that type
+    pub value: String,
+}
+
+impl Define {
+    pub(crate) fn declaration(&self) -> String {
+        format!("let {}: {} = {};", self.name, self.ty, self.value)
+    }
+}
+
+fn parse_define(define: &str) -> Result<Define, String> {
+    let (name, value) = define
+        .split_once('=')
+        .ok_or("expected NAME=VALUE, e.g. QUALITY=2")?;
+    let (name, value) = (name.trim(), value.trim());
+    let mut chars = name.chars();
+    let is_identifier = chars
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !is_identifier {
+        return Err(format!("`{}` isn't a valid WGSL name", name));
+    }
+    let (ty, value) = if value == "true" || value == "false" {
+        ("bool", value.to_owned())
+    } else if value
+        .strip_suffix('u')
+        .is_some_and(|n| n.parse::<u32>().is_ok())
+    {
+        ("u32", value.to_owned())
+    } else if value.parse::<i32>().is_ok() {
+        ("i32", value.to_owned())
+    } else if value.parse::<f32>().is_ok_and(f32::is_finite) {
+        // WGSL floats need a decimal point or exponent
+        if value.contains(['.', 'e', 'E']) {
+            ("f32", value.to_owned())
+        } else {
+            ("f32", format!("{}.0", value))
+        }
+    } else {
+        return Err(format!(
+            "`{}` isn't a bool, integer or float, e.g. true, 2, 2u or 0.5",
+            value
+        ));
+    };
+    let define = Define {
+        name: name.to_owned(),
+        ty,
+        value,
+    };
+    // catches anything the checks above let through, like a name that's a keyword
+    naga::front::wgsl::parse_str(&define.declaration()).map_err(|error| {
+        format!(
+            "`{}` isn't valid WGSL: {}",
+            define.declaration(),
+            error.emit_to_string(&define.declaration()).trim()
+        )
+    })?;
+    Ok(define)
+}
+
+fn parse_midi_mapping(mapping: &str) -> Result<MidiMapping, String> {
+    let (controller, slot) = mapping
+        .split_once('=')
+        .ok_or("expected CC=SLOT, e.g. 74=0")?;
+    let controller = match controller.trim().parse() {
+        Ok(controller) if controller < 128 => controller,
+        Ok(_) => return Err("MIDI controller numbers go up to 127".to_owned()),
+        Err(error) => return Err(format!("{}", error)),
+    };
+    let slot = slot.trim().parse().map_err(|error| format!("{}", error))?;
+    Ok(MidiMapping { controller, slot })
+}
+
+// command line names for the device features a shader might need
+const FEATURE_NAMES: &[(&str, Features)] = &[
+    ("depth-clip-control", Features::DEPTH_CLIP_CONTROL),
+    ("texture-compression-bc", Features::TEXTURE_COMPRESSION_BC),
+    ("indirect-first-instance", Features::INDIRECT_FIRST_INSTANCE),
+    ("timestamp-query", Features::TIMESTAMP_QUERY),
+    (
+        "pipeline-statistics-query",
+        Features::PIPELINE_STATISTICS_QUERY,
+    ),
+    (
+        "mappable-primary-buffers",
+        Features::MAPPABLE_PRIMARY_BUFFERS,
+    ),
+    ("texture-binding-array", Features::TEXTURE_BINDING_ARRAY),
+    ("buffer-binding-array", Features::BUFFER_BINDING_ARRAY),
+    (
+        "storage-resource-binding-array",
+        Features::STORAGE_RESOURCE_BINDING_ARRAY,
+    ),
+    ("multi-draw-indirect", Features::MULTI_DRAW_INDIRECT),
+    (
+        "multi-draw-indirect-count",
+        Features::MULTI_DRAW_INDIRECT_COUNT,
+    ),
+    ("push-constants", Features::PUSH_CONSTANTS),
+    (
+        "address-mode-clamp-to-border",
+        Features::ADDRESS_MODE_CLAMP_TO_BORDER,
+    ),
+    ("polygon-mode-line", Features::POLYGON_MODE_LINE),
+    ("polygon-mode-point", Features::POLYGON_MODE_POINT),
+    (
+        "texture-compression-etc2",
+        Features::TEXTURE_COMPRESSION_ETC2,
+    ),
+    (
+        "texture-compression-astc-ldr",
+        Features::TEXTURE_COMPRESSION_ASTC_LDR,
+    ),
+    (
+        "texture-adapter-specific-format-features",
+        Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES,
+    ),
+    ("shader-float64", Features::SHADER_FLOAT64),
+    ("vertex-attribute-64bit", Features::VERTEX_ATTRIBUTE_64BIT),
+    (
+        "conservative-rasterization",
+        Features::CONSERVATIVE_RASTERIZATION,
+    ),
+    ("vertex-writable-storage", Features::VERTEX_WRITABLE_STORAGE),
+    ("clear-commands", Features::CLEAR_COMMANDS),
+    (
+        "spirv-shader-passthrough",
+        Features::SPIRV_SHADER_PASSTHROUGH,
+    ),
+    ("shader-primitive-index", Features::SHADER_PRIMITIVE_INDEX),
+    ("multiview", Features::MULTIVIEW),
+    (
+        "texture-format-16bit-norm",
+        Features::TEXTURE_FORMAT_16BIT_NORM,
+    ),
+];
+
+fn parse_feature(name: &str) -> Result<Features, String> {
+    FEATURE_NAMES
+        .iter()
+        .find(|(feature_name, _)| *feature_name == name)
+        .map(|&(_, feature)| feature)
+        .ok_or_else(|| {
+            let known: Vec<_> = FEATURE_NAMES.iter().map(|(name, _)| *name).collect();
+            format!("unknown feature, expected one of: {}", known.join(", "))
+        })
+}
+
+// by their command line names
+fn serialize_features<S: Serializer>(
+    features: &[Features],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(features.iter().map(|&feature| feature_name(feature)))
+}
+
+// name of a single feature flag, for error messages
+pub(crate) fn feature_name(feature: Features) -> String {
+    FEATURE_NAMES
+        .iter()
+        .find(|&&(_, known)| known == feature)
+        .map_or_else(|| format!("{:?}", feature), |(name, _)| (*name).to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    // catches clap attributes naming arguments that don't exist, like a misspelt conflicts_with
+    #[test]
+    fn arguments_are_consistent() {
+        Config::command().debug_assert();
+    }
+
+    #[test]
+    fn seconds_are_at_least_a_millisecond() {
+        assert_eq!(parse_seconds("1.5"), Ok(Duration::from_millis(1500)));
+        assert_eq!(parse_seconds(" 0.001 "), Ok(Duration::from_millis(1)));
+        assert!(parse_seconds("0").is_err());
+        assert!(parse_seconds("-1").is_err());
+        assert!(parse_seconds("inf").is_err());
+        assert!(parse_seconds("soon").is_err());
+        // too long for a Duration, rather than a panic
+        assert!(parse_seconds("1e20").is_err());
+    }
+
+    #[test]
+    fn at_takes_any_time_from_zero() {
+        assert_eq!(parse_at("0"), Ok(Duration::ZERO));
+        assert_eq!(parse_at("2.5"), Ok(Duration::from_millis(2500)));
+        assert!(parse_at("-0.5").is_err());
+        assert!(parse_at("NaN").is_err());
+        assert!(parse_at("1e20").is_err());
+    }
+
+    #[test]
+    fn samples_are_ones_wgpu_resolves() {
+        assert_eq!(parse_samples("1"), Ok(1));
+        assert_eq!(parse_samples("4"), Ok(4));
+        assert!(parse_samples("2").is_err());
+        assert!(parse_samples("8").is_err());
+        assert!(parse_samples("four").is_err());
+    }
+
+    #[test]
+    fn workgroups_default_to_one_on_missing_axes() {
+        let Workgroups { x, y, z } = parse_workgroups("64").expect("one axis");
+        assert_eq!((x, y, z), (64, 1, 1));
+        let Workgroups { x, y, z } = parse_workgroups("8, 8").expect("two axes");
+        assert_eq!((x, y, z), (8, 8, 1));
+        assert!(parse_workgroups("8,0,1").is_err());
+        assert!(parse_workgroups("1,1,1,1").is_err());
+    }
+
+    #[test]
+    fn sizes_are_nonzero() {
+        let Size { width, height } = parse_size("1920x1080").expect("it's a size");
+        assert_eq!((width, height), (1920, 1080));
+        assert!(parse_size("0x1080").is_err());
+        assert!(parse_size("1920").is_err());
+        let Tile { rows, columns } = parse_tile("2x3").expect("it's a grid");
+        assert_eq!((rows, columns), (2, 3));
+        assert!(parse_tile("2x0").is_err());
+        let Aspect { width, height } = parse_aspect("16:9").expect("it's a ratio");
+        assert_eq!((width, height), (16, 9));
+        assert!(parse_aspect("16x9").is_err());
+        assert!(parse_scissor("0,0,0,10").is_err());
+        assert!(parse_scissor("0,0,10").is_err());
+    }
+
+    #[test]
+    fn clear_colours_are_linear() {
+        let ClearColor { r, g, b } = parse_clear_color("#ff0000").expect("hex parses");
+        assert_eq!((r, g, b), (1.0, 0.0, 0.0));
+        // sRGB's middle grey is darker in linear terms
+        let ClearColor { r, .. } = parse_clear_color("#808080").expect("hex parses");
+        assert!((r - 0.2158).abs() < 1e-3, "{}", r);
+        let ClearColor { r, g, b } = parse_clear_color("0.5, 0.25, 1").expect("channels parse");
+        assert_eq!((r, g, b), (0.5, 0.25, 1.0));
+        assert!(parse_clear_color("#fff").is_err());
+        assert!(parse_clear_color("0.5,2,0").is_err());
+        assert!(parse_clear_color("0.5,0.5").is_err());
+    }
+
+    #[test]
+    fn suffixes_are_only_taken_off_paths_when_they_parse() {
+        let texture = parse_texture("noise.png:nearest").expect("it's a texture");
+        assert_eq!(texture.path, "noise.png");
+        assert!(matches!(texture.filter, Filter::Nearest));
+        let texture = parse_texture(r"C:\noise.png").expect("it's a texture");
+        assert_eq!(texture.path, r"C:\noise.png");
+        assert!(matches!(texture.filter, Filter::Linear));
+        let layer = parse_layer("glow.wgsl:add:0.5").expect("it's a layer");
+        assert_eq!(layer.path, "glow.wgsl");
+        assert!(matches!(layer.blend, Blend::Add));
+        assert_eq!(layer.opacity, 0.5);
+        let layer = parse_layer(r"C:\glow.wgsl").expect("it's a layer");
+        assert_eq!(layer.path, r"C:\glow.wgsl");
+        assert!(matches!(layer.blend, Blend::Over));
+        assert!(parse_layer("glow.wgsl:1.5").is_err());
+    }
+
+    #[test]
+    fn defines_are_typed_by_their_values() {
+        let declaration = |define: &str| parse_define(define).map(|define| define.declaration());
+        assert_eq!(
+            declaration("FANCY=true"),
+            Ok("let FANCY: bool = true;".to_owned())
+        );
+        assert_eq!(
+            declaration("STEPS=2u"),
+            Ok("let STEPS: u32 = 2u;".to_owned())
+        );
+        assert_eq!(declaration("COUNT=3"), Ok("let COUNT: i32 = 3;".to_owned()));
+        assert_eq!(
+            declaration("GAIN=0.5"),
+            Ok("let GAIN: f32 = 0.5;".to_owned())
+        );
+        // too big for an i32, and WGSL floats need a decimal point
+        assert_eq!(
+            declaration("BIG=3000000000"),
+            Ok("let BIG: f32 = 3000000000.0;".to_owned())
+        );
+        assert!(parse_define("2FAST=1").is_err());
+        assert!(parse_define("NAME=blue").is_err());
+        assert!(parse_define("NAME").is_err());
+    }
+
+    #[test]
+    fn midi_controllers_go_up_to_127() {
+        let MidiMapping { controller, slot } = parse_midi_mapping("74=0").expect("it maps");
+        assert_eq!((controller, slot), (74, 0));
+        assert!(parse_midi_mapping("128=0").is_err());
+        assert!(parse_midi_mapping("74").is_err());
+    }
 }
@@ -0,0 +1,44 @@
+use crate::config::UniformName;
+
+// built-in shaders for --example, to learn from without hunting for files
+const EXAMPLES: &[(&str, &str)] = &[
+    (
+        "gradient",
+        include_str!("../shaders/examples/gradient.wgsl"),
+    ),
+    ("plasma", include_str!("../shaders/examples/plasma.wgsl")),
+    ("sphere", include_str!("../shaders/examples/sphere.wgsl")),
+];
+
+// starting point for --init, with the placeholder bindings filled in by `template`
+const TEMPLATE: &str = include_str!("../shaders/template.wgsl");
+
+pub(crate) fn names() -> impl Iterator<Item = &'static str> {
+    EXAMPLES.iter().map(|&(name, _)| name)
+}
+
+pub(crate) fn source(name: &str) -> Option<&'static str> {
+    EXAMPLES
+        .iter()
+        .find(|&&(example, _)| example == name)
+        .map(|&(_, source)| source)
+}
+
+/// The starter shader for --init, declaring the uniforms at the bindings `uniforms` gives them
+///
+/// Fails if the uniforms it uses aren't all bound.
+pub(crate) fn template(uniforms: &[UniformName]) -> Result<String, String> {
+    let mut template = TEMPLATE.to_owned();
+    for (placeholder, name) in [
+        ("TIME_BINDING", UniformName::Time),
+        ("MOUSE_BINDING", UniformName::Mouse),
+        ("RESOLUTION_BINDING", UniformName::Resolution),
+    ] {
+        let binding = uniforms
+            .iter()
+            .position(|&uniform| uniform == name)
+            .ok_or_else(|| format!("the template needs {:?} in --uniforms", name))?;
+        template = template.replace(placeholder, &binding.to_string());
+    }
+    Ok(template)
+}
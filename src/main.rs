@@ -1,32 +1,274 @@
 // with appreciation to https://sotrh.github.io/learn-wgpu/
 
-use clap::Parser;
+use clap::{CommandFactory, FromArgMatches, Parser, ValueSource};
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use tracing_chrome::ChromeLayerBuilder;
+use tracing_subscriber::prelude::*;
 use wgpu::SurfaceError;
 use winit::{
-    event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent},
+    dpi::PhysicalSize,
+    event::{ElementState, Event, KeyboardInput, ModifiersState, VirtualKeyCode, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
-    window::WindowBuilder,
+    window::{Fullscreen, Window, WindowBuilder},
 };
 
+mod bundle;
 mod config;
+mod examples;
+mod recent;
 mod shader;
+mod watch;
+
+use self::{
+    config::{Config, DEFAULT_PATH},
+    recent::{last_shader, remember_shader},
+    shader::{check_shader, manifest_files, record_gif, render_to_file, State, Target},
+    watch::{spawn_watcher, ShaderChanged},
+};
+
+// the current monitor's video mode at its native resolution, with the highest refresh rate
+// or borderless fullscreen if the platform doesn't list any modes, as on Wayland
+fn exclusive_fullscreen(window: &Window) -> Fullscreen {
+    let monitor = window.current_monitor();
+    let native = monitor.as_ref().map(|monitor| monitor.size());
+    let mode = monitor.as_ref().and_then(|monitor| {
+        monitor.video_modes().max_by_key(|mode| {
+            (
+                Some(mode.size()) == native,
+                mode.refresh_rate(),
+                mode.size().width * mode.size().height,
+                mode.bit_depth(),
+            )
+        })
+    });
+    match mode {
+        Some(mode) => {
+            log::info!(
+                "Going fullscreen at {}x{}, {}Hz",
+                mode.size().width,
+                mode.size().height,
+                mode.refresh_rate()
+            );
+            Fullscreen::Exclusive(mode)
+        }
+        None => {
+            log::warn!("No video modes to go exclusive fullscreen with, going borderless instead");
+            Fullscreen::Borderless(monitor)
+        }
+    }
+}
+
+// how much [ and ] change the selected custom uniform by, or with Shift held
+const PARAM_STEP: f32 = 0.05;
+const FINE_PARAM_STEP: f32 = 0.005;
 
-use self::{config::Config, shader::State};
+// the preset slot for a number key, counting from 0 for 1
+fn preset_slot(key: VirtualKeyCode) -> Option<usize> {
+    use VirtualKeyCode::*;
+    [Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9]
+        .iter()
+        .position(|&number| number == key)
+}
 
 fn main() {
     env_logger::init();
+    // before opening a window, so --help and the options that just print something don't flash one up
+    log::info!("Parsing command line arguments");
+    let matches = Config::command().get_matches();
+    let mut config = Config::from_arg_matches(&matches).unwrap_or_else(|error| error.exit());
+    // the shader from last time, when it isn't picked on the command line or by a bundle
+    let explicit_path = matches.value_source("path") == Some(ValueSource::CommandLine);
+    let remember = config.bundle.is_none() && config.example.is_none() && config.manifest.is_none();
+    // the bundle's files are reloaded from where it's unpacked, so it's kept until exiting
+    let mut bundle = None;
+    if let Some(path) = &config.bundle {
+        let (args, unpacked) = bundle::bundle_args(path);
+        config = Config::parse_from(args);
+        bundle = Some(unpacked);
+    }
+    if remember && !explicit_path {
+        match last_shader() {
+            Some(path) => {
+                log::info!("Running the last shader run, {}", path);
+                config.path = path;
+            }
+            None => log::info!("No last shader to run, so running {}", DEFAULT_PATH),
+        }
+    }
+    if let Err(error) = config.load_layers() {
+        eprintln!("error: {}", error);
+        drop(bundle);
+        std::process::exit(1);
+    }
+    if config.list_examples {
+        for name in examples::names() {
+            println!("{}", name);
+        }
+        return;
+    }
+    if config.print_config {
+        print!("{}", config.to_toml());
+        return;
+    }
+    if let Some(path) = &config.init {
+        let written = examples::template(&config.uniforms).and_then(|template| {
+            // create_new, so an existing shader is never overwritten
+            OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(path)
+                .and_then(|mut file| file.write_all(template.as_bytes()))
+                .map_err(|error| format!("couldn't write {}: {}", path, error))
+        });
+        match written {
+            Ok(()) => println!("Wrote a starter shader to {}", path),
+            Err(error) => {
+                eprintln!("error: {}", error);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    if config.check {
+        let passed = check_shader(&config);
+        // exiting skips destructors
+        drop(bundle);
+        // exit code for editors and pre-commit hooks
+        std::process::exit(if passed { 0 } else { 1 });
+    }
+    if config.output.is_some() {
+        // exit code for scripts making thumbnails or testing shaders
+        if let Err(error) = render_to_file(config) {
+            eprintln!("error: {}", error);
+            drop(bundle);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if config.gif.is_some() && config.size.is_some() {
+        if let Err(error) = record_gif(config) {
+            eprintln!("error: {}", error);
+            drop(bundle);
+            std::process::exit(1);
+        }
+        return;
+    }
+    let once = config.once;
+    // for Home to go back to
+    let time_scale = config.time_scale;
+    // the trace is only written out when this is dropped, as the event loop exits
+    let mut profile = config.profile.as_deref().map(|path| {
+        log::info!("Profiling to {}", path);
+        let (chrome_layer, guard) = ChromeLayerBuilder::new().file(path).build();
+        // not .init(), which would also try to install a log logger, and env_logger already has
+        let subscriber = tracing_subscriber::registry().with(chrome_layer);
+        if let Err(error) = tracing::subscriber::set_global_default(subscriber) {
+            log::error!("Couldn't start profiling: {}", error);
+        }
+        guard
+    });
+    let hide_cursor_after = config.hide_cursor_after;
+    // frames are spaced at least this far apart with --max-fps
+    let frame_interval = config.max_fps.map(|fps| Duration::from_secs_f64(1.0 / fps));
+    // when the next frame is allowed to start
+    let mut next_frame = Instant::now();
+
+    // exit between frames on Ctrl+C or SIGTERM, instead of dying partway through one
+    let interrupted = Arc::new(AtomicBool::new(false));
+    {
+        let interrupted = Arc::clone(&interrupted);
+        ctrlc::set_handler(move || interrupted.store(true, Ordering::SeqCst))
+            .expect("Could not set signal handler");
+    }
+
     log::info!("Creating event loop");
-    let event_loop = EventLoop::new(); // make an event loop
+    // with an event for the watcher to wake it with
+    let event_loop = EventLoop::with_user_event(); // make an event loop
+
+    // for --watch, which starts once the shader's loaded and it's known what it includes
+    let mut watched = None;
+    if config.watch {
+        // a --manifest's passes are watched instead of the shader file
+        if let Some(manifest) = &config.manifest {
+            if let Err(error) = manifest_files(manifest) {
+                eprintln!("error: failed loading manifest {}: {}", manifest, error);
+                drop(bundle);
+                std::process::exit(1);
+            }
+        }
+        let others: Vec<String> = config
+            .layer
+            .iter()
+            .map(|layer| layer.path.clone())
+            .chain(config.feedback.iter().cloned())
+            .chain(config.compute.iter().cloned())
+            .collect();
+        watched = Some((config.manifest.clone(), config.path.clone(), others));
+    }
     log::info!("Creating window");
-    let window = WindowBuilder::new()
+    let mut builder = WindowBuilder::new().with_title(&config.title);
+    if let (Some(width), Some(height)) = (config.width, config.height) {
+        builder = builder.with_inner_size(PhysicalSize::new(width, height));
+    }
+    if config.fullscreen {
+        builder = builder.with_fullscreen(Some(Fullscreen::Borderless(None)));
+    }
+    let window = builder
         .build(&event_loop) // make a window from it
         .expect("Could not create window");
-
-    log::info!("Parsing command line arguments");
-    let config = Config::parse();
+    if config.exclusive_fullscreen {
+        window.set_fullscreen(Some(exclusive_fullscreen(&window)));
+    }
+    let mut cursor_visible = !config.no_cursor;
+    window.set_cursor_visible(cursor_visible);
 
     log::info!("Initialising State");
-    let mut state = pollster::block_on(State::new(&window, config)); // could also use an async main with a crate
+    let loaded_path = remember.then(|| config.path.clone());
+    // could also use an async main with a crate
+    let mut state = match pollster::block_on(State::new(Target::Window(&window), config)) {
+        Ok(state) => state,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            drop(bundle);
+            std::process::exit(1);
+        }
+    };
+    // it loaded, so it's the one to run next time there's no --path
+    if let Some(path) = loaded_path {
+        remember_shader(&path);
+    }
+    if let Some((manifest, shader, others)) = watched {
+        let includes = state.includes();
+        // listed again on every check, as the manifest may have gained passes, and the shader
+        // `//!include`s, which the state keeps up to date as it reloads
+        let list_paths = move || {
+            let shaders = match &manifest {
+                // a manifest that's broken partway through an edit keeps just itself watched
+                Some(manifest) => {
+                    manifest_files(manifest).unwrap_or_else(|_| vec![manifest.clone()])
+                }
+                None => vec![shader.clone()],
+            };
+            let includes = includes.lock().expect("Loading the shader never panics");
+            shaders
+                .into_iter()
+                .chain(others.iter().cloned())
+                .chain(includes.iter().cloned())
+                .collect()
+        };
+        spawn_watcher(list_paths, event_loop.create_proxy());
+    }
+
+    // for telling saving a preset from recalling it
+    let mut modifiers = ModifiersState::empty();
 
     log::info!("Starting event loop");
     event_loop.run(move |event, _, control_flow| match event {
@@ -63,37 +305,352 @@ fn main() {
                         ..
                     } => {
                         log::info!("Reloading shader");
-                        state.refresh_shader()
+                        state.reload()
+                    }
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::G),
+                                ..
+                            },
+                        ..
+                    } => {
+                        log::info!("Toggling grid overlay");
+                        state.toggle_overlay()
+                    }
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::N),
+                                ..
+                            },
+                        ..
+                    } => {
+                        log::info!("Toggling NDC overlay");
+                        state.toggle_ndc_overlay()
+                    }
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::F),
+                                ..
+                            },
+                        ..
+                    } => {
+                        log::info!("Cycling surface format");
+                        state.cycle_surface_format()
+                    }
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::C),
+                                ..
+                            },
+                        ..
+                    } => {
+                        if state.toggle_reference() {
+                            log::info!("Toggling reference comparison");
+                        } else {
+                            log::warn!("No --reference image to compare with");
+                        }
                     }
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::P),
+                                ..
+                            },
+                        ..
+                    } => {
+                        if !state.pick_pixel() {
+                            log::warn!("Move the cursor into the window to pick a pixel");
+                        }
+                    }
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::F12),
+                                ..
+                            },
+                        ..
+                    } => state.screenshot(),
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::T),
+                                ..
+                            },
+                        ..
+                    } => match state.tap_tempo() {
+                        Some(bpm) => log::info!("Tapped tempo: {:.1} BPM", bpm),
+                        None => log::info!("Tap T on the beat to set the tempo"),
+                    },
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::B),
+                                ..
+                            },
+                        ..
+                    } => state.print_layout(),
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::Minus),
+                                ..
+                            },
+                        ..
+                    } => state.shrink_quad(),
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::Equals),
+                                ..
+                            },
+                        ..
+                    } => state.grow_quad(),
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::Z),
+                                ..
+                            },
+                        ..
+                    } => {
+                        log::info!("Resetting zoom");
+                        state.reset_zoom()
+                    }
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::L),
+                                ..
+                            },
+                        ..
+                    } => {
+                        log::info!("Resetting mouse path length");
+                        state.reset_path_length()
+                    }
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::Space),
+                                ..
+                            },
+                        ..
+                    } => {
+                        if state.toggle_pause() {
+                            log::info!("Pausing time");
+                        } else {
+                            log::info!("Resuming time");
+                        }
+                    }
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::Period),
+                                ..
+                            },
+                        ..
+                    } => {
+                        if !state.step_time() {
+                            log::warn!("Pause time with Space before stepping it");
+                        }
+                    }
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::R),
+                                ..
+                            },
+                        ..
+                    } => {
+                        log::info!("Resetting time");
+                        state.reset_time();
+                    }
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::PageDown),
+                                ..
+                            },
+                        ..
+                    } => state.set_time_scale(state.time_scale() / 2.0),
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::PageUp),
+                                ..
+                            },
+                        ..
+                    } => state.set_time_scale(state.time_scale() * 2.0),
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::Home),
+                                ..
+                            },
+                        ..
+                    } => state.set_time_scale(time_scale),
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::Tab),
+                                ..
+                            },
+                        ..
+                    } => state.select_param(!modifiers.shift()),
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode:
+                                    Some(key @ (VirtualKeyCode::LBracket | VirtualKeyCode::RBracket)),
+                                ..
+                            },
+                        ..
+                    } => {
+                        let step = if modifiers.shift() {
+                            FINE_PARAM_STEP
+                        } else {
+                            PARAM_STEP
+                        };
+                        let delta = if *key == VirtualKeyCode::LBracket {
+                            -step
+                        } else {
+                            step
+                        };
+                        state.adjust_param(state.selected_param(), delta);
+                    }
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(key),
+                                ..
+                            },
+                        ..
+                    } => match preset_slot(*key) {
+                        Some(slot) if modifiers.shift() => state.save_preset(slot),
+                        Some(slot) => match state.recall_preset(slot) {
+                            Some(name) => log::info!("Recalled preset `{}`", name),
+                            None => log::warn!("No preset {} to recall", slot + 1),
+                        },
+                        None => {}
+                    },
+                    WindowEvent::ModifiersChanged(new_modifiers) => modifiers = *new_modifiers,
                     WindowEvent::Resized(physical_size) => {
                         log::debug!("Resizing");
                         state.resize(*physical_size);
                     }
-                    WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                    WindowEvent::ScaleFactorChanged {
+                        scale_factor,
+                        new_inner_size,
+                    } => {
                         log::debug!("Rescaling");
                         // deref it twice because it's &&mut
-                        state.resize(**new_inner_size);
+                        state.rescale(*scale_factor, **new_inner_size);
                     }
+                    // might be on a different monitor now
+                    WindowEvent::Moved(_) => state.moved(&window),
                     _ => {} // do nothing
                 }
             }
         }
+        Event::UserEvent(ShaderChanged) => {
+            log::info!("Shader changed, reloading");
+            state.reload()
+        }
         Event::RedrawRequested(window_id) if window_id == window.id() => {
-            state.update();
-            match state.render() {
+            {
+                let _update = tracing::info_span!("update").entered();
+                state.update();
+            }
+            let rendered = state.render();
+            if let Some(interval) = frame_interval {
+                // on schedule, unless a slow frame put it behind, which isn't made up for with
+                // a burst of quick ones
+                next_frame = (next_frame + interval).max(Instant::now());
+            }
+            if let Some(icon) = state.take_icon() {
+                window.set_window_icon(Some(icon));
+            }
+            if let Some(title) = state.take_title() {
+                window.set_title(&title);
+            }
+            match rendered {
+                // a frame made it to the screen, so stop if only one was wanted
+                Ok(_) if once => {
+                    log::info!("Rendered one frame, exiting");
+                    *control_flow = ControlFlow::Exit
+                }
+                Ok(_) if state.finished() => {
+                    log::info!("Finished recording, exiting");
+                    *control_flow = ControlFlow::Exit
+                }
                 Ok(_) => {}
                 // reconfig the surface if lost
                 Err(SurfaceError::Lost) => state.resize(state.current_size()),
                 // quit if out of memory
                 Err(SurfaceError::OutOfMemory) => *control_flow = ControlFlow::Exit,
-                // should resolve other errors, (Outdated, Timeout), by next frame
+                // the GPU was busy, so skip this frame and try again next one
+                // it can happen every frame for a while, so it isn't worth an error each time
+                Err(SurfaceError::Timeout) => log::debug!("Timed out acquiring a frame"),
+                // still outdated after reconfiguring, which should resolve by next frame
                 Err(e) => log::error!("{:?}", e),
             }
         }
+        Event::MainEventsCleared if interrupted.load(Ordering::SeqCst) => {
+            log::info!("Interrupted, exiting");
+            *control_flow = ControlFlow::Exit
+        }
         Event::MainEventsCleared => {
-            // only one RedrawRequested will happen automatically
-            // so request it manually
-            window.request_redraw();
+            if let Some(timeout) = hide_cursor_after {
+                let visible = state.mouse_idle_time() < timeout;
+                if visible != cursor_visible {
+                    window.set_cursor_visible(visible);
+                    cursor_visible = visible;
+                }
+            }
+            match frame_interval {
+                // sleep until the next frame's due, waking early for input
+                Some(_) if Instant::now() < next_frame => {
+                    *control_flow = ControlFlow::WaitUntil(next_frame)
+                }
+                _ => {
+                    if frame_interval.is_some() {
+                        *control_flow = ControlFlow::Poll;
+                    }
+                    // only one RedrawRequested will happen automatically
+                    // so request it manually
+                    window.request_redraw();
+                }
+            }
+        }
+        // the window may outlive the event loop briefly, so don't leave the cursor hidden
+        Event::LoopDestroyed => {
+            window.set_cursor_visible(true);
+            drop(profile.take());
+            drop(bundle.take());
         }
         _ => {}
     });
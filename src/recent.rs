@@ -0,0 +1,58 @@
+use directories::ProjectDirs;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+// in the platform's config directory, e.g. ~/.config/shader-party on Linux
+const LAST_SHADER_FILE: &str = "last-shader";
+
+// where the last shader's path is kept, or None if the platform has no config directory
+fn last_shader_file() -> Option<PathBuf> {
+    ProjectDirs::from("", "", "shader-party").map(|dirs| dirs.config_dir().join(LAST_SHADER_FILE))
+}
+
+/// The shader run last time, to run again when there's no --path
+///
+/// None if nothing's been run yet, or the shader has since been moved or deleted.
+pub(crate) fn last_shader() -> Option<String> {
+    let file = last_shader_file()?;
+    let path = fs::read_to_string(&file).ok()?;
+    let path = path.trim();
+    if path.is_empty() {
+        return None;
+    }
+    if !Path::new(path).is_file() {
+        log::info!("The last shader run, {}, is gone", path);
+        return None;
+    }
+    Some(path.to_owned())
+}
+
+/// Remember a shader that loaded, for the next run without a --path
+///
+/// It's stored absolute, so it's found again from any working directory.
+pub(crate) fn remember_shader(path: &str) {
+    let file = match last_shader_file() {
+        Some(file) => file,
+        None => return,
+    };
+    let path = match fs::canonicalize(path) {
+        Ok(path) => path,
+        Err(error) => {
+            log::warn!("Not remembering shader {}: {}", path, error);
+            return;
+        }
+    };
+    let written = file
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|()| fs::write(&file, path.to_string_lossy().as_bytes()));
+    if let Err(error) = written {
+        log::warn!(
+            "Failed remembering the shader in {}: {}",
+            file.display(),
+            error
+        );
+    }
+}
@@ -0,0 +1,177 @@
+use rodio::{buffer::SamplesBuffer, Decoder, OutputStream, OutputStreamHandle, Sink, Source};
+use rustfft::{num_complex::Complex, Fft, FftPlanner};
+use std::{
+    fs::File,
+    io::BufReader,
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use super::uniforms::{AudioUniform, SPECTRUM_BINS, WAVEFORM_SAMPLES};
+
+// samples in each FFT, giving a bin for each of the spectrum's frequencies up to Nyquist
+const FFT_SIZE: usize = 2 * SPECTRUM_BINS;
+// the spectrum's range in decibels, mapped to 0..1, where 0dB is a full scale sine
+const MIN_DECIBELS: f32 = -80.0;
+const MAX_DECIBELS: f32 = 0.0;
+// playback further than this from shader time is moved back in line with it
+const RESYNC_THRESHOLD: Duration = Duration::from_millis(100);
+
+/// An audio file from --audio, analysed at shader time and played in step with it
+pub(super) struct Audio {
+    // mixed down to mono for analysis
+    mono: Vec<f32>,
+    sample_rate: u32,
+    fft: Arc<dyn Fft<f32>>,
+    // Hann window, to keep the edges of each FFT from smearing across the spectrum
+    window: Vec<f32>,
+    // None when recording offscreen, or when there's no audio device to play on
+    playback: Option<Playback>,
+}
+
+struct Playback {
+    // playback stops when the stream is dropped
+    _stream: OutputStream,
+    handle: OutputStreamHandle,
+    sink: Sink,
+    channels: u16,
+    // interleaved, as decoded
+    samples: Vec<f32>,
+    // the shader time last synced at, and when, or None while paused
+    last_sync: Option<(Duration, Instant)>,
+}
+
+impl std::fmt::Debug for Audio {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Audio")
+            .field("samples", &self.mono.len())
+            .field("sample_rate", &self.sample_rate)
+            .field("playing", &self.playback.is_some())
+            .finish()
+    }
+}
+
+impl Audio {
+    pub(super) fn load(path: &Path, play: bool) -> Result<Self, String> {
+        let file = File::open(path)
+            .map_err(|error| format!("failed opening {}: {}", path.display(), error))?;
+        let decoder = Decoder::new(BufReader::new(file))
+            .map_err(|error| format!("failed decoding {}: {}", path.display(), error))?;
+        let channels = decoder.channels();
+        let sample_rate = decoder.sample_rate();
+        let samples: Vec<f32> = decoder.convert_samples().collect();
+        let mono = samples
+            .chunks(usize::from(channels.max(1)))
+            .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+            .collect();
+        log::info!(
+            "Loaded {:.1}s of audio from {}",
+            samples.len() as f64 / f64::from(channels.max(1)) / f64::from(sample_rate),
+            path.display()
+        );
+        let window = (0..FFT_SIZE)
+            .map(|index| {
+                let phase = index as f32 / (FFT_SIZE - 1) as f32;
+                0.5 - 0.5 * (2.0 * std::f32::consts::PI * phase).cos()
+            })
+            .collect();
+        let playback = if play {
+            Playback::new(channels, samples)
+                .map_err(|error| log::warn!("Not playing --audio: {}", error))
+                .ok()
+        } else {
+            None
+        };
+        Ok(Self {
+            mono,
+            sample_rate,
+            fft: FftPlanner::new().plan_fft_forward(FFT_SIZE),
+            window,
+            playback,
+        })
+    }
+
+    // analyse the audio leading up to a shader time, and keep playback in line with it
+    pub(super) fn update(&mut self, uniform: &mut AudioUniform, elapsed: Duration, paused: bool) {
+        if let Some(playback) = &mut self.playback {
+            playback.sync(elapsed, paused, self.sample_rate);
+        }
+        // the newest sample heard at this time, which both the FFT and the waveform end at
+        let end = (elapsed.as_secs_f64() * f64::from(self.sample_rate)) as usize;
+        // silence before the start and after the end of the file
+        let window_start = end as isize - FFT_SIZE as isize;
+        let at = |offset: usize| {
+            let index = window_start + offset as isize;
+            usize::try_from(index)
+                .ok()
+                .and_then(|index| self.mono.get(index))
+                .copied()
+                .unwrap_or(0.0)
+        };
+        let mut buffer: Vec<Complex<f32>> = (0..FFT_SIZE)
+            .map(|offset| Complex::new(at(offset) * self.window[offset], 0.0))
+            .collect();
+        self.fft.process(&mut buffer);
+        // scaled so a full scale sine peaks at 1, whatever the window and FFT size
+        let scale = 2.0 / self.window.iter().sum::<f32>();
+        let spectrum = buffer[..SPECTRUM_BINS].iter().map(|bin| {
+            let decibels = 20.0 * (bin.norm() * scale).max(f32::MIN_POSITIVE).log10();
+            ((decibels - MIN_DECIBELS) / (MAX_DECIBELS - MIN_DECIBELS)).clamp(0.0, 1.0)
+        });
+        let waveform = (FFT_SIZE - WAVEFORM_SAMPLES..FFT_SIZE).map(at);
+        uniform.update(spectrum, waveform);
+    }
+}
+
+impl Playback {
+    fn new(channels: u16, samples: Vec<f32>) -> Result<Self, String> {
+        let (stream, handle) = OutputStream::try_default().map_err(|error| error.to_string())?;
+        let sink = Sink::try_new(&handle).map_err(|error| error.to_string())?;
+        Ok(Self {
+            _stream: stream,
+            handle,
+            sink,
+            channels,
+            samples,
+            last_sync: None,
+        })
+    }
+
+    // playback runs on its own clock, so it's restarted at shader time whenever the two drift
+    // apart, as when time is reset, stepped or wraps, or when it's unpaused
+    fn sync(&mut self, elapsed: Duration, paused: bool, sample_rate: u32) {
+        if paused {
+            self.sink.pause();
+            self.last_sync = None;
+            return;
+        }
+        let now = Instant::now();
+        let in_step = self.last_sync.is_some_and(|(last_elapsed, last_now)| {
+            let expected = last_elapsed + (now - last_now);
+            expected.abs_diff(elapsed) < RESYNC_THRESHOLD
+        });
+        if !in_step {
+            self.restart(elapsed, sample_rate);
+        }
+        self.last_sync = Some((elapsed, now));
+    }
+
+    fn restart(&mut self, elapsed: Duration, sample_rate: u32) {
+        self.sink.stop();
+        self.sink = match Sink::try_new(&self.handle) {
+            Ok(sink) => sink,
+            Err(error) => {
+                log::warn!("Failed restarting --audio: {}", error);
+                return;
+            }
+        };
+        let frame = (elapsed.as_secs_f64() * f64::from(sample_rate)) as usize;
+        let start = (frame * usize::from(self.channels)).min(self.samples.len());
+        self.sink.append(SamplesBuffer::new(
+            self.channels,
+            sample_rate,
+            &self.samples[start..],
+        ));
+    }
+}
@@ -0,0 +1,65 @@
+use wgpu::*;
+
+use super::{
+    geometry::{Mesh, ScreenGeometry},
+    new_pipeline,
+    passes::{input_bind_group, input_bind_group_layout},
+    textures::{Channel, Sampling, TextureFile},
+};
+
+/// The image from --background-image, drawn under the shader's last pass for it to blend over
+#[derive(Debug)]
+pub(super) struct Background {
+    layout: BindGroupLayout,
+    bind_group: BindGroup,
+}
+
+impl Background {
+    pub(super) fn new(device: &Device, queue: &Queue, file: &TextureFile) -> Self {
+        // stretched to fit exactly, so it never needs to repeat
+        let sampling = Sampling {
+            address_mode: AddressMode::ClampToEdge,
+            anisotropy: None,
+        };
+        let channel = Channel::new(device, queue, file, sampling, None);
+        let layout = input_bind_group_layout(device, 1, &[]);
+        let bind_group =
+            input_bind_group(device, &layout, channel.sampler(), &[channel.view()], &[]);
+        Self { layout, bind_group }
+    }
+
+    // a pipeline drawing the background into targets of this format
+    // the shader's last pass can render to the surface or an offscreen target,
+    // so this is made along with it
+    pub(super) fn pipeline(&self, device: &Device, format: TextureFormat) -> RenderPipeline {
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Background Pipeline Layout"),
+            bind_group_layouts: &[&self.layout],
+            push_constant_ranges: &[],
+        });
+        let shader = device.create_shader_module(&ShaderModuleDescriptor {
+            label: Some("Background Shader"),
+            source: ShaderSource::Wgsl(include_str!("background.wgsl").into()),
+        });
+        new_pipeline(
+            device,
+            format,
+            &pipeline_layout,
+            shader,
+            BlendState::REPLACE,
+            ScreenGeometry::Quad,
+        )
+    }
+
+    // draw into a render pass, before the shader draws over it
+    pub(super) fn draw<'pass>(
+        &'pass self,
+        render_pass: &mut RenderPass<'pass>,
+        pipeline: &'pass RenderPipeline,
+        quad: &'pass Mesh,
+    ) {
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        quad.draw(render_pass);
+    }
+}
@@ -0,0 +1,31 @@
+// Draws --background-image stretched over the target, for the shader to blend over
+
+[[group(0), binding(0)]]
+var background_texture: texture_2d<f32>;
+[[group(0), binding(1)]]
+var background_sampler: sampler;
+
+struct VertexInput {
+    [[location(0)]] position: vec3<f32>;
+    [[location(1)]] tex_coords: vec2<f32>;
+};
+
+struct VertexOutput {
+    [[builtin(position)]] clip_position: vec4<f32>;
+    [[location(0)]] tex_coords: vec2<f32>;
+};
+
+[[stage(vertex)]]
+fn vs_main(model: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.tex_coords = model.tex_coords;
+    out.clip_position = vec4<f32>(model.position, 1.0);
+    return out;
+}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    // textures have y pointing down, the opposite way to tex_coords
+    let uv = vec2<f32>(in.tex_coords.x, 1.0 - in.tex_coords.y);
+    return textureSample(background_texture, background_sampler, uv);
+}
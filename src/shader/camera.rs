@@ -0,0 +1,95 @@
+use cgmath::{perspective, Deg, Matrix4, Point3, Rad, Vector3};
+use winit::event::MouseScrollDelta;
+
+// cgmath is built for OpenGL's -1..1 depth range, but wgpu uses 0..1
+#[rustfmt::skip]
+const OPENGL_TO_WGPU_MATRIX: Matrix4<f32> = Matrix4::new(
+    1.0, 0.0, 0.0, 0.0,
+    0.0, 1.0, 0.0, 0.0,
+    0.0, 0.0, 0.5, 0.0,
+    0.0, 0.0, 0.5, 1.0,
+);
+
+// radians turned per pixel of mouse drag
+const ORBIT_SPEED: f32 = 0.01;
+// fraction of the distance moved per line of scrolling
+const ZOOM_SPEED: f32 = 0.1;
+// stop just short of straight up or down, where the up vector breaks
+const MAX_PITCH: f32 = std::f32::consts::FRAC_PI_2 - 0.01;
+
+// lines scrolled, positive for up, for mice that scroll in lines and touchpads in pixels
+pub(super) fn scroll_lines(delta: MouseScrollDelta) -> f32 {
+    match delta {
+        MouseScrollDelta::LineDelta(_, y) => y,
+        // roughly how many pixels a line of scrolling is
+        MouseScrollDelta::PixelDelta(position) => position.y as f32 / 20.0,
+    }
+}
+
+/// Camera orbiting the origin, turned by dragging and zoomed by scrolling
+#[derive(Debug)]
+pub(super) struct OrbitCamera {
+    // rotation about the y axis
+    yaw: f32,
+    // rotation above or below the horizon
+    pitch: f32,
+    distance: f32,
+    aspect: f32,
+    dragging: bool,
+    last_cursor: Option<(f64, f64)>,
+}
+
+impl OrbitCamera {
+    pub(super) fn new(width: u32, height: u32) -> Self {
+        Self {
+            yaw: 0.0,
+            pitch: 0.0,
+            distance: 3.0,
+            aspect: width as f32 / height as f32,
+            dragging: false,
+            last_cursor: None,
+        }
+    }
+
+    pub(super) fn resize(&mut self, width: u32, height: u32) {
+        self.aspect = width as f32 / height as f32;
+    }
+
+    pub(super) fn set_dragging(&mut self, dragging: bool) {
+        self.dragging = dragging;
+    }
+
+    // cursor position in pixels, orbits if a drag is happening
+    // returns whether the camera moved
+    pub(super) fn cursor_moved(&mut self, x: f64, y: f64) -> bool {
+        let moved = match self.last_cursor {
+            Some((last_x, last_y)) if self.dragging => {
+                self.yaw -= (x - last_x) as f32 * ORBIT_SPEED;
+                self.pitch =
+                    (self.pitch + (y - last_y) as f32 * ORBIT_SPEED).clamp(-MAX_PITCH, MAX_PITCH);
+                true
+            }
+            _ => false,
+        };
+        self.last_cursor = Some((x, y));
+        moved
+    }
+
+    pub(super) fn scroll(&mut self, lines: f32) {
+        // zoom multiplicatively so it feels the same close up and far away
+        self.distance = (self.distance * (1.0 - ZOOM_SPEED).powf(lines)).clamp(0.1, 100.0);
+    }
+
+    pub(super) fn view_projection(&self) -> Matrix4<f32> {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        let eye = Point3::new(
+            self.distance * cos_pitch * sin_yaw,
+            self.distance * sin_pitch,
+            self.distance * cos_pitch * cos_yaw,
+        );
+        let view = Matrix4::look_at_rh(eye, Point3::new(0.0, 0.0, 0.0), Vector3::unit_y());
+        let projection = perspective(Rad::from(Deg(45.0)), self.aspect, 0.1, 100.0);
+        OPENGL_TO_WGPU_MATRIX * projection * view
+    }
+}
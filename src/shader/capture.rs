@@ -0,0 +1,551 @@
+use serde::Serialize;
+use sha1_smol::Sha1;
+use std::{
+    borrow::Cow,
+    fs::File,
+    io::{self, BufWriter, Write},
+    num::NonZeroU32,
+    path::Path,
+    time::{Duration, Instant},
+};
+use wgpu::*;
+use winit::window::Icon;
+
+use crate::config::Colorspace;
+
+// frames are read back from the GPU as tightly packed pixels, rows from the top down
+// 8-bit surfaces give `rgba8`, 4 bytes per pixel, swizzled if the surface is `bgra8`
+// `Rgba16Float` surfaces give `rgba16f`, 8 bytes per pixel, little endian halfs
+// copies pad each row to 256 bytes, which is dropped, so a frame is always
+// `width * height * bytes_per_pixel` bytes
+
+/// A frame's pixels, laid out as described above
+#[derive(Debug)]
+pub(super) struct Frame {
+    pub(super) width: u32,
+    pub(super) height: u32,
+    pub(super) pixels: Vec<u8>,
+}
+
+/// A copy of a texture, waiting for its commands to be submitted before it can be read
+#[derive(Debug)]
+pub(super) struct PendingFrame {
+    buffer: Buffer,
+    format: TextureFormat,
+    size: Extent3d,
+    // bytes per row in the buffer, including wgpu's padding
+    padded_row: u32,
+}
+
+// whether a surface format needs its red and blue channels swapped to be `rgba`
+fn is_bgra(format: TextureFormat) -> bool {
+    matches!(
+        format,
+        TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb
+    )
+}
+
+impl PendingFrame {
+    // copy the texture into a buffer, which needs COPY_SRC usage
+    pub(super) fn copy(
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        texture: &Texture,
+        format: TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        Self::copy_region(
+            device,
+            encoder,
+            texture,
+            format,
+            Origin3d::ZERO,
+            width,
+            height,
+        )
+    }
+
+    // copy the `width` by `height` pixels of the texture starting at `origin`
+    // even a single pixel's row gets padded out to 256 bytes
+    pub(super) fn copy_region(
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        texture: &Texture,
+        format: TextureFormat,
+        origin: Origin3d,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let bytes_per_pixel = format.describe().block_size as u32;
+        let unpadded_row = width * bytes_per_pixel;
+        let padded_row =
+            unpadded_row.div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT) * COPY_BYTES_PER_ROW_ALIGNMENT;
+        let buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Capture Buffer"),
+            size: padded_row as u64 * height as u64,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let size = Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        encoder.copy_texture_to_buffer(
+            ImageCopyTexture {
+                texture,
+                mip_level: 0,
+                origin,
+                aspect: TextureAspect::All,
+            },
+            ImageCopyBuffer {
+                buffer: &buffer,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(padded_row),
+                    rows_per_image: NonZeroU32::new(height),
+                },
+            },
+            size,
+        );
+        Self {
+            buffer,
+            format,
+            size,
+            padded_row,
+        }
+    }
+
+    // wait for the copy to finish, after the encoder's been submitted
+    pub(super) fn read(self, device: &Device) -> Frame {
+        let slice = self.buffer.slice(..);
+        let mapping = slice.map_async(MapMode::Read);
+        device.poll(Maintain::Wait);
+        pollster::block_on(mapping).expect("Could not read frame back from GPU");
+
+        let bytes_per_pixel = self.format.describe().block_size as u32;
+        let unpadded_row = (self.size.width * bytes_per_pixel) as usize;
+        let mut pixels = Vec::with_capacity(unpadded_row * self.size.height as usize);
+        for row in slice.get_mapped_range().chunks(self.padded_row as usize) {
+            pixels.extend_from_slice(&row[..unpadded_row]);
+        }
+        self.buffer.unmap();
+
+        if is_bgra(self.format) {
+            for pixel in pixels.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+        Frame {
+            width: self.size.width,
+            height: self.size.height,
+            pixels,
+        }
+    }
+}
+
+// a frame's first pixel as text, as picked from under the cursor
+// sRGB surfaces store encoded values, so the linear value the shader output is shown too
+pub(super) fn pixel_text(frame: &Frame, format: TextureFormat) -> String {
+    match frame.pixels.len() {
+        // halfs from an `Rgba16Float` surface, which are exactly what the shader output
+        8 => {
+            let channels: Vec<_> = frame
+                .pixels
+                .chunks_exact(2)
+                .map(|half| exr::prelude::f16::from_le_bytes([half[0], half[1]]).to_f32())
+                .collect();
+            format!("rgba16f {:?}", channels)
+        }
+        _ => {
+            let bytes = &frame.pixels[..4];
+            let encoded: Vec<_> = bytes.iter().map(|&byte| byte as f32 / 255.0).collect();
+            if format.describe().srgb {
+                let linear: Vec<_> = encoded[..3]
+                    .iter()
+                    .map(|&value| srgb_to_linear(value))
+                    .chain([encoded[3]])
+                    .collect();
+                format!(
+                    "rgba8 {:?}, sRGB encoded {:.4?}, linear {:.4?}",
+                    bytes, encoded, linear
+                )
+            } else {
+                format!("rgba8 {:?}, {:.4?}", bytes, encoded)
+            }
+        }
+    }
+}
+
+// the inverse of the sRGB transfer function
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+// the sRGB transfer function
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+// a frame's pixels as 8-bit sRGB, which is what PNGs hold
+// linear colours get encoded, and float ones lose anything brighter than white
+// `auto` takes `rgba16f` frames as linear, and `rgba8` ones as holding what's on screen already
+fn srgb8_pixels(frame: &Frame, colorspace: Colorspace) -> Cow<'_, [u8]> {
+    let is_float = frame.pixels.len() != frame.width as usize * frame.height as usize * 4;
+    let encode = match colorspace {
+        Colorspace::Auto => is_float,
+        Colorspace::Srgb => false,
+        Colorspace::Linear => true,
+    };
+    if !is_float && !encode {
+        return Cow::Borrowed(&frame.pixels);
+    }
+    let values: Vec<f32> = if is_float {
+        frame
+            .pixels
+            .chunks_exact(2)
+            .map(|half| exr::prelude::f16::from_le_bytes([half[0], half[1]]).to_f32())
+            .collect()
+    } else {
+        frame
+            .pixels
+            .iter()
+            .map(|&byte| byte as f32 / 255.0)
+            .collect()
+    };
+    let pixels = values
+        .into_iter()
+        .enumerate()
+        .map(|(index, value)| {
+            // alpha isn't colour, so it stays linear
+            let encoded = if encode && index % 4 != 3 {
+                linear_to_srgb(value.max(0.0))
+            } else {
+                value
+            };
+            (encoded.clamp(0.0, 1.0) * 255.0).round() as u8
+        })
+        .collect();
+    Cow::Owned(pixels)
+}
+
+// write a frame out as a PNG, for screenshots
+pub(super) fn write_png(
+    frame: &Frame,
+    path: &Path,
+    colorspace: Colorspace,
+) -> image::ImageResult<()> {
+    image::save_buffer(
+        path,
+        &srgb8_pixels(frame, colorspace),
+        frame.width,
+        frame.height,
+        image::ColorType::Rgba8,
+    )
+}
+
+/// Writes frames back to back with no header, for --raw
+pub(super) struct RawWriter {
+    writer: Box<dyn Write>,
+    // of the last frame written, as readers need to know it to split the stream into frames
+    size: Option<(u32, u32)>,
+}
+
+impl std::fmt::Debug for RawWriter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RawWriter").finish_non_exhaustive()
+    }
+}
+
+impl RawWriter {
+    // `-` is stdout, for piping into another program
+    pub(super) fn create(path: &str) -> io::Result<Self> {
+        let writer: Box<dyn Write> = if path == "-" {
+            Box::new(BufWriter::new(io::stdout()))
+        } else {
+            Box::new(BufWriter::new(File::create(path)?))
+        };
+        Ok(Self { writer, size: None })
+    }
+
+    // make a named pipe for another program to read frames from, like a video mixer
+    // opening it waits until something starts reading
+    #[cfg(unix)]
+    pub(super) fn create_pipe(path: &str) -> io::Result<Self> {
+        use std::{ffi::CString, fs, os::unix::fs::FileTypeExt};
+
+        let exists = match fs::metadata(path) {
+            Ok(metadata) if metadata.file_type().is_fifo() => true,
+            Ok(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    "a file that isn't a named pipe is in the way",
+                ))
+            }
+            Err(_) => false,
+        };
+        if !exists {
+            let c_path = CString::new(path)
+                .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?;
+            // SAFETY: c_path is a valid nul terminated string for the duration of the call
+            if unsafe { libc::mkfifo(c_path.as_ptr(), 0o644) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        log::info!("Waiting for something to read frames from {}", path);
+        Self::create(path)
+    }
+
+    #[cfg(not(unix))]
+    pub(super) fn create_pipe(_path: &str) -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "named pipes are only supported on Unix, try --raw to a file instead",
+        ))
+    }
+
+    pub(super) fn write(&mut self, frame: &Frame) -> io::Result<()> {
+        let size = (frame.width, frame.height);
+        match self.size {
+            None => log::info!("Writing raw frames at {}x{}", size.0, size.1),
+            // there's no header to say so, so anything reading the stream will be misaligned
+            Some(old) if old != size => {
+                log::warn!("Raw frame size changed to {}x{}", size.0, size.1)
+            }
+            Some(_) => {}
+        }
+        self.size = Some(size);
+        self.writer.write_all(&frame.pixels)?;
+        // flushed every frame, so a reader sees whole frames as soon as they're rendered
+        self.writer.flush()
+    }
+}
+
+/// What produced a captured frame, for --capture-metadata
+#[derive(Debug, Serialize)]
+pub(super) struct FrameMetadata<'a> {
+    // the shader file's path, or the example's name
+    pub(super) shader: &'a str,
+    // of the shader file as loaded, the same as `git hash-object` gives
+    pub(super) shader_hash: &'a str,
+    // counting from 0 at startup
+    pub(super) frame: u64,
+    // the time uniform, in seconds
+    pub(super) time: f64,
+    // 0..1 across the window with y going up, like the mouse uniform's position
+    pub(super) mouse: [f32; 2],
+    pub(super) resolution: [u32; 2],
+}
+
+/// Writes a line of JSON per captured frame, beside a --raw, --pipe or --gif capture
+#[derive(Debug)]
+pub(super) struct MetadataWriter {
+    path: String,
+    writer: BufWriter<File>,
+}
+
+impl MetadataWriter {
+    // `<capture>.metadata.jsonl`
+    pub(super) fn create(capture: &str) -> io::Result<Self> {
+        let path = format!("{}.metadata.jsonl", capture);
+        let writer = BufWriter::new(File::create(&path)?);
+        log::info!("Writing capture metadata to {}", path);
+        Ok(Self { path, writer })
+    }
+
+    pub(super) fn path(&self) -> &str {
+        &self.path
+    }
+
+    // flushed every frame, like --raw, so it keeps up with the frames it describes
+    pub(super) fn write(&mut self, metadata: &FrameMetadata) -> io::Result<()> {
+        serde_json::to_writer(&mut self.writer, metadata)?;
+        writeln!(self.writer)?;
+        self.writer.flush()
+    }
+}
+
+// the id git gives a file with these contents, as `git hash-object` prints it
+pub(super) fn git_hash(contents: &[u8]) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(format!("blob {}\0", contents.len()).as_bytes());
+    hasher.update(contents);
+    hasher.digest().to_string()
+}
+
+// above this many pixels across all frames, a GIF is likely to be tens of megabytes
+const LARGE_GIF_PIXELS: u64 = 50_000_000;
+// how hard the palette quantizer works, from 1 (best) to 30 (fastest)
+const GIF_QUANTIZER_SPEED: i32 = 10;
+
+/// Collects frames in memory for --gif, then encodes them all as a looping GIF
+#[derive(Debug)]
+pub(super) struct GifRecorder {
+    path: String,
+    fps: f64,
+    // frames to record before encoding
+    count: usize,
+    frames: Vec<Frame>,
+}
+
+impl GifRecorder {
+    pub(super) fn new(path: &str, duration: Duration, fps: f64) -> Self {
+        let count = (duration.as_secs_f64() * fps).round().max(1.0) as usize;
+        log::info!("Recording {} frames for {}", count, path);
+        log::warn!("GIFs only have 256 colours per frame, so smooth gradients will band");
+        Self {
+            path: path.to_owned(),
+            fps,
+            count,
+            frames: Vec::with_capacity(count),
+        }
+    }
+
+    // shader time for the next frame, counting from the start of the recording
+    pub(super) fn next_frame_time(&self) -> Duration {
+        Duration::from_secs_f64(self.frames.len() as f64 / self.fps)
+    }
+
+    // frames recorded so far, and how many there'll be
+    pub(super) fn progress(&self) -> (usize, usize) {
+        (self.frames.len(), self.count)
+    }
+
+    // store a frame, and return whether that was the last one needed
+    pub(super) fn push(&mut self, frame: Frame) -> bool {
+        if frame.pixels.len() != frame.width as usize * frame.height as usize * 4 {
+            panic!("--gif needs an 8-bit surface format, try switching formats with F");
+        }
+        if self.frames.is_empty() {
+            let pixels = frame.width as u64 * frame.height as u64 * self.count as u64;
+            if pixels > LARGE_GIF_PIXELS {
+                log::warn!(
+                    "Recording {} frames at {}x{} will make a huge GIF, try a smaller window",
+                    self.count,
+                    frame.width,
+                    frame.height
+                );
+            }
+        }
+        self.frames.push(frame);
+        self.frames.len() >= self.count
+    }
+
+    // quantize every frame down to a palette and write them out, looping forever
+    pub(super) fn encode(self) -> Result<(), gif::EncodingError> {
+        log::info!("Encoding {} frames into {}", self.frames.len(), self.path);
+        // the frame size can't change partway through a GIF, so it's the first frame's
+        let (width, height) = match self.frames.first() {
+            Some(frame) => (frame.width as u16, frame.height as u16),
+            None => return Ok(()),
+        };
+        let file = BufWriter::new(File::create(&self.path)?);
+        let mut encoder = gif::Encoder::new(file, width, height, &[])?;
+        encoder.set_repeat(gif::Repeat::Infinite)?;
+        // GIF delays are in hundredths of a second
+        let delay = (100.0 / self.fps).round() as u16;
+        for mut frame in self.frames {
+            if (frame.width as u16, frame.height as u16) != (width, height) {
+                log::warn!("Skipping a frame from after the window was resized");
+                continue;
+            }
+            let mut gif_frame =
+                gif::Frame::from_rgba_speed(width, height, &mut frame.pixels, GIF_QUANTIZER_SPEED);
+            gif_frame.delay = delay;
+            encoder.write_frame(&gif_frame)?;
+        }
+        log::info!("Wrote {}", self.path);
+        Ok(())
+    }
+}
+
+// icons are square, and taskbars rarely show them any bigger
+const ICON_SIZE: u32 = 64;
+// a couple of updates a second looks live, without reading back every frame
+const ICON_INTERVAL: Duration = Duration::from_millis(500);
+
+// the frame pixels along one side of the square averaged into an icon pixel
+// at least one pixel, for frames smaller than the icon
+fn icon_box(index: u32, side: u32) -> std::ops::Range<u32> {
+    let start = index * side / ICON_SIZE;
+    start..((index + 1) * side / ICON_SIZE).max(start + 1)
+}
+
+/// Scales frames down into the window icon every so often, for --live-icon
+#[derive(Debug, Default)]
+pub(super) struct IconPreview {
+    // when the last frame was scaled down, or None to take the next one
+    last_update: Option<Instant>,
+    // waiting for the window to show it
+    icon: Option<Icon>,
+    // whether the surface format has been complained about, so it only happens once
+    warned: bool,
+}
+
+impl IconPreview {
+    // whether enough time has passed to read back another frame
+    pub(super) fn due(&self) -> bool {
+        self.last_update
+            .is_none_or(|last_update| last_update.elapsed() >= ICON_INTERVAL)
+    }
+
+    // scale the middle square of a frame down to icon size, averaging the pixels in each box
+    pub(super) fn push(&mut self, frame: &Frame) {
+        self.last_update = Some(Instant::now());
+        if frame.pixels.len() != frame.width as usize * frame.height as usize * 4 {
+            if !self.warned {
+                log::warn!(
+                    "--live-icon needs an 8-bit surface format, try switching formats with F"
+                );
+                self.warned = true;
+            }
+            return;
+        }
+        let side = frame.width.min(frame.height);
+        if side == 0 {
+            return;
+        }
+        let left = (frame.width - side) / 2;
+        let top = (frame.height - side) / 2;
+        let mut rgba = Vec::with_capacity((ICON_SIZE * ICON_SIZE * 4) as usize);
+        for icon_y in 0..ICON_SIZE {
+            let y_range = icon_box(icon_y, side);
+            for icon_x in 0..ICON_SIZE {
+                let x_range = icon_box(icon_x, side);
+                let mut sum = [0u32; 3];
+                let mut count = 0;
+                for y in y_range.clone() {
+                    for x in x_range.clone() {
+                        let index = (((top + y) * frame.width + left + x) * 4) as usize;
+                        for (total, &channel) in sum.iter_mut().zip(&frame.pixels[index..index + 3])
+                        {
+                            *total += channel as u32;
+                        }
+                        count += 1;
+                    }
+                }
+                rgba.extend(sum.map(|total| (total / count) as u8));
+                // the surface's alpha isn't meant to be seen, so the icon is opaque
+                rgba.push(u8::MAX);
+            }
+        }
+        match Icon::from_rgba(rgba, ICON_SIZE, ICON_SIZE) {
+            Ok(icon) => self.icon = Some(icon),
+            Err(e) => log::error!("Could not make window icon: {}", e),
+        }
+    }
+
+    // the latest icon, if it hasn't been shown yet
+    pub(super) fn take(&mut self) -> Option<Icon> {
+        self.icon.take()
+    }
+}
@@ -0,0 +1,85 @@
+use naga::{
+    front::wgsl,
+    valid::{Capabilities, ValidationFlags, Validator},
+};
+use std::error::Error;
+
+use super::{
+    geometry::pass_entry_points, include::line_map, reflect::missing_entry_point, shader_passes,
+};
+use crate::config::Config;
+
+// 1-based line number of a byte offset into some source
+fn line_of(source: &str, offset: usize) -> usize {
+    source[..offset.min(source.len())].matches('\n').count() + 1
+}
+
+// a line of a pass's source, as the file and line it came from if the line map says
+fn describe_line(lines: &[Option<(String, usize)>], line: usize) -> String {
+    match lines.get(line - 1) {
+        Some(Some((path, number))) => format!("{} line {}", path, number),
+        _ => format!("line {}", line),
+    }
+}
+
+/// Parse and validate every pass of the shader, without a window or GPU, for --check
+///
+/// Prints each error it finds, and returns whether there weren't any.
+pub(crate) fn check_shader(config: &Config) -> bool {
+    let sources = match shader_passes(config) {
+        Ok(sources) => sources,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            return false;
+        }
+    };
+    // a pass's source is the --defines, the shared prelude with includes inlined, and its own
+    // section, so lines are counted in that and mapped back to the files they came from
+    let mut valid = true;
+    for source in &sources {
+        let lines = line_map(&source.source);
+        let module = match wgsl::parse_str(&source.source) {
+            Ok(module) => module,
+            Err(error) => {
+                eprintln!("in pass `{}`:", source.name);
+                eprint!("{}", error.emit_to_string(&source.source));
+                let (line, _) = error.location(&source.source);
+                if let Some(Some((path, number))) = lines.get(line - 1) {
+                    eprintln!("  line {} is {} line {}", line, path, number);
+                }
+                valid = false;
+                continue;
+            }
+        };
+        let mut validator = Validator::new(ValidationFlags::all(), Capabilities::all());
+        if let Err(error) = validator.validate(&module) {
+            eprintln!("in pass `{}`:", source.name);
+            eprintln!("error: {}", error);
+            let mut cause = error.source();
+            while let Some(error) = cause {
+                eprintln!("  caused by: {}", error);
+                cause = error.source();
+            }
+            for (span, label) in error.spans() {
+                if let Some(range) = span.to_range() {
+                    let line = line_of(&source.source, range.start);
+                    let text = source.source.lines().nth(line - 1).unwrap_or_default();
+                    eprintln!("  {}: {}", describe_line(&lines, line), label);
+                    eprintln!("    {}", text.trim());
+                }
+            }
+            valid = false;
+            continue;
+        }
+        let (vertex, fragment) = pass_entry_points(config);
+        if let Some(error) = missing_entry_point(&source.source, vertex, fragment) {
+            eprintln!("in pass `{}`:", source.name);
+            eprintln!("error: {}", error);
+            valid = false;
+        }
+    }
+    if valid {
+        println!("Shader is valid");
+    }
+    valid
+}
@@ -0,0 +1,51 @@
+// Draws a --layer's output over the shader's, faded by its opacity
+
+struct Layer {
+    opacity: f32;
+    // the --blend mode, 0 over, 1 add, 2 multiply, 3 screen
+    mode: u32;
+};
+
+[[group(0), binding(0)]]
+var layer_texture: texture_2d<f32>;
+[[group(0), binding(1)]]
+var layer_sampler: sampler;
+
+[[group(1), binding(0)]]
+var<uniform> layer: Layer;
+
+let MULTIPLY: u32 = 2u;
+let SCREEN: u32 = 3u;
+
+struct VertexInput {
+    [[location(0)]] position: vec3<f32>;
+    [[location(1)]] tex_coords: vec2<f32>;
+};
+
+struct VertexOutput {
+    [[builtin(position)]] clip_position: vec4<f32>;
+    [[location(0)]] tex_coords: vec2<f32>;
+};
+
+[[stage(vertex)]]
+fn vs_main(model: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.tex_coords = model.tex_coords;
+    out.clip_position = vec4<f32>(model.position, 1.0);
+    return out;
+}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    // textures have y pointing down, the opposite way to tex_coords
+    let colour = textureSample(layer_texture, layer_sampler, vec2<f32>(in.tex_coords.x, 1.0 - in.tex_coords.y));
+    let alpha = colour.a * layer.opacity;
+    // multiply and screen ignore alpha, so fade towards the colour that leaves the target alone
+    if (layer.mode == MULTIPLY) {
+        return vec4<f32>(mix(vec3<f32>(1.0), colour.rgb, alpha), alpha);
+    }
+    if (layer.mode == SCREEN) {
+        return vec4<f32>(colour.rgb * alpha, alpha);
+    }
+    return vec4<f32>(colour.rgb, alpha);
+}
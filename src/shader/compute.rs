@@ -0,0 +1,218 @@
+use std::path::{Path, PathBuf};
+use wgpu::*;
+
+use super::{
+    new_shader,
+    reflect::{missing_compute_entry_point, parse_error, workgroup_size},
+    source::read_source,
+    uniforms::bindings::{uniforms_bind_group, uniforms_bind_group_layout, StorageAccess},
+    with_defines,
+};
+use crate::config::{Config, Workgroups};
+
+// the function the --compute shader is dispatched at
+const COMPUTE_ENTRY_POINT: &str = "cs_main";
+
+/// The --compute shader, dispatched before each frame's passes to fill the --storage buffer
+///
+/// Its `[[group(0)]]` has the same uniforms at the same bindings as the fragment shader's, with
+/// the storage buffer as `var<storage, read_write>`, so a simulation can step itself each frame
+/// before the fragment shader reads it.
+#[derive(Debug)]
+pub(super) struct Compute {
+    layout: BindGroupLayout,
+    // where the storage buffer is bound in the group
+    storage_binding: u32,
+    // None until the uniforms are bound, once their buffers fit the shader
+    bind_group: Option<BindGroup>,
+    // None until the shader's loaded
+    pipeline: Option<ComputePipeline>,
+    // files the shader included, for --watch
+    includes: Vec<PathBuf>,
+    // how many workgroups the shader's dispatched with, which can depend on its workgroup size
+    workgroups: Workgroups,
+    // bytes in the storage buffer, to dispatch an invocation per u32 of without --workgroups
+    storage_size: u64,
+}
+
+/// A --compute shader that built, to replace the current one with
+#[derive(Debug)]
+pub(super) struct BuiltCompute {
+    pipeline: ComputePipeline,
+    workgroups: Workgroups,
+    includes: Vec<PathBuf>,
+}
+
+impl Compute {
+    pub(super) fn new(
+        device: &Device,
+        dynamic: &[bool],
+        storage_binding: u32,
+        storage_size: u64,
+    ) -> Self {
+        let layout = uniforms_bind_group_layout(
+            device,
+            "Compute Bind Group Layout",
+            dynamic,
+            Some((storage_binding, StorageAccess::Compute)),
+        );
+        Self {
+            layout,
+            storage_binding,
+            bind_group: None,
+            pipeline: None,
+            includes: Vec::new(),
+            workgroups: Workgroups { x: 1, y: 1, z: 1 },
+            storage_size,
+        }
+    }
+
+    // the uniforms, at the same bindings as the fragment shader's, and the storage buffer
+    pub(super) fn build_bind_group(
+        &self,
+        device: &Device,
+        uniform_buffers: Vec<BindingResource>,
+        storage: &Buffer,
+    ) -> BindGroup {
+        uniforms_bind_group(
+            device,
+            "Compute Bind Group",
+            &self.layout,
+            uniform_buffers,
+            Some((self.storage_binding, storage)),
+        )
+    }
+
+    pub(super) fn set_bind_group(&mut self, bind_group: BindGroup) {
+        self.bind_group = Some(bind_group);
+    }
+
+    // read the shader from disk and build its pipeline, without replacing the current one,
+    // so a mistake keeps the last one that worked
+    // also works out how many workgroups to dispatch, and returns the files the shader includes
+    pub(super) fn build_pipeline(
+        &self,
+        device: &Device,
+        config: &Config,
+        path: &str,
+    ) -> Result<BuiltCompute, String> {
+        log::info!("Reading compute shader {}", path);
+        let (source, includes) = read_source(Path::new(path), &config.fragment_entry)
+            .map_err(|error| format!("Failed reading compute shader {}: {}", path, error))?;
+        let source = with_defines(config, &source);
+        if let Some(error) = parse_error(&source) {
+            return Err(format!("Compute shader doesn't parse:\n{}", error));
+        }
+        if let Some(error) = missing_compute_entry_point(&source, COMPUTE_ENTRY_POINT) {
+            return Err(format!("Compute shader {}", error));
+        }
+        let size = workgroup_size(&source, COMPUTE_ENTRY_POINT).unwrap_or([1, 1, 1]);
+        let workgroups = self.workgroups(device, config, size)?;
+        log::info!(
+            "Dispatching compute shader as {}x{}x{} workgroups of {}x{}x{}",
+            workgroups.x,
+            workgroups.y,
+            workgroups.z,
+            size[0],
+            size[1],
+            size[2]
+        );
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Compute Pipeline Layout"),
+            bind_group_layouts: &[&self.layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("Compute Pipeline"),
+            layout: Some(&layout),
+            module: &new_shader(device, "Compute Shader", source),
+            entry_point: COMPUTE_ENTRY_POINT,
+        });
+        Ok(BuiltCompute {
+            pipeline,
+            workgroups,
+            includes,
+        })
+    }
+
+    // the --workgroups to dispatch, or enough for every u32 of the storage buffer without it,
+    // checking the shader's workgroup size and the dispatch both fit the GPU adapter
+    fn workgroups(
+        &self,
+        device: &Device,
+        config: &Config,
+        size: [u32; 3],
+    ) -> Result<Workgroups, String> {
+        let limits = device.limits();
+        let max_size = [
+            limits.max_compute_workgroup_size_x,
+            limits.max_compute_workgroup_size_y,
+            limits.max_compute_workgroup_size_z,
+        ];
+        let invocations: u32 = size.iter().product();
+        if size.iter().zip(max_size).any(|(&size, max)| size > max)
+            || invocations > limits.max_compute_invocations_per_workgroup
+        {
+            return Err(format!(
+                "Compute shader's workgroup size is {}x{}x{}, but the GPU adapter allows at most \
+                {}x{}x{}, and {} invocations in all",
+                size[0],
+                size[1],
+                size[2],
+                max_size[0],
+                max_size[1],
+                max_size[2],
+                limits.max_compute_invocations_per_workgroup
+            ));
+        }
+        let max = limits.max_compute_workgroups_per_dimension;
+        let workgroups = match config.workgroups {
+            Some(workgroups) => workgroups,
+            None => {
+                let x = (self.storage_size / 4).div_ceil(u64::from(invocations));
+                Workgroups {
+                    x: u32::try_from(x).unwrap_or(u32::MAX),
+                    y: 1,
+                    z: 1,
+                }
+            }
+        };
+        if [workgroups.x, workgroups.y, workgroups.z]
+            .iter()
+            .any(|&count| count > max)
+        {
+            return Err(format!(
+                "Compute shader would be dispatched as {}x{}x{} workgroups, but the GPU adapter \
+                allows at most {} on each axis",
+                workgroups.x, workgroups.y, workgroups.z, max
+            ));
+        }
+        Ok(workgroups)
+    }
+
+    pub(super) fn set_pipeline(&mut self, built: BuiltCompute) {
+        self.pipeline = Some(built.pipeline);
+        self.workgroups = built.workgroups;
+        self.includes = built.includes;
+    }
+
+    pub(super) fn includes(&self) -> &[PathBuf] {
+        &self.includes
+    }
+
+    // run the shader over every workgroup, with the uniform rings at the same offsets as the
+    // frame's passes
+    pub(super) fn dispatch(&self, encoder: &mut CommandEncoder, dynamic_offsets: &[DynamicOffset]) {
+        let (pipeline, bind_group) = match (&self.pipeline, &self.bind_group) {
+            (Some(pipeline), Some(bind_group)) => (pipeline, bind_group),
+            _ => return,
+        };
+        let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some("Compute Pass"),
+        });
+        compute_pass.set_pipeline(pipeline);
+        compute_pass.set_bind_group(0, bind_group, dynamic_offsets);
+        let Workgroups { x, y, z } = self.workgroups;
+        compute_pass.dispatch(x, y, z);
+    }
+}
@@ -0,0 +1,44 @@
+// Copies the shader's output onto the window, stretched to fit if it's rendered at a --scale
+// Also shows the --accumulate average, which the shader's last pass blends each frame into
+
+[[group(0), binding(0)]]
+var input_texture: texture_2d<f32>;
+[[group(0), binding(1)]]
+var input_sampler: sampler;
+
+struct VertexInput {
+    [[location(0)]] position: vec3<f32>;
+    [[location(1)]] tex_coords: vec2<f32>;
+};
+
+struct VertexOutput {
+    [[builtin(position)]] clip_position: vec4<f32>;
+    [[location(0)]] tex_coords: vec2<f32>;
+};
+
+[[stage(vertex)]]
+fn vs_main(model: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.tex_coords = model.tex_coords;
+    out.clip_position = vec4<f32>(model.position, 1.0);
+    return out;
+}
+
+// for --fullscreen-tri
+[[stage(vertex)]]
+fn vs_fullscreen([[builtin(vertex_index)]] index: u32) -> VertexOutput {
+    let corner = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.tex_coords = corner;
+    out.clip_position = vec4<f32>(corner * 2.0 - 1.0, 0.0, 1.0);
+    return out;
+}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    // the input is a different size from the window when scaled, so it's sampled by
+    // tex_coords instead of pixel position
+    // textures have y pointing down, the opposite way to tex_coords
+    let uv = vec2<f32>(in.tex_coords.x, 1.0 - in.tex_coords.y);
+    return textureSample(input_texture, input_sampler, uv);
+}
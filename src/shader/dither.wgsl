@@ -0,0 +1,70 @@
+// Ordered dithering, to break up banding when the shader's output
+// is squashed into an 8-bit surface
+
+// prepended by the host: whether the surface converts from linear to sRGB on write
+// let SRGB_SURFACE: bool = ...;
+
+[[group(0), binding(0)]]
+var input_texture: texture_2d<f32>;
+[[group(0), binding(1)]]
+var input_sampler: sampler;
+
+struct VertexInput {
+    [[location(0)]] position: vec3<f32>;
+    [[location(1)]] tex_coords: vec2<f32>;
+};
+
+[[stage(vertex)]]
+fn vs_main(model: VertexInput) -> [[builtin(position)]] vec4<f32> {
+    return vec4<f32>(model.position, 1.0);
+}
+
+// for --fullscreen-tri
+[[stage(vertex)]]
+fn vs_fullscreen([[builtin(vertex_index)]] index: u32) -> [[builtin(position)]] vec4<f32> {
+    let corner = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(corner * 2.0 - 1.0, 0.0, 1.0);
+}
+
+fn linear_to_srgb(linear: vec3<f32>) -> vec3<f32> {
+    return select(
+        1.055 * pow(linear, vec3<f32>(1.0 / 2.4)) - 0.055,
+        linear * 12.92,
+        linear <= vec3<f32>(0.0031308)
+    );
+}
+
+fn srgb_to_linear(srgb: vec3<f32>) -> vec3<f32> {
+    return select(
+        pow((srgb + 0.055) / 1.055, vec3<f32>(2.4)),
+        srgb / 12.92,
+        srgb <= vec3<f32>(0.04045)
+    );
+}
+
+[[stage(fragment)]]
+fn fs_main([[builtin(position)]] position: vec4<f32>) -> [[location(0)]] vec4<f32> {
+    let uv = position.xy / vec2<f32>(textureDimensions(input_texture));
+    let colour = textureSample(input_texture, input_sampler, uv);
+
+    // 4x4 Bayer matrix, spreads thresholds evenly over each 4x4 block of pixels
+    var bayer = array<f32, 16>(
+        0.0, 8.0, 2.0, 10.0,
+        12.0, 4.0, 14.0, 6.0,
+        3.0, 11.0, 1.0, 9.0,
+        15.0, 7.0, 13.0, 5.0
+    );
+    let cell = vec2<u32>(position.xy) % vec2<u32>(4u);
+    // offset of up to half an 8-bit step either way
+    let offset = ((bayer[cell.y * 4u + cell.x] + 0.5) / 16.0 - 0.5) / 255.0;
+
+    // the 8-bit steps are evenly spaced in whatever space gets stored,
+    // so dither in sRGB space if the surface will encode to sRGB
+    var rgb = clamp(colour.rgb, vec3<f32>(0.0), vec3<f32>(1.0));
+    if (SRGB_SURFACE) {
+        rgb = srgb_to_linear(linear_to_srgb(rgb) + offset);
+    } else {
+        rgb = rgb + offset;
+    }
+    return vec4<f32>(rgb, colour.a);
+}
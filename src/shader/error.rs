@@ -0,0 +1,83 @@
+use std::fmt;
+use wgpu::{Backend, RequestDeviceError};
+
+use crate::config::GpuBackend;
+
+/// Why a State couldn't be made
+#[derive(Debug)]
+pub(crate) enum StateError {
+    // no adapter at either power preference, on the --backend asked for
+    NoAdapter {
+        fallback: bool,
+        backend: GpuBackend,
+    },
+    // the adapter was found, but wouldn't give a device with the features and limits needed
+    Device {
+        adapter: String,
+        error: RequestDeviceError,
+    },
+    // options that can't be used together
+    Config(String),
+    // frames have to be read back, but the backend's surfaces can't be copied from
+    Readback(Backend),
+    // the shader, or a file it needs, didn't load
+    Shader(String),
+    // the --mesh didn't load
+    Mesh(String),
+    // the --audio file didn't load
+    Audio(String),
+    // a --texture, --background-image or --reference image didn't load, with a message that
+    // already says which
+    Texture(String),
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateError::NoAdapter {
+                fallback: false,
+                backend: GpuBackend::All,
+            } => write!(
+                f,
+                "No GPU adapter found that can draw here. Check the graphics drivers support \
+                Vulkan, Metal, DX12 or OpenGL, or try --fallback-adapter for a software renderer"
+            ),
+            StateError::NoAdapter {
+                fallback: true,
+                backend: GpuBackend::All,
+            } => write!(f, "No GPU adapter found, not even a fallback one"),
+            StateError::NoAdapter { fallback, backend } => write!(
+                f,
+                "No {}GPU adapter found for the {} backend. It may not be supported on this \
+                platform or by the graphics drivers, so try another --backend, or leave it out \
+                to let any backend be used",
+                if *fallback { "fallback " } else { "" },
+                backend.name()
+            ),
+            StateError::Device { adapter, error } => write!(
+                f,
+                "Could not get a device from GPU adapter {}: {}. It may not support the \
+                --features or texture formats asked for",
+                adapter, error
+            ),
+            StateError::Config(error) => write!(f, "{}", error),
+            StateError::Readback(backend) => write!(
+                f,
+                "The {:?} backend can't copy frames out of the window, which --raw, --pipe, --gif \
+                and --live-icon need. Try another --backend, or render without a window with \
+                --output, or --gif with --size",
+                backend
+            ),
+            StateError::Shader(error) => write!(f, "Failed loading shader: {}", error),
+            StateError::Mesh(error) => write!(f, "Failed loading mesh: {}", error),
+            StateError::Audio(error) => write!(f, "Failed loading audio: {}", error),
+            StateError::Texture(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl From<String> for StateError {
+    fn from(error: String) -> Self {
+        StateError::Shader(error)
+    }
+}
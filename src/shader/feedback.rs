@@ -0,0 +1,88 @@
+use wgpu::*;
+
+use super::passes::{input_bind_group, input_bind_group_layout, PassTarget};
+
+// bind group the feedback buffer is sampled from, after the pass inputs in group 2
+pub(super) const FEEDBACK_GROUP: u32 = 3;
+
+/// The --feedback buffer, drawn by its own pass before the shader's, like Shadertoy's Buffer A
+///
+/// It's double buffered, so the feedback pass samples what it drew last frame while it draws
+/// this one, and the shader's passes sample what it drew this frame. Either way it's bound in
+/// group 3 as a `texture_2d<f32>` at binding 0 with a `sampler` at binding 1.
+#[derive(Debug)]
+pub(super) struct Feedback {
+    layout: BindGroupLayout,
+    // drawn to in turn, so one keeps last frame while the other is drawn
+    targets: [PassTarget; 2],
+    // each samples the target at the same index
+    bind_groups: [BindGroup; 2],
+    // the target being drawn this frame
+    current: usize,
+}
+
+impl Feedback {
+    pub(super) fn new(device: &Device, sampler: &Sampler, width: u32, height: u32) -> Self {
+        let layout = input_bind_group_layout(device, 1, &[]);
+        let (targets, bind_groups) = Self::buffers(device, &layout, sampler, width, height);
+        Self {
+            layout,
+            targets,
+            bind_groups,
+            current: 0,
+        }
+    }
+
+    // two cleared targets and their bind groups
+    fn buffers(
+        device: &Device,
+        layout: &BindGroupLayout,
+        sampler: &Sampler,
+        width: u32,
+        height: u32,
+    ) -> ([PassTarget; 2], [BindGroup; 2]) {
+        let targets = [0, 1].map(|index| {
+            PassTarget::new(
+                device,
+                &format!("Feedback Target {}", index),
+                width,
+                height,
+                false,
+            )
+        });
+        let bind_groups = [0, 1]
+            .map(|index| input_bind_group(device, layout, sampler, &[targets[index].view()], &[]));
+        (targets, bind_groups)
+    }
+
+    pub(super) fn layout(&self) -> &BindGroupLayout {
+        &self.layout
+    }
+
+    // start a new frame, so last frame's target is kept and the other one gets drawn
+    pub(super) fn swap(&mut self) {
+        self.current = 1 - self.current;
+    }
+
+    // where the feedback pass draws this frame
+    pub(super) fn target(&self) -> &TextureView {
+        self.targets[self.current].render_view()
+    }
+
+    // last frame, for the feedback pass
+    pub(super) fn previous(&self) -> &BindGroup {
+        &self.bind_groups[1 - self.current]
+    }
+
+    // this frame, for the shader's passes once the feedback pass has drawn it
+    pub(super) fn latest(&self) -> &BindGroup {
+        &self.bind_groups[self.current]
+    }
+
+    // the new targets start out cleared, so the feedback starts over
+    pub(super) fn resize(&mut self, device: &Device, sampler: &Sampler, width: u32, height: u32) {
+        let (targets, bind_groups) = Self::buffers(device, &self.layout, sampler, width, height);
+        self.targets = targets;
+        self.bind_groups = bind_groups;
+    }
+}
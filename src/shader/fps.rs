@@ -0,0 +1,61 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+// frame times averaged over, about a second at 60fps
+const AVERAGE_FRAMES: usize = 60;
+// a few updates a second is readable, where every frame would just flicker
+const TITLE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Frame rate and frame time averaged over the last second or so, for --show-fps
+#[derive(Debug)]
+pub(super) struct FpsTitle {
+    // the --title, which the readout goes after
+    prefix: String,
+    // latest frame times in seconds, oldest first
+    frame_times: VecDeque<f64>,
+    // when the title was last made, or None before the first one
+    last_title: Option<Instant>,
+}
+
+impl FpsTitle {
+    pub(super) fn new(prefix: &str) -> Self {
+        Self {
+            prefix: prefix.to_owned(),
+            frame_times: VecDeque::with_capacity(AVERAGE_FRAMES),
+            last_title: None,
+        }
+    }
+
+    pub(super) fn push(&mut self, frame_time: Duration) {
+        if self.frame_times.len() == AVERAGE_FRAMES {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(frame_time.as_secs_f64());
+    }
+
+    // a new title, if it's time for one
+    pub(super) fn take(&mut self) -> Option<String> {
+        let now = Instant::now();
+        if self
+            .last_title
+            .is_some_and(|last| now - last < TITLE_INTERVAL)
+        {
+            return None;
+        }
+        // the mean frame time rather than the mean of each frame's rate, so one slow frame
+        // counts for as long as it took
+        let average = self.frame_times.iter().sum::<f64>() / self.frame_times.len().max(1) as f64;
+        if average <= 0.0 {
+            return None;
+        }
+        self.last_title = Some(now);
+        Some(format!(
+            "{} - {:.0} fps ({:.2}ms)",
+            self.prefix,
+            1.0 / average,
+            average * 1000.0
+        ))
+    }
+}
@@ -0,0 +1,83 @@
+// Fast approximate antialiasing, after Timothy Lottes' FXAA
+// finds edges by their contrast in luma, then blurs along them
+
+[[group(0), binding(0)]]
+var input_texture: texture_2d<f32>;
+[[group(0), binding(1)]]
+var input_sampler: sampler;
+
+struct VertexInput {
+    [[location(0)]] position: vec3<f32>;
+    [[location(1)]] tex_coords: vec2<f32>;
+};
+
+[[stage(vertex)]]
+fn vs_main(model: VertexInput) -> [[builtin(position)]] vec4<f32> {
+    return vec4<f32>(model.position, 1.0);
+}
+
+// for --fullscreen-tri
+[[stage(vertex)]]
+fn vs_fullscreen([[builtin(vertex_index)]] index: u32) -> [[builtin(position)]] vec4<f32> {
+    let corner = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(corner * 2.0 - 1.0, 0.0, 1.0);
+}
+
+// smallest amount the blur direction gets scaled down by
+let REDUCE_MIN: f32 = 0.0078125; // 1 / 128
+// how much bright neighbourhoods shrink the blur
+let REDUCE_MUL: f32 = 0.125; // 1 / 8
+// longest blur, in pixels
+let SPAN_MAX: f32 = 8.0;
+
+fn luma(colour: vec3<f32>) -> f32 {
+    return dot(colour, vec3<f32>(0.299, 0.587, 0.114));
+}
+
+fn sample(uv: vec2<f32>) -> vec4<f32> {
+    return textureSample(input_texture, input_sampler, uv);
+}
+
+[[stage(fragment)]]
+fn fs_main([[builtin(position)]] position: vec4<f32>) -> [[location(0)]] vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(input_texture));
+    let uv = position.xy * texel;
+
+    // luma at the pixel and its diagonal neighbours
+    let centre = sample(uv);
+    let luma_centre = luma(centre.rgb);
+    let luma_nw = luma(sample(uv + vec2<f32>(-1.0, -1.0) * texel).rgb);
+    let luma_ne = luma(sample(uv + vec2<f32>(1.0, -1.0) * texel).rgb);
+    let luma_sw = luma(sample(uv + vec2<f32>(-1.0, 1.0) * texel).rgb);
+    let luma_se = luma(sample(uv + vec2<f32>(1.0, 1.0) * texel).rgb);
+    let luma_min = min(luma_centre, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    let luma_max = max(luma_centre, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+    // the edge runs perpendicular to the luma gradient
+    var direction = vec2<f32>(
+        (luma_sw + luma_se) - (luma_nw + luma_ne),
+        (luma_nw + luma_sw) - (luma_ne + luma_se)
+    );
+    let reduce = max(
+        (luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * REDUCE_MUL,
+        REDUCE_MIN
+    );
+    let scale = 1.0 / (min(abs(direction.x), abs(direction.y)) + reduce);
+    direction = clamp(direction * scale, vec2<f32>(-SPAN_MAX), vec2<f32>(SPAN_MAX)) * texel;
+
+    // average along the edge, near and far
+    let near = 0.5 * (
+        sample(uv + direction * (1.0 / 3.0 - 0.5)).rgb
+        + sample(uv + direction * (2.0 / 3.0 - 0.5)).rgb
+    );
+    let far = near * 0.5 + 0.25 * (
+        sample(uv - direction * 0.5).rgb
+        + sample(uv + direction * 0.5).rgb
+    );
+    // the far samples overshot the edge if they left the neighbourhood's luma range
+    let luma_far = luma(far);
+    if (luma_far < luma_min || luma_far > luma_max) {
+        return vec4<f32>(near, centre.a);
+    }
+    return vec4<f32>(far, centre.a);
+}
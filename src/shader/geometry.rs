@@ -1,38 +1,61 @@
 use bytemuck::{Pod, Zeroable};
-use wgpu::{VertexBufferLayout, BufferAddress, vertex_attr_array, VertexAttribute, VertexStepMode};
+use std::path::Path;
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    vertex_attr_array, Buffer, BufferAddress, BufferUsages, Device, IndexFormat, RenderPass,
+    VertexAttribute, VertexBufferLayout, VertexStepMode,
+};
+
+use super::super::config::Config;
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Pod, Zeroable)]
 pub(super) struct Vertex {
     position: [f32; 3],
     tex_coords: [f32; 2],
+    // for lighting, meshes without normals get QUAD_NORMAL
+    normal: [f32; 3],
 }
 
-// square, a quad's corners
-pub(super) const VERTICES: &[Vertex] = &[
+// the quad lies flat in the screen, facing the viewer
+const QUAD_NORMAL: [f32; 3] = [0.0, 0.0, 1.0];
+
+// square, a quad's corners, covering the whole screen
+// clip space has y pointing up, and so do the tex_coords
+const VERTICES: &[Vertex] = &[
     Vertex {
         position: [-1.0, -1.0, 0.0],
         tex_coords: [0.0, 0.0],
-    }, // Top left
+        normal: QUAD_NORMAL,
+    }, // Bottom left
     Vertex {
         position: [1.0, -1.0, 0.0],
         tex_coords: [1.0, 0.0],
-    }, // Top right
+        normal: QUAD_NORMAL,
+    }, // Bottom right
     Vertex {
         position: [1.0, 1.0, 0.0],
         tex_coords: [1.0, 1.0],
-    }, // Bottom left
+        normal: QUAD_NORMAL,
+    }, // Top right
     Vertex {
         position: [-1.0, 1.0, 0.0],
         tex_coords: [0.0, 1.0],
-    }, // Bottom right
+        normal: QUAD_NORMAL,
+    }, // Top left
 ];
 
-// a simple quad shape
-pub(super) const INDICES: &[u16] = &[2, 3, 0, 1, 2, 0];
+// a simple quad shape, as two triangles
+// both go counter-clockwise around the screen, so they face forward
+// and survive back-face culling
+const INDICES: &[u16] = &[
+    0, 1, 2, // bottom left, bottom right, top right
+    0, 2, 3, // bottom left, top right, top left
+];
 
 impl Vertex {
-    const ATTRIBS: [VertexAttribute; 2] = vertex_attr_array![0 => Float32x3, 1 => Float32x2];
+    const ATTRIBS: [VertexAttribute; 3] =
+        vertex_attr_array![0 => Float32x3, 1 => Float32x2, 2 => Float32x3];
 
     pub(super) fn desc<'pipeline>() -> VertexBufferLayout<'pipeline> {
         use std::mem;
@@ -43,3 +66,227 @@ impl Vertex {
         }
     }
 }
+
+/// How shader passes cover the screen
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum ScreenGeometry {
+    // the quad from the vertex and index buffers, drawn by `vs_main`
+    Quad,
+    // one oversized triangle with no vertex buffer, drawn by `vs_fullscreen` from `vertex_index`
+    // avoids the quad's diagonal seam, with --fullscreen-tri
+    Triangle,
+}
+
+impl ScreenGeometry {
+    pub(super) fn new(config: &Config) -> Self {
+        if config.fullscreen_tri {
+            ScreenGeometry::Triangle
+        } else {
+            ScreenGeometry::Quad
+        }
+    }
+
+    pub(super) fn vertex_entry_point(self) -> &'static str {
+        match self {
+            ScreenGeometry::Quad => "vs_main",
+            ScreenGeometry::Triangle => "vs_fullscreen",
+        }
+    }
+}
+
+// the vertex and fragment entry points of the shader's own passes, from --vertex-entry and
+// --fragment-entry
+// built-in passes always use the usual ones
+pub(super) fn pass_entry_points(config: &Config) -> (&str, &str) {
+    let vertex = config
+        .vertex_entry
+        .as_deref()
+        .unwrap_or_else(|| ScreenGeometry::new(config).vertex_entry_point());
+    (vertex, &config.fragment_entry)
+}
+
+/// Vertices on the GPU, and indices into them if the mesh has any
+#[derive(Debug)]
+pub(super) struct Mesh {
+    vertex_buffer: Buffer,
+    index_buffer: Option<Buffer>,
+    // Uint16 for the quad, Uint32 for --mesh, which can have more vertices than u16 reaches
+    index_format: IndexFormat,
+    // number of indices, or of vertices if there are no indices
+    count: u32,
+}
+
+impl Mesh {
+    pub(super) fn new(device: &Device, vertices: &[Vertex], indices: Option<&[u16]>) -> Self {
+        let count = indices.map_or(vertices.len(), <[u16]>::len) as u32;
+        Self::with_indices(
+            device,
+            vertices,
+            indices.map(bytemuck::cast_slice),
+            IndexFormat::Uint16,
+            count,
+        )
+    }
+
+    fn with_indices(
+        device: &Device,
+        vertices: &[Vertex],
+        indices: Option<&[u8]>,
+        index_format: IndexFormat,
+        count: u32,
+    ) -> Self {
+        let vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(vertices),
+            usage: BufferUsages::VERTEX,
+        });
+        let index_buffer = indices.map(|indices| {
+            device.create_buffer_init(&BufferInitDescriptor {
+                label: Some("Index Buffer"),
+                contents: indices,
+                usage: BufferUsages::INDEX,
+            })
+        });
+        Self {
+            vertex_buffer,
+            index_buffer,
+            index_format,
+            count,
+        }
+    }
+
+    // the fullscreen quad
+    // without indices, each triangle gets its own copy of the shared corners
+    pub(super) fn quad(device: &Device, indexed: bool) -> Self {
+        Self::scaled_quad(device, indexed, 1.0)
+    }
+
+    // the quad shrunk towards the centre of the screen, by a fraction of its size
+    // tex_coords still cover 0..1, so shaders see the same coordinates across it
+    pub(super) fn scaled_quad(device: &Device, indexed: bool, scale: f32) -> Self {
+        let vertices: Vec<Vertex> = VERTICES
+            .iter()
+            .map(|vertex| Vertex {
+                position: vertex.position.map(|coordinate| coordinate * scale),
+                ..*vertex
+            })
+            .collect();
+        if indexed {
+            Self::new(device, &vertices, Some(INDICES))
+        } else {
+            let vertices: Vec<Vertex> = INDICES
+                .iter()
+                .map(|&index| vertices[usize::from(index)])
+                .collect();
+            Self::new(device, &vertices, None)
+        }
+    }
+
+    // the models in an OBJ file, for --mesh, drawn in place of the quad
+    // faces are triangulated, and every model needs texture coordinates, since shaders are
+    // written against tex_coords
+    pub(super) fn load_obj(device: &Device, path: &Path, indexed: bool) -> Result<Self, String> {
+        let options = tobj::LoadOptions {
+            single_index: true,
+            triangulate: true,
+            ignore_points: true,
+            ignore_lines: true,
+        };
+        let (models, _materials) = tobj::load_obj(path, &options)
+            .map_err(|error| format!("failed loading {}: {}", path.display(), error))?;
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        for model in &models {
+            let mesh = &model.mesh;
+            if mesh.texcoords.is_empty() {
+                return Err(format!(
+                    "{}: model `{}` has no texture coordinates (`vt` lines), \
+                    which shaders need for tex_coords",
+                    path.display(),
+                    model.name
+                ));
+            }
+            // models share the buffers, so each one's indices start after the last one's vertices
+            let offset = vertices.len() as u32;
+            vertices.extend((0..mesh.positions.len() / 3).map(|index| Vertex {
+                position: [
+                    mesh.positions[3 * index],
+                    mesh.positions[3 * index + 1],
+                    mesh.positions[3 * index + 2],
+                ],
+                tex_coords: [mesh.texcoords[2 * index], mesh.texcoords[2 * index + 1]],
+                normal: if mesh.normals.is_empty() {
+                    QUAD_NORMAL
+                } else {
+                    [
+                        mesh.normals[3 * index],
+                        mesh.normals[3 * index + 1],
+                        mesh.normals[3 * index + 2],
+                    ]
+                },
+            }));
+            indices.extend(mesh.indices.iter().map(|&index| index + offset));
+        }
+        if indices.is_empty() {
+            return Err(format!("{} has no faces", path.display()));
+        }
+        log::info!(
+            "Loaded {} vertices and {} triangles from {}",
+            vertices.len(),
+            indices.len() / 3,
+            path.display()
+        );
+        if indexed {
+            let count = indices.len() as u32;
+            Ok(Self::with_indices(
+                device,
+                &vertices,
+                Some(bytemuck::cast_slice(&indices)),
+                IndexFormat::Uint32,
+                count,
+            ))
+        } else {
+            let vertices: Vec<Vertex> = indices
+                .iter()
+                .map(|&index| vertices[index as usize])
+                .collect();
+            Ok(Self::new(device, &vertices, None))
+        }
+    }
+
+    // bind the buffers and draw one instance
+    pub(super) fn draw<'pass>(&'pass self, render_pass: &mut RenderPass<'pass>) {
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        match &self.index_buffer {
+            Some(index_buffer) => {
+                render_pass.set_index_buffer(index_buffer.slice(..), self.index_format);
+                render_pass.draw_indexed(0..self.count, 0, 0..1);
+            }
+            None => render_pass.draw(0..self.count, 0..1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // twice the triangle's area, positive when it goes counter-clockwise with y up
+    fn signed_area(triangle: &[u16]) -> f32 {
+        let [a, b, c] = [0, 1, 2].map(|corner| VERTICES[usize::from(triangle[corner])].position);
+        (b[0] - a[0]) * (c[1] - a[1]) - (c[0] - a[0]) * (b[1] - a[1])
+    }
+
+    // pipelines use `FrontFace::Ccw` and cull `Face::Back`, so a clockwise triangle would
+    // leave a blank half of the screen
+    #[test]
+    fn quad_triangles_face_forward() {
+        for triangle in INDICES.chunks(3) {
+            assert!(
+                signed_area(triangle) > 0.0,
+                "triangle {:?} is clockwise, so it gets culled",
+                triangle
+            );
+        }
+    }
+}
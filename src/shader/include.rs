@@ -0,0 +1,226 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+const INCLUDE_MARKER: &str = "//!include";
+// says which file and line the lines after it came from, as `//!line 12 "path"`
+// left in place of includes and in front of each pass, so errors in the assembled source can
+// point at the file that was written
+const LINE_MARKER: &str = "//!line";
+
+// a LINE_MARKER line, saying the next line is line `line` of `path`
+pub(super) fn line_marker(line: usize, path: &Path) -> String {
+    format!("{} {} \"{}\"\n", LINE_MARKER, line, path.display())
+}
+
+// the line number and path a LINE_MARKER gives, if the line is one
+pub(super) fn parse_line_marker(line: &str) -> Option<(usize, &str)> {
+    let (number, path) = line
+        .trim()
+        .strip_prefix(LINE_MARKER)?
+        .trim()
+        .split_once(' ')?;
+    let path = path.trim().strip_prefix('"')?.strip_suffix('"')?;
+    Some((number.parse().ok()?, path))
+}
+
+/// Where each line of assembled source came from, going by its LINE_MARKERs
+///
+/// The `n`th entry is the path and line number of line `n + 1`. It's None for the markers
+/// themselves and for anything before the first one, like --defines, or a whole shader that
+/// never had any, like an example or translated GLSL.
+pub(super) fn line_map(source: &str) -> Vec<Option<(String, usize)>> {
+    let mut next_line: Option<(String, usize)> = None;
+    source
+        .lines()
+        .map(|line| match parse_line_marker(line) {
+            Some((number, path)) => {
+                next_line = Some((path.to_owned(), number));
+                None
+            }
+            None => {
+                let origin = next_line.clone();
+                if let Some((_, number)) = &mut next_line {
+                    *number += 1;
+                }
+                origin
+            }
+        })
+        .collect()
+}
+
+/// Inline the files named by `//!include "path"` lines, with paths relative to the file
+/// including them, so shaders can share helper functions
+///
+/// Included files can include others in turn. Each file is inlined once, the first place it's
+/// included, so two files can share a library without defining its functions twice, but a file
+/// that ends up including itself is an error.
+///
+/// Also returns every file that was inlined, for --watch to watch too.
+pub(super) fn resolve_includes(
+    source: &str,
+    path: &Path,
+) -> Result<(String, Vec<PathBuf>), String> {
+    let mut included = Vec::new();
+    let mut stack = vec![canonical(path)];
+    let merged = inline(source, path, &mut stack, &mut included)?;
+    Ok((merged, included))
+}
+
+fn inline(
+    source: &str,
+    path: &Path,
+    stack: &mut Vec<PathBuf>,
+    included: &mut Vec<PathBuf>,
+) -> Result<String, String> {
+    let mut merged = line_marker(1, path);
+    for (index, line) in source.lines().enumerate() {
+        let target = match line.trim().strip_prefix(INCLUDE_MARKER) {
+            Some(target) => target.trim(),
+            None => {
+                merged.push_str(line);
+                merged.push('\n');
+                continue;
+            }
+        };
+        let target = target
+            .strip_prefix('"')
+            .and_then(|target| target.strip_suffix('"'))
+            .ok_or_else(|| {
+                format!(
+                    "{} line {}: expected `{} \"path\"`",
+                    path.display(),
+                    index + 1,
+                    INCLUDE_MARKER
+                )
+            })?;
+        let include_path = path.parent().unwrap_or_else(|| Path::new("")).join(target);
+        let key = canonical(&include_path);
+        if stack.contains(&key) {
+            return Err(format!(
+                "{} line {}: {} includes itself",
+                path.display(),
+                index + 1,
+                include_path.display()
+            ));
+        }
+        // the rest of this file carries on after the include
+        let resume = line_marker(index + 2, path);
+        if included.contains(&key) {
+            log::debug!("Already included {}", include_path.display());
+            merged.push_str(&resume);
+            continue;
+        }
+        let include_source = fs::read_to_string(&include_path).map_err(|error| {
+            format!(
+                "{} line {}: failed reading {}: {}",
+                path.display(),
+                index + 1,
+                include_path.display(),
+                error
+            )
+        })?;
+        included.push(key.clone());
+        stack.push(key);
+        merged.push_str(&inline(&include_source, &include_path, stack, included)?);
+        merged.push_str(&resume);
+        stack.pop();
+    }
+    Ok(merged)
+}
+
+// the same file through different relative paths compares equal, as long as it exists
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process;
+
+    // a fresh directory of shader files for one test, named by it
+    fn shader_dir(test: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("shader-party-{}-{}", test, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).expect("temp dir is writable");
+        for (name, source) in files {
+            fs::write(dir.join(name), source).expect("temp dir is writable");
+        }
+        dir
+    }
+
+    #[test]
+    fn line_marker_round_trips() {
+        let marker = line_marker(12, Path::new("shaders/lib.wgsl"));
+        assert_eq!(parse_line_marker(&marker), Some((12, "shaders/lib.wgsl")));
+        assert_eq!(parse_line_marker("// just a comment"), None);
+        assert_eq!(parse_line_marker("//!line twelve \"lib.wgsl\""), None);
+    }
+
+    #[test]
+    fn line_map_follows_markers() {
+        let source = format!(
+            "let a = 1;\n{}b\nc\n",
+            line_marker(5, Path::new("lib.wgsl"))
+        );
+        assert_eq!(
+            line_map(&source),
+            vec![
+                None,
+                None,
+                Some(("lib.wgsl".to_owned(), 5)),
+                Some(("lib.wgsl".to_owned(), 6)),
+            ]
+        );
+    }
+
+    #[test]
+    fn includes_are_inlined_once_and_listed() {
+        let dir = shader_dir(
+            "include-once",
+            &[
+                ("lib.wgsl", "fn lib() {}\n"),
+                ("a.wgsl", "//!include \"lib.wgsl\"\nfn a() {}\n"),
+            ],
+        );
+        let main = dir.join("main.wgsl");
+        let source = "//!include \"a.wgsl\"\n//!include \"lib.wgsl\"\nfn main() {}\n";
+        let (merged, included) = resolve_includes(source, &main).expect("includes resolve");
+        assert_eq!(merged.matches("fn lib() {}").count(), 1);
+        assert!(merged.contains("fn a() {}") && merged.contains("fn main() {}"));
+        assert_eq!(
+            included,
+            vec![
+                canonical(&dir.join("a.wgsl")),
+                canonical(&dir.join("lib.wgsl"))
+            ]
+        );
+        // errors after the includes still point at the right line of the file written
+        let lines = line_map(&merged);
+        let main_line = merged
+            .lines()
+            .position(|line| line == "fn main() {}")
+            .expect("main is in there");
+        assert_eq!(lines[main_line], Some((main.display().to_string(), 3)));
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn include_cycle_is_an_error() {
+        let dir = shader_dir(
+            "include-cycle",
+            &[
+                ("a.wgsl", "//!include \"b.wgsl\"\n"),
+                ("b.wgsl", "fn b() {}\n//!include \"a.wgsl\"\n"),
+            ],
+        );
+        let a = dir.join("a.wgsl");
+        let source = fs::read_to_string(&a).expect("a.wgsl was written");
+        let error = resolve_includes(&source, &a).expect_err("a includes itself through b");
+        assert!(error.contains("includes itself"), "{}", error);
+        assert!(error.contains("line 2"), "{}", error);
+        fs::remove_dir_all(dir).ok();
+    }
+}
@@ -0,0 +1,108 @@
+use wgpu::*;
+
+use super::super::config::LayerInput;
+use super::{
+    geometry::{Mesh, ScreenGeometry},
+    new_pipeline,
+    passes::{input_bind_group, input_bind_group_layout},
+    uniforms::{
+        bindings::{uniform_bind_group_layout, Uniform, UniformBinding},
+        LayerUniform,
+    },
+};
+
+/// Makes what's needed to composite --layers over the shader's last pass
+#[derive(Debug)]
+pub(super) struct Compositor {
+    input_layout: BindGroupLayout,
+    uniform_layout: BindGroupLayout,
+}
+
+/// One --layer's output, drawn over the shader's last pass by its blend mode and opacity
+#[derive(Debug)]
+pub(super) struct CompositeLayer {
+    pipeline: RenderPipeline,
+    input_bind_group: BindGroup,
+    uniform: UniformBinding<LayerUniform>,
+    // the pass whose output this is, the last of the layer's passes
+    source: usize,
+}
+
+impl Compositor {
+    pub(super) fn new(device: &Device) -> Self {
+        Self {
+            input_layout: input_bind_group_layout(device, 1, &[]),
+            uniform_layout: uniform_bind_group_layout(device, "Layer Bind Group Layout"),
+        }
+    }
+
+    // for compositing a layer from the `source` pass's target view into targets of this format
+    pub(super) fn layer(
+        &self,
+        device: &Device,
+        format: TextureFormat,
+        input: &LayerInput,
+        source: usize,
+        view: &TextureView,
+        sampler: &Sampler,
+    ) -> CompositeLayer {
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Layer Pipeline Layout"),
+            bind_group_layouts: &[&self.input_layout, &self.uniform_layout],
+            push_constant_ranges: &[],
+        });
+        let shader = device.create_shader_module(&ShaderModuleDescriptor {
+            label: Some("Composite Shader"),
+            source: ShaderSource::Wgsl(include_str!("composite.wgsl").into()),
+        });
+        let pipeline = new_pipeline(
+            device,
+            format,
+            &pipeline_layout,
+            shader,
+            input.blend.blend_state(),
+            ScreenGeometry::Quad,
+        );
+        CompositeLayer {
+            pipeline,
+            input_bind_group: self.input_bind_group(device, view, sampler),
+            uniform: LayerUniform::new(input.opacity, input.blend as u32)
+                .make_binding(device, &self.uniform_layout),
+            source,
+        }
+    }
+
+    // point a layer at its source's new target, as after a resize
+    pub(super) fn rebind(
+        &self,
+        device: &Device,
+        layer: &mut CompositeLayer,
+        view: &TextureView,
+        sampler: &Sampler,
+    ) {
+        layer.input_bind_group = self.input_bind_group(device, view, sampler);
+    }
+
+    fn input_bind_group(
+        &self,
+        device: &Device,
+        view: &TextureView,
+        sampler: &Sampler,
+    ) -> BindGroup {
+        input_bind_group(device, &self.input_layout, sampler, &[view], &[])
+    }
+}
+
+impl CompositeLayer {
+    pub(super) fn source(&self) -> usize {
+        self.source
+    }
+
+    // draw into a render pass, over whatever the pass drew
+    pub(super) fn draw<'pass>(&'pass self, render_pass: &mut RenderPass<'pass>, quad: &'pass Mesh) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.input_bind_group, &[]);
+        render_pass.set_bind_group(1, self.uniform.bind_group(), &[]);
+        quad.draw(render_pass);
+    }
+}
@@ -0,0 +1,112 @@
+use serde::Deserialize;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::{
+    passes::{resolve_inputs, PassError, PassSource},
+    source::read_source,
+};
+
+/// Passes in separate shader files, listed in order in a TOML file, for --manifest
+///
+/// Each `[[pass]]` table gives a `shader` path, relative to the manifest, an `output` name for
+/// later passes to read it by, and the `inputs` it reads from earlier passes:
+///
+/// ```toml
+/// [[pass]]
+/// shader = "buffer_a.wgsl"
+/// output = "buffer_a"
+///
+/// [[pass]]
+/// shader = "main.wgsl"
+/// inputs = ["buffer_a"]
+/// ```
+///
+/// The passes work like the sections of a shader with `//!pass` markers, see `PassSource`,
+/// with each file as one pass. Inputs are bound in the same places, and the last pass renders
+/// to the screen. A pass without an `output` is named after its file.
+#[derive(Debug, Deserialize)]
+struct Manifest {
+    #[serde(default)]
+    pass: Vec<ManifestPass>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ManifestPass {
+    shader: String,
+    output: Option<String>,
+    #[serde(default)]
+    inputs: Vec<String>,
+}
+
+impl Manifest {
+    fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|error| error.to_string())?;
+        let manifest: Self = toml::from_str(&text).map_err(|error| error.to_string())?;
+        if manifest.pass.is_empty() {
+            return Err("it has no `[[pass]]` tables".to_owned());
+        }
+        Ok(manifest)
+    }
+}
+
+impl ManifestPass {
+    // relative to the manifest, so it runs the same from any working directory
+    fn shader_path(&self, manifest: &Path) -> PathBuf {
+        manifest
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join(&self.shader)
+    }
+
+    fn name(&self) -> String {
+        self.output.clone().unwrap_or_else(|| {
+            Path::new(&self.shader)
+                .file_stem()
+                .map_or_else(|| self.shader.clone(), |stem| stem.to_string_lossy().into())
+        })
+    }
+}
+
+// every pass in the manifest, with `prepare` adding anything every shader gets, like --defines
+pub(super) fn manifest_passes(
+    path: &Path,
+    fragment: &str,
+    prepare: impl Fn(&str) -> String,
+) -> Result<Vec<PassSource>, String> {
+    let manifest = Manifest::load(path)?;
+    let mut passes: Vec<PassSource> = Vec::with_capacity(manifest.pass.len());
+    for pass in manifest.pass {
+        let name = pass.name();
+        if passes.iter().any(|existing| existing.name == name) {
+            return Err(PassError::DuplicateName(name).to_string());
+        }
+        let shader = pass.shader_path(path);
+        log::info!("Reading pass `{}` from {}", name, shader.display());
+        let (source, includes) = read_source(&shader, fragment)
+            .map_err(|error| format!("failed reading {}: {}", shader.display(), error))?;
+        let inputs =
+            resolve_inputs(&passes, &name, pass.inputs).map_err(|error| error.to_string())?;
+        passes.push(PassSource {
+            name,
+            inputs,
+            source: prepare(&source),
+            uniforms: true,
+            includes,
+        });
+    }
+    Ok(passes)
+}
+
+/// The manifest and every shader file it lists, for --watch
+pub(crate) fn manifest_files(path: &str) -> Result<Vec<String>, String> {
+    let path = Path::new(path);
+    let manifest = Manifest::load(path)?;
+    Ok(std::iter::once(path.to_owned())
+        .chain(manifest.pass.iter().map(|pass| pass.shader_path(path)))
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect())
+}
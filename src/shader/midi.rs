@@ -0,0 +1,119 @@
+use midir::{Ignore, MidiInput, MidiInputConnection};
+use std::{
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::Duration,
+};
+
+use super::uniforms::CustomUniform;
+use crate::config::MidiMapping;
+
+// how this program shows up in the system's MIDI connections
+const CLIENT_NAME: &str = "shader-party";
+// how often to check whether a controller was plugged in or unplugged
+const HOTPLUG_INTERVAL: Duration = Duration::from_secs(1);
+// status byte of a control change message, with the channel masked off
+const CONTROL_CHANGE: u8 = 0xB0;
+
+/// Receives MIDI control changes on a background thread, for setting custom uniforms
+#[derive(Debug)]
+pub(super) struct MidiListener {
+    // custom uniform index and its new value
+    receiver: Receiver<(usize, f32)>,
+}
+
+impl MidiListener {
+    // an empty `mappings` maps each controller to the slot with the same number
+    pub(super) fn spawn(mappings: Vec<MidiMapping>) -> std::io::Result<Self> {
+        let (sender, receiver) = mpsc::channel();
+        thread::Builder::new()
+            .name("MIDI Listener".to_owned())
+            .spawn(move || watch(&mappings, &sender))?;
+        Ok(Self { receiver })
+    }
+
+    // apply every value received since the last poll
+    pub(super) fn poll(&self, uniform: &mut CustomUniform) {
+        for (index, value) in self.receiver.try_iter() {
+            uniform.set(index, value);
+        }
+    }
+}
+
+// stay connected to the first MIDI input, reconnecting as inputs come and go
+fn watch(mappings: &[MidiMapping], sender: &Sender<(usize, f32)>) {
+    // the port's name, and the connection which stays open until dropped
+    let mut connection: Option<(String, MidiInputConnection<()>)> = None;
+    loop {
+        let input = match MidiInput::new(CLIENT_NAME) {
+            Ok(input) => input,
+            Err(error) => {
+                log::error!("Couldn't start MIDI: {}", error);
+                return;
+            }
+        };
+        if let Some((name, _)) = &connection {
+            let plugged_in = input
+                .ports()
+                .iter()
+                .filter_map(|port| input.port_name(port).ok())
+                .any(|port_name| &port_name == name);
+            if !plugged_in {
+                log::info!("MIDI input `{}` disconnected", name);
+                connection = None;
+            }
+        }
+        if connection.is_none() {
+            connection = connect(input, mappings, sender);
+        }
+        thread::sleep(HOTPLUG_INTERVAL);
+    }
+}
+
+fn connect(
+    mut input: MidiInput,
+    mappings: &[MidiMapping],
+    sender: &Sender<(usize, f32)>,
+) -> Option<(String, MidiInputConnection<()>)> {
+    // only control changes are wanted, so skip sysex, clock and active sensing
+    input.ignore(Ignore::All);
+    let port = input.ports().into_iter().next()?;
+    let name = input.port_name(&port).ok()?;
+    let mappings = mappings.to_vec();
+    let sender = sender.clone();
+    let callback = move |_timestamp: u64, message: &[u8], _: &mut ()| {
+        if let Some(change) = control_change(&mappings, message) {
+            // the window has closed if this fails, so there's nothing to do
+            let _ = sender.send(change);
+        }
+    };
+    match input.connect(&port, "shader-party-input", callback, ()) {
+        Ok(connection) => {
+            log::info!("Listening for MIDI on `{}`", name);
+            Some((name, connection))
+        }
+        Err(error) => {
+            log::error!("Couldn't connect to MIDI input `{}`: {}", name, error);
+            None
+        }
+    }
+}
+
+// custom uniform slot and value for a control change message
+fn control_change(mappings: &[MidiMapping], message: &[u8]) -> Option<(usize, f32)> {
+    match *message {
+        [status, controller, value] if status & 0xF0 == CONTROL_CHANGE => {
+            let slot = if mappings.is_empty() {
+                usize::from(controller)
+            } else {
+                mappings
+                    .iter()
+                    .find(|mapping| mapping.controller == controller)?
+                    .slot
+            };
+            // CC values go from 0 to 127
+            Some((slot, f32::from(value) / 127.0))
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,130 @@
+use std::num::NonZeroU32;
+use wgpu::*;
+
+use super::{
+    geometry::ScreenGeometry,
+    new_pipeline,
+    passes::{input_bind_group, input_bind_group_layout, PassTarget, PASS_TARGET_FORMAT},
+};
+
+/// Fills in mip levels, each level downsampled from the one above, for pass targets and for
+/// --texture images that don't come with their own
+#[derive(Debug)]
+pub(super) struct MipmapGenerator {
+    // for pass targets, which are all PASS_TARGET_FORMAT
+    pipeline: RenderPipeline,
+    layout: BindGroupLayout,
+    // linear, so each output pixel averages the four input pixels around it
+    sampler: Sampler,
+}
+
+impl MipmapGenerator {
+    pub(super) fn new(device: &Device) -> Self {
+        let layout = input_bind_group_layout(device, 1, &[]);
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("Mipmap Sampler"),
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+        let pipeline = downsample_pipeline(device, &layout, PASS_TARGET_FORMAT);
+        Self {
+            pipeline,
+            layout,
+            sampler,
+        }
+    }
+
+    // record the downsampling passes, after the pass has rendered to level 0
+    pub(super) fn generate(
+        &self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        target: &PassTarget,
+    ) {
+        self.downsample(device, encoder, &self.pipeline, target.mip_views());
+    }
+
+    // fill in every level of a loaded texture after the first, once that's been written
+    // textures are loaded outside of frames, so this is submitted on its own
+    pub(super) fn generate_texture(
+        &self,
+        device: &Device,
+        queue: &Queue,
+        texture: &Texture,
+        format: TextureFormat,
+        levels: u32,
+    ) {
+        let pipeline = downsample_pipeline(device, &self.layout, format);
+        let views: Vec<_> = (0..levels)
+            .map(|level| {
+                texture.create_view(&TextureViewDescriptor {
+                    base_mip_level: level,
+                    mip_level_count: NonZeroU32::new(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("Mipmap Encoder"),
+        });
+        self.downsample(device, &mut encoder, &pipeline, &views);
+        queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    // draw each of `levels` from the one before it, one render pass each
+    fn downsample(
+        &self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        pipeline: &RenderPipeline,
+        levels: &[TextureView],
+    ) {
+        for levels in levels.windows(2) {
+            let (source, destination) = (&levels[0], &levels[1]);
+            let bind_group = input_bind_group(device, &self.layout, &self.sampler, &[source], &[]);
+            let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("Mipmap Pass"),
+                color_attachments: &[RenderPassColorAttachment {
+                    view: destination,
+                    resolve_target: None,
+                    ops: Operations {
+                        // every pixel gets drawn over, so there's nothing worth loading
+                        load: LoadOp::Clear(Color::TRANSPARENT),
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: None,
+            });
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_bind_group(0, &bind_group, &[]);
+            // the fullscreen triangle, three vertices with one instance
+            render_pass.draw(0..3, 0..1);
+        }
+    }
+}
+
+// a pipeline downsampling into levels of this format
+fn downsample_pipeline(
+    device: &Device,
+    layout: &BindGroupLayout,
+    format: TextureFormat,
+) -> RenderPipeline {
+    let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: Some("Mipmap Pipeline Layout"),
+        bind_group_layouts: &[layout],
+        push_constant_ranges: &[],
+    });
+    let shader = device.create_shader_module(&ShaderModuleDescriptor {
+        label: Some("Mipmap Shader"),
+        source: ShaderSource::Wgsl(include_str!("mipmaps.wgsl").into()),
+    });
+    new_pipeline(
+        device,
+        format,
+        &pipeline_layout,
+        shader,
+        BlendState::REPLACE,
+        ScreenGeometry::Triangle,
+    )
+}
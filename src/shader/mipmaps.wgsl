@@ -0,0 +1,24 @@
+// Downsamples one mip level into the next, half the size
+
+[[group(0), binding(0)]]
+var input_texture: texture_2d<f32>;
+[[group(0), binding(1)]]
+var input_sampler: sampler;
+
+// one oversized triangle covering the level, with the rest clipped
+[[stage(vertex)]]
+fn vs_fullscreen([[builtin(vertex_index)]] index: u32) -> [[builtin(position)]] vec4<f32> {
+    let corner = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(corner * 2.0 - 1.0, 0.0, 1.0);
+}
+
+[[stage(fragment)]]
+fn fs_main([[builtin(position)]] position: vec4<f32>) -> [[location(0)]] vec4<f32> {
+    // each output pixel's centre lands between four input pixels,
+    // so linear filtering averages them
+    let output_size = max(
+        floor(vec2<f32>(textureDimensions(input_texture)) / 2.0),
+        vec2<f32>(1.0)
+    );
+    return textureSample(input_texture, input_sampler, position.xy / output_size);
+}
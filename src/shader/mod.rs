@@ -1,60 +1,534 @@
-use std::{fs, time::Instant};
-use wgpu::{util::DeviceExt, *};
-use winit::{dpi::PhysicalSize, event::*, window::Window};
+use clap::ValueEnum;
+use std::{
+    collections::HashMap,
+    fs, mem,
+    num::NonZeroU8,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
+use wgpu::*;
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::*,
+    window::{Fullscreen, Window},
+};
 
+mod audio;
+mod background;
+mod camera;
+mod capture;
+mod check;
+mod compute;
+mod error;
+mod feedback;
+mod fps;
 mod geometry;
+mod include;
+mod layers;
+mod manifest;
+mod midi;
+mod mipmaps;
+mod noise;
+mod osc;
+mod output;
+mod overlay;
+mod pacing;
+mod passes;
+mod post;
+mod presets;
+mod reference;
+mod reflect;
+mod scaling;
+mod script;
+mod source;
+mod tempo;
+mod textures;
 mod uniforms;
+mod video;
 
 use self::{
-    geometry::{Vertex, INDICES, VERTICES},
+    audio::Audio,
+    background::Background,
+    camera::{scroll_lines, OrbitCamera},
+    capture::{
+        git_hash, pixel_text, write_png, Frame, FrameMetadata, GifRecorder, IconPreview,
+        MetadataWriter, PendingFrame, RawWriter,
+    },
+    compute::Compute,
+    feedback::{Feedback, FEEDBACK_GROUP},
+    fps::FpsTitle,
+    geometry::{pass_entry_points, Mesh, ScreenGeometry, Vertex},
+    layers::{CompositeLayer, Compositor},
+    manifest::manifest_passes,
+    midi::MidiListener,
+    mipmaps::MipmapGenerator,
+    noise::blue_noise_channel,
+    osc::OscListener,
+    output::Output,
+    overlay::{Overlay, OverlayLayer},
+    pacing::FramePacer,
+    passes::{
+        depth_view, input_bind_group, input_bind_group_layout, input_binding_name, input_bindings,
+        input_layout_entries, mip_level_count, split_passes, Multisampled, PassError, PassInput,
+        PassSource, PassTarget, DEPTH_FORMAT, PASS_TARGET_FORMAT,
+    },
+    post::post_process_passes,
+    presets::PresetBank,
+    reference::Reference,
+    reflect::{
+        check_uniform_layouts, declared_bindings, declared_uniforms, describe_layout,
+        fragment_outputs, missing_entry_point, parse_error, HostUniform,
+    },
+    scaling::{scaled_rect, scaled_size, AdaptiveScale},
+    script::Script,
+    source::read_source,
+    tempo::Tempo,
+    textures::{Channel, Sampling, TextureFile, TextureWatcher},
     uniforms::{
-        bindings::{Uniform, UniformBinding},
-        MouseUniform, TimeUniform,
+        bindings::{
+            uniform_bind_group_layout, uniforms_bind_group, uniforms_bind_group_layout,
+            uniforms_layout_entries, StorageAccess, Uniform, UniformBinding, UniformBuffer,
+            UniformRing,
+        },
+        AdapterUniform, AudioUniform, CameraUniform, CustomUniform, DateUniform, DisplayUniform,
+        FrameTimesUniform, KeyboardUniform, LodUniform, MouseUniform, ResolutionUniform,
+        RngUniform, TileUniform, TimeUniform, WindowUniform,
     },
+    video::VideoTexture,
+};
+pub(super) use self::{
+    check::check_shader, error::StateError, manifest::manifest_files, output::Target,
+};
+use super::{
+    config::{feature_name, ClearColor, Config, LayerInput, Tile, UniformName},
+    examples,
+    recent::remember_shader,
 };
-use super::config::Config;
 
-fn new_shader(device: &Device, path: &str) -> ShaderModule {
-    log::info!("Reading shader");
+// refresh rate assumed when the monitor's current one can't be found
+const FALLBACK_REFRESH_RATE: f32 = 60.0;
+
+// refresh rate of the monitor the window is on, in Hz, or None if it can't be found
+// winit only says which video mode is in use for exclusive fullscreen, and otherwise only lists
+// the ones the monitor could switch to
+fn refresh_rate(window: &Window) -> Option<f32> {
+    match window.fullscreen() {
+        Some(Fullscreen::Exclusive(mode)) => Some(f32::from(mode.refresh_rate())),
+        _ => None,
+    }
+}
+
+// where the window's contents are on the virtual desktop, and the size of the whole desktop
+// the desktop is the box around every monitor, and positions are from its top left
+// some platforms, like Wayland, won't say where windows are, so they're all at 0, 0
+fn window_placement(window: &Window) -> ([f32; 2], [f32; 2]) {
+    let (min, max) = window.available_monitors().fold(
+        ((i32::MAX, i32::MAX), (i32::MIN, i32::MIN)),
+        |(min, max), monitor| {
+            let position = monitor.position();
+            let size = monitor.size();
+            (
+                (min.0.min(position.x), min.1.min(position.y)),
+                (
+                    max.0.max(position.x + size.width as i32),
+                    max.1.max(position.y + size.height as i32),
+                ),
+            )
+        },
+    );
+    // no monitors at all, so there's no desktop to be placed on
+    if min.0 > max.0 {
+        return ([0.0; 2], [0.0; 2]);
+    }
+    let position = window.inner_position().map_or([0.0; 2], |position| {
+        [(position.x - min.0) as f32, (position.y - min.1) as f32]
+    });
+    (position, [(max.0 - min.0) as f32, (max.1 - min.1) as f32])
+}
+
+// the same format with the other colour encoding, if it has one
+fn srgb_counterpart(format: TextureFormat) -> Option<TextureFormat> {
+    match format {
+        TextureFormat::Bgra8Unorm => Some(TextureFormat::Bgra8UnormSrgb),
+        TextureFormat::Bgra8UnormSrgb => Some(TextureFormat::Bgra8Unorm),
+        TextureFormat::Rgba8Unorm => Some(TextureFormat::Rgba8UnormSrgb),
+        TextureFormat::Rgba8UnormSrgb => Some(TextureFormat::Rgba8Unorm),
+        _ => None,
+    }
+}
+
+// with the files it includes
+fn read_shader(config: &Config) -> Result<(String, Vec<PathBuf>), String> {
+    let (source, includes) = if let Some(name) = &config.example {
+        log::info!("Using example shader {}", name);
+        let source = examples::source(name).expect("Examples are checked when parsing arguments");
+        (source.to_owned(), Vec::new())
+    } else {
+        log::info!("Reading shader");
+
+        // load shader from file
+        // let shader_source = include_str!("shader.wgsl").into();
+        read_source(Path::new(&config.path), &config.fragment_entry)
+            .map_err(|error| format!("Failed reading shader {}: {}", config.path, error))?
+    };
+    Ok((with_defines(config, &source), includes))
+}
+
+// the shader file's git blob hash, before --defines, for --capture-metadata
+fn shader_hash(config: &Config) -> String {
+    match &config.example {
+        Some(name) => git_hash(
+            examples::source(name)
+                .expect("Examples are checked when parsing arguments")
+                .as_bytes(),
+        ),
+        None => match &config.manifest {
+            // with a --manifest, of the manifest and every shader it lists read one after
+            // another, so editing any pass changes it
+            Some(manifest) => {
+                let files = manifest_files(manifest).expect("The manifest has just loaded");
+                let mut contents = Vec::new();
+                for file in files {
+                    contents.extend(fs::read(file).expect("Failed reading shader"));
+                }
+                git_hash(&contents)
+            }
+            None => git_hash(&fs::read(&config.path).expect("Failed reading shader")),
+        },
+    }
+}
+
+// a new file in the working directory to screenshot into, named after the shader and when
+// it was taken, so screenshots sort in the order they were taken
+fn screenshot_path(config: &Config) -> PathBuf {
+    let name = match &config.example {
+        Some(name) => name.as_str(),
+        None => Path::new(&config.path)
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("shader"),
+    };
+    let millis = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    PathBuf::from(format!("{}-{}.png", name, millis))
+}
+
+// size of --output frames without --size
+const OUTPUT_SIZE: PhysicalSize<u32> = PhysicalSize::new(1280, 720);
+
+// size of frames rendered without a window
+fn offscreen_size(config: &Config) -> PhysicalSize<u32> {
+    config.size.map_or(OUTPUT_SIZE, |size| {
+        PhysicalSize::new(size.width, size.height)
+    })
+}
+
+/// Render one frame to the --output PNG without a window, at --at seconds
+///
+/// Errors if the shader doesn't load or the PNG can't be written.
+pub(super) fn render_to_file(config: Config) -> Result<(), String> {
+    let path = PathBuf::from(config.output.as_deref().expect("Only called with --output"));
+    let size = offscreen_size(&config);
+    let at = config.at.or(config.start_time).unwrap_or_default();
+    let mut state = pollster::block_on(State::new(Target::Offscreen(size), config))
+        .map_err(|error| error.to_string())?;
+    // time stands still at --at, so the same shader always renders the same frame
+    state.set_time(at);
+    // with a frame's worth of delta, as if there'd been one before
+    state.last_elapsed = at.saturating_sub(TIME_STEP);
+    state.screenshot = Some(path.clone());
+    state.update();
+    state
+        .render()
+        .map_err(|error| format!("Failed rendering: {}", error))?;
+    if state.screenshot_failed {
+        return Err(format!("Could not write {}", path.display()));
+    }
+    Ok(())
+}
+
+/// Record the --gif without a window, at --size, printing progress as it goes
+///
+/// Errors if the shader doesn't load or the GIF can't be written.
+pub(super) fn record_gif(config: Config) -> Result<(), String> {
+    let path = config.gif.clone().expect("Only called with --gif");
+    let size = offscreen_size(&config);
+    let mut state = pollster::block_on(State::new(Target::Offscreen(size), config))
+        .map_err(|error| error.to_string())?;
+    while let Some((recorded, count)) = state.gif.as_ref().map(GifRecorder::progress) {
+        eprint!("\rRecording frame {}/{}", recorded + 1, count);
+        state.update();
+        state
+            .render()
+            .map_err(|error| format!("Failed rendering: {}", error))?;
+    }
+    eprintln!();
+    if state.gif_failed {
+        return Err(format!("Could not write {}", path));
+    }
+    Ok(())
+}
+
+// a --layer's shader, which gets the --defines too, with the files it includes
+fn read_layer(config: &Config, layer: &LayerInput) -> Result<(String, Vec<PathBuf>), String> {
+    log::info!("Reading layer {}", layer.path);
+    let (source, includes) = read_source(Path::new(&layer.path), &config.fragment_entry)
+        .map_err(|error| format!("Failed reading layer {}: {}", layer.path, error))?;
+    Ok((with_defines(config, &source), includes))
+}
+
+// passes split from one file, each of which depends on everything the file includes
+fn split_file_passes(source: &str, includes: &[PathBuf]) -> Result<Vec<PassSource>, PassError> {
+    let mut passes = split_passes(source)?;
+    for pass in &mut passes {
+        pass.includes = includes.to_vec();
+    }
+    Ok(passes)
+}
+
+// --define constants go first, so the whole shader can use them
+fn with_defines(config: &Config, source: &str) -> String {
+    let mut shader: String = config
+        .define
+        .iter()
+        .map(|define| define.declaration() + "\n")
+        .collect();
+    shader.push_str(source);
+    shader
+}
+
+// the --feedback shader, as one pass however many `//!pass` markers it has
+fn feedback_pass(config: &Config, path: &str) -> Result<PassSource, String> {
+    log::info!("Reading feedback shader {}", path);
+    let (source, includes) = read_source(Path::new(path), &config.fragment_entry)
+        .map_err(|error| format!("Failed reading feedback shader {}: {}", path, error))?;
+    Ok(PassSource {
+        name: "feedback".to_owned(),
+        inputs: Vec::new(),
+        source: with_defines(config, &source),
+        uniforms: true,
+        includes,
+    })
+}
+
+// the shader's own passes, from the --manifest if there is one, or else split along the
+// shader file's `//!pass` markers
+fn shader_passes(config: &Config) -> Result<Vec<PassSource>, String> {
+    match &config.manifest {
+        Some(path) => {
+            log::info!("Reading manifest {}", path);
+            manifest_passes(Path::new(path), &config.fragment_entry, |source| {
+                with_defines(config, source)
+            })
+            .map_err(|error| format!("Failed loading manifest {}: {}", path, error))
+        }
+        None => {
+            let (source, includes) = read_shader(config)?;
+            split_file_passes(&source, &includes)
+                .map_err(|error| format!("Failed splitting shader into passes: {}", error))
+        }
+    }
+}
+
+// the --feedback pass, every layer's passes, then the shader's, with inputs counting from the
+// first pass, and the index of each layer's last pass, which is its output
+fn layered_passes(config: &Config) -> Result<(Vec<PassSource>, Vec<usize>), String> {
+    let mut sources = Vec::new();
+    let mut outputs = Vec::new();
+    if let Some(path) = &config.feedback {
+        sources.push(feedback_pass(config, path)?);
+    }
+    for layer in &config.layer {
+        let (source, includes) = read_layer(config, layer)?;
+        let layer_sources = split_file_passes(&source, &includes).map_err(|error| {
+            format!(
+                "Failed splitting layer {} into passes: {}",
+                layer.path, error
+            )
+        })?;
+        append_passes(&mut sources, layer_sources, &layer.path);
+        outputs.push(sources.len() - 1);
+    }
+    append_passes(&mut sources, shader_passes(config)?, "");
+    Ok((sources, outputs))
+}
+
+// add one file's passes after the others, shifting their inputs past the passes already there
+// `prefix` tells apart layers' passes, which can have the same names as the shader's
+fn append_passes(sources: &mut Vec<PassSource>, passes: Vec<PassSource>, prefix: &str) {
+    let base = sources.len();
+    sources.extend(passes.into_iter().map(|mut source| {
+        for input in &mut source.inputs {
+            input.pass += base;
+        }
+        if !prefix.is_empty() {
+            source.name = format!("{} {}", prefix, source.name);
+        }
+        source
+    }));
+}
+
+// a window size cut down to the largest texture the GPU can make, since the surface and the
+// pass targets are textures that size
+fn texture_size(size: PhysicalSize<u32>, max_dimension: u32) -> PhysicalSize<u32> {
+    if size.width > max_dimension || size.height > max_dimension {
+        log::warn!(
+            "Window is {}x{}, but this GPU only supports textures up to {} pixels across, \
+            so it's rendered smaller and stretched to fit",
+            size.width,
+            size.height,
+            max_dimension
+        );
+    }
+    PhysicalSize::new(
+        size.width.min(max_dimension),
+        size.height.min(max_dimension),
+    )
+}
+
+// the --anisotropy level to sample textures at, or None if it's 1 or the GPU can't do it
+fn anisotropy(adapter: &Adapter, level: u8) -> Option<NonZeroU8> {
+    let level = NonZeroU8::new(level).filter(|level| level.get() > 1)?;
+    if !adapter
+        .get_downlevel_properties()
+        .flags
+        .contains(DownlevelFlags::ANISOTROPIC_FILTERING)
+    {
+        log::warn!(
+            "This GPU can't do anisotropic filtering, so --anisotropy {} is ignored",
+            level
+        );
+        return None;
+    }
+    Some(level)
+}
+
+// the biggest size each uniform is declared at across the shader's passes, by group and binding
+fn declared_uniform_sizes(config: &Config) -> HashMap<(u32, u32), u64> {
+    let mut sizes = HashMap::new();
+    let sources = layered_passes(config)
+        .map(|(sources, _)| sources)
+        .unwrap_or_default();
+    for source in sources.iter().filter(|source| source.uniforms) {
+        for (key, uniform) in declared_uniforms(&source.source) {
+            let size = sizes.entry(key).or_insert(0);
+            *size = uniform.size.max(*size);
+        }
+    }
+    sizes
+}
+
+// the size of a built-in uniform's struct and where its fields start, to check the shader's
+// declaration against
+fn host_layout<T: Uniform>() -> (u64, &'static [u32]) {
+    (mem::size_of::<T>() as u64, T::MEMBER_OFFSETS)
+}
 
-    // load shader from file
-    // let shader_source = include_str!("shader.wgsl").into();
-    let shader_source = fs::read_to_string(path)
-        .expect("Failed reading shader")
-        .into();
+fn new_shader(device: &Device, label: &str, source: String) -> ShaderModule {
     device.create_shader_module(&ShaderModuleDescriptor {
-        label: Some("Shader"),
-        source: ShaderSource::Wgsl(shader_source),
+        label: Some(label),
+        source: ShaderSource::Wgsl(source.into()),
     })
 }
 
+// a shader module, and the entry points a pipeline is built from
+struct PassShader<'a> {
+    module: ShaderModule,
+    vertex: &'a str,
+    fragment: &'a str,
+}
+
 fn new_pipeline(
     device: &Device,
-    surface_config: &SurfaceConfiguration,
+    format: TextureFormat,
     render_pipeline_layout: &PipelineLayout,
     shader: ShaderModule,
+    blend: BlendState,
+    geometry: ScreenGeometry,
+) -> RenderPipeline {
+    let shader = PassShader {
+        module: shader,
+        vertex: geometry.vertex_entry_point(),
+        fragment: "fs_main",
+    };
+    new_pipeline_with_outputs(
+        device,
+        format,
+        render_pipeline_layout,
+        shader,
+        blend,
+        geometry,
+        PassAttachments {
+            outputs: 1,
+            depth: false,
+            samples: 1,
+            alpha_to_coverage: false,
+        },
+    )
+}
+
+// what a pass's pipeline draws into, besides the `format` target of new_pipeline_with_outputs
+#[derive(Clone, Copy, Debug)]
+struct PassAttachments {
+    // colours the shader writes, one per `[[location(n)]]`
+    outputs: u32,
+    // whether nearer fragments hide further ones in a DEPTH_FORMAT depth buffer
+    depth: bool,
+    // samples per pixel, more than 1 drawing into Multisampled targets for --msaa
+    samples: u32,
+    // whether the first colour's alpha decides how many samples it covers, with
+    // --alpha-to-coverage
+    alpha_to_coverage: bool,
+}
+
+// like new_pipeline, for a shader writing more than one colour, or testing depth
+// the first colour goes to a `format` target with `blend`, and the rest to offscreen pass targets
+fn new_pipeline_with_outputs(
+    device: &Device,
+    format: TextureFormat,
+    render_pipeline_layout: &PipelineLayout,
+    shader: PassShader,
+    blend: BlendState,
+    geometry: ScreenGeometry,
+    attachments: PassAttachments,
 ) -> RenderPipeline {
+    let extra_targets = (1..attachments.outputs).map(|_| ColorTargetState {
+        format: PASS_TARGET_FORMAT,
+        blend: Some(BlendState::REPLACE),
+        write_mask: ColorWrites::ALL,
+    });
+    let targets: Vec<_> = std::iter::once(ColorTargetState {
+        // same format as the target for easier copying
+        format,
+        // how to combine with old pixels
+        blend: Some(blend),
+        // write to every colour channel including alpha
+        write_mask: ColorWrites::ALL,
+    })
+    .chain(extra_targets)
+    .collect();
+    let buffers = match geometry {
+        ScreenGeometry::Quad => vec![Vertex::desc()],
+        // positions come from the vertex index instead
+        ScreenGeometry::Triangle => Vec::new(),
+    };
     device.create_render_pipeline(&RenderPipelineDescriptor {
         label: Some("Render Pipeline"),
         layout: Some(render_pipeline_layout),
         // vertex shader and buffers
         vertex: VertexState {
-            module: &shader,
-            entry_point: "vs_main",
-            buffers: &[Vertex::desc()],
+            module: &shader.module,
+            entry_point: shader.vertex,
+            buffers: &buffers,
         },
         // fragment shader and buffers and blending modes
         fragment: Some(FragmentState {
-            module: &shader,
-            entry_point: "fs_main",
-            targets: &[ColorTargetState {
-                // same format as the surface for easier copying
-                format: surface_config.format,
-                // don't care about old pixels, just replace them
-                blend: Some(BlendState::REPLACE),
-                // write to every colour channel including alpha
-                write_mask: ColorWrites::ALL,
-            }],
+            module: &shader.module,
+            entry_point: shader.fragment,
+            targets: &targets,
         }),
         // how to interpret vertices as triangles
         primitive: PrimitiveState {
@@ -73,257 +547,2618 @@ fn new_pipeline(
             // must be false unless CONSERVATIVE_RASTERIZATION
             conservative: false,
         },
-        depth_stencil: None,
-        // use one buffer
+        depth_stencil: attachments.depth.then(|| DepthStencilState {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: true,
+            // nearer is smaller, as the depth buffer is cleared to 1
+            depth_compare: CompareFunction::Less,
+            stencil: StencilState::default(),
+            bias: DepthBiasState::default(),
+        }),
         multisample: MultisampleState {
-            // only one sample
-            count: 1,
+            // one sample unless it's antialiased with --msaa
+            count: attachments.samples,
             // bits set to use all samples
             mask: !0,
-            // no antialiasing
-            alpha_to_coverage_enabled: false,
+            // coverage comes from the triangles' edges, and the alpha of cutouts if asked for
+            alpha_to_coverage_enabled: attachments.alpha_to_coverage,
         },
         // not using array textures
         multiview: None,
     })
 }
 
+// a render pass drawing to `target` as `[[location(0)]]`, and any extra targets after it,
+// which start out cleared, as does the `depth` buffer if there is one
+// with `multisampled` attachments, it draws into those instead, and resolves them into the
+// targets at the end
+fn begin_render_pass<'a>(
+    encoder: &'a mut CommandEncoder,
+    target: &'a TextureView,
+    ops: Operations<Color>,
+    extra_targets: &'a [PassTarget],
+    depth: Option<&'a TextureView>,
+    multisampled: Option<&'a Multisampled>,
+) -> RenderPass<'a> {
+    let extra_ops = Operations {
+        load: LoadOp::Clear(Color::TRANSPARENT),
+        store: true,
+    };
+    let targets = std::iter::once((target, ops))
+        .chain(
+            extra_targets
+                .iter()
+                .map(|target| (target.render_view(), extra_ops)),
+        )
+        .enumerate();
+    // `[[location(n)]]` in the fragment shader's return val is the nth attachment
+    let color_attachments: Vec<_> = targets
+        .map(|(location, (target, ops))| match multisampled {
+            Some(multisampled) => RenderPassColorAttachment {
+                view: multisampled.view(location),
+                resolve_target: Some(target),
+                ops,
+            },
+            None => RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops,
+            },
+        })
+        .collect();
+    encoder.begin_render_pass(&RenderPassDescriptor {
+        label: Some("Render Pass"),
+        // where to draw colour to
+        color_attachments: &color_attachments,
+        depth_stencil_attachment: depth.map(|view| RenderPassDepthStencilAttachment {
+            view,
+            depth_ops: Some(Operations {
+                load: LoadOp::Clear(1.0),
+                // only needed while drawing
+                store: false,
+            }),
+            stencil_ops: None,
+        }),
+    })
+}
+
+// most `[[location(n)]]` outputs a pass can write
+// wgpu 0.12's device limits don't include it, but it never allows more than 8 colour targets
+const MAX_COLOR_ATTACHMENTS: u32 = 8;
+
+// mixes a new --accumulate sample into the average so far, by the blend constant
+// which is 1/n for the nth sample
+const ACCUMULATE_BLEND: BlendState = BlendState {
+    color: BlendComponent {
+        src_factor: BlendFactor::Constant,
+        dst_factor: BlendFactor::OneMinusConstant,
+        operation: BlendOperation::Add,
+    },
+    alpha: BlendComponent {
+        src_factor: BlendFactor::Constant,
+        dst_factor: BlendFactor::OneMinusConstant,
+        operation: BlendOperation::Add,
+    },
+};
+
+// the images passes read besides each other's outputs
+struct PassImages<'a> {
+    // for pass inputs
+    sampler: &'a Sampler,
+    // from --texture, for the shader's own passes
+    channels: &'a [Channel],
+    // from --background-image, drawn under the shader's last pass
+    background: Option<&'a Background>,
+    // for drawing --layers' outputs over the shader's last pass
+    compositor: &'a Compositor,
+    // sampled by every pass with the built-in uniforms, with --feedback
+    feedback: Option<&'a Feedback>,
+}
+
+// build a pipeline for every pass in the shader file
+// intermediate passes get offscreen targets, the last pass draws to the surface
+// errors are for the shader's mistakes, while wgpu reports its own through the device
+// the shader's own passes render at `render_size`, for --scale, and the rest at the surface's
+fn new_passes(
+    device: &Device,
+    surface_config: &SurfaceConfiguration,
+    render_size: PhysicalSize<u32>,
+    uniform_layouts: &[&BindGroupLayout],
+    host_uniforms: &[HostUniform],
+    images: PassImages,
+    config: &Config,
+) -> Result<Vec<Pass>, String> {
+    let (mut sources, layer_outputs) = layered_passes(config)?;
+    // the shader's own last pass is the one drawn over --background-image, and under --layers
+    let shader_last = sources.len() - 1;
+    // post-processing reads the output of the shader's last pass
+    let post = post_process_passes(config, surface_config.format, sources.len() - 1);
+    sources.extend(post);
+    let last = sources.len() - 1;
+    // what's bound in group 0, to check the shader's declarations against
+    let mut uniform_bindings: Vec<_> = host_uniforms
+        .iter()
+        .filter(|uniform| uniform.group == 0)
+        .map(|uniform| uniform.binding)
+        .collect();
+    if storage_size(config).is_some() {
+        uniform_bindings.push(STORAGE_BINDING);
+    }
+    // the uniform groups' entries, named for what's bound, to describe each pass's layout with
+    let dynamic: Vec<_> = config.uniforms.iter().map(|&name| is_ring(name)).collect();
+    let uniform_entries = [
+        uniforms_layout_entries(
+            &dynamic,
+            storage_size(config).map(|_| (STORAGE_BINDING, storage_access(config))),
+        ),
+        uniforms_layout_entries(&[false], None),
+    ];
+    let uniform_groups: Vec<Vec<_>> = (0..)
+        .zip(uniform_entries)
+        .map(|(group, entries)| {
+            entries
+                .into_iter()
+                .map(|entry| {
+                    let name = host_uniforms
+                        .iter()
+                        .find(|uniform| (uniform.group, uniform.binding) == (group, entry.binding))
+                        .map_or_else(|| "storage".to_owned(), |uniform| uniform.name.clone());
+                    (name, entry)
+                })
+                .collect()
+        })
+        .collect();
+    // for errors about earlier passes, which have been consumed by then
+    let names: Vec<_> = sources.iter().map(|source| source.name.clone()).collect();
+    let mut passes: Vec<Pass> = Vec::with_capacity(sources.len());
+    for (index, source) in sources.into_iter().enumerate() {
+        log::debug!("Building pass {}", source.name);
+        // naga's own message points at the mistake, where wgpu's only says the module's invalid
+        if let Some(error) = parse_error(&source.source) {
+            return Err(format!("Pass `{}` doesn't parse:\n{}", source.name, error));
+        }
+        if source.uniforms {
+            check_uniform_layouts(&source.name, &source.source, host_uniforms);
+        }
+        // instead of the pipeline's vaguer error about a missing function
+        let (vertex, fragment) = if source.uniforms {
+            pass_entry_points(config)
+        } else {
+            (ScreenGeometry::new(config).vertex_entry_point(), "fs_main")
+        };
+        if let Some(error) = missing_entry_point(&source.source, vertex, fragment) {
+            return Err(format!("Pass `{}` {}", source.name, error));
+        }
+        let channels = pass_channels(source.uniforms, images.channels);
+        let input_layout = input_bind_group_layout(device, source.inputs.len(), channels);
+        let mut bind_group_layouts = if source.uniforms {
+            uniform_layouts.to_vec()
+        } else {
+            Vec::new()
+        };
+        let feedback = images.feedback.filter(|_| source.uniforms);
+        // the feedback group comes after the inputs, so they're bound even if there aren't any
+        let has_inputs = !source.inputs.is_empty() || !channels.is_empty() || feedback.is_some();
+        if has_inputs {
+            bind_group_layouts.push(&input_layout);
+        }
+        if let Some(feedback) = feedback {
+            bind_group_layouts.push(feedback.layout());
+        }
+        // the bindings in each group, in the same order as the layouts
+        let mut bound = if source.uniforms {
+            vec![uniform_bindings.clone(), vec![0]]
+        } else {
+            Vec::new()
+        };
+        if has_inputs {
+            bound.push(input_bindings(source.inputs.len(), channels.len()));
+        }
+        if feedback.is_some() {
+            bound.push(input_bindings(1, 0));
+        }
+        let groups = pass_groups(&source, &bound)?.unwrap_or(bind_group_layouts.len() as u32);
+        // groups after the last one the shader declares aren't part of its pipeline at all
+        bind_group_layouts.truncate(groups as usize);
+        let mut layout_groups = if source.uniforms {
+            uniform_groups.clone()
+        } else {
+            Vec::new()
+        };
+        if has_inputs {
+            let entries = input_layout_entries(source.inputs.len(), channels);
+            layout_groups.push(
+                entries
+                    .into_iter()
+                    .map(|entry| (input_binding_name(entry.binding), entry))
+                    .collect(),
+            );
+        }
+        if feedback.is_some() {
+            let entries = input_layout_entries(1, &[]);
+            let names = ["feedback", "feedback sampler"].map(str::to_owned);
+            layout_groups.push(names.into_iter().zip(entries).collect());
+        }
+        layout_groups.truncate(groups as usize);
+        let layout = describe_layout(&source.name, &layout_groups);
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Render Pipeline Layout"),
+            // collect bind groups here
+            // first elem is `[[group(0)]]` etc
+            bind_group_layouts: &bind_group_layouts,
+            push_constant_ranges: &[],
+        });
+        let outputs = fragment_outputs(&source.source, fragment).unwrap_or(1);
+        if outputs > MAX_COLOR_ATTACHMENTS {
+            return Err(format!(
+                "Pass `{}` writes {} outputs, but at most {} are supported",
+                source.name, outputs, MAX_COLOR_ATTACHMENTS
+            ));
+        }
+        let PhysicalSize { width, height } = if source.uniforms {
+            render_size
+        } else {
+            PhysicalSize::new(surface_config.width, surface_config.height)
+        };
+        let extra_targets = (1..outputs)
+            .map(|location| {
+                PassTarget::new(
+                    device,
+                    &format!("{} location {}", source.name, location),
+                    width,
+                    height,
+                    config.mipmaps && source.uniforms,
+                )
+            })
+            .collect();
+        let is_feedback = images.feedback.is_some() && index == 0;
+        let (target, format) = if index == last {
+            (None, surface_config.format)
+        } else if is_feedback {
+            // draws into the feedback buffer instead
+            (None, PASS_TARGET_FORMAT)
+        } else {
+            let target = PassTarget::new(
+                device,
+                &source.name,
+                width,
+                height,
+                // only the shader's own passes, for it to sample
+                config.mipmaps && source.uniforms,
+            );
+            (Some(target), PASS_TARGET_FORMAT)
+        };
+        // only the shader's own passes are antialiased
+        let samples = if source.uniforms { config.msaa } else { 1 };
+        let multisampled = (samples > 1)
+            .then(|| Multisampled::new(device, format, outputs, (width, height), samples));
+        let background = images.background.filter(|_| index == shader_last);
+        let accumulate = config.accumulate && index == shader_last;
+        let blend = match background {
+            Some(_) => config.blend.blend_state(),
+            None if accumulate => ACCUMULATE_BLEND,
+            // don't care about old pixels, just replace them
+            None => BlendState::REPLACE,
+        };
+        let pipeline = new_pipeline_with_outputs(
+            device,
+            format,
+            &pipeline_layout,
+            PassShader {
+                module: new_shader(device, &source.name, source.source),
+                vertex,
+                fragment,
+            },
+            blend,
+            ScreenGeometry::new(config),
+            PassAttachments {
+                outputs,
+                depth: config.depth && source.uniforms,
+                samples,
+                alpha_to_coverage: config.alpha_to_coverage && samples > 1,
+            },
+        );
+        let background = background.map(|background| background.pipeline(device, format));
+        let layers = if index == shader_last {
+            config
+                .layer
+                .iter()
+                .zip(&layer_outputs)
+                .map(|(layer, &output)| {
+                    let view = passes[output]
+                        .target
+                        .as_ref()
+                        .expect("Layers render to offscreen targets")
+                        .view();
+                    images
+                        .compositor
+                        .layer(device, format, layer, output, view, images.sampler)
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        for input in &source.inputs {
+            let read = &passes[input.pass];
+            if input.location >= read.outputs() {
+                return Err(format!(
+                    "Pass `{}` reads location {} of pass `{}`, which only writes {} outputs",
+                    source.name,
+                    input.location,
+                    names[input.pass],
+                    read.outputs()
+                ));
+            }
+        }
+        let input_bind_group = has_inputs.then(|| {
+            let inputs = Pass::input_views(&passes, &source.inputs);
+            input_bind_group(device, &input_layout, images.sampler, &inputs, channels)
+        });
+        passes.push(Pass {
+            pipeline,
+            groups,
+            uniforms: source.uniforms,
+            feedback: is_feedback,
+            inputs: source.inputs,
+            input_layout,
+            input_bind_group,
+            background,
+            layers,
+            target,
+            extra_targets,
+            multisampled,
+            accumulate,
+            depth: config.depth && source.uniforms,
+            layout,
+            includes: source.includes,
+        });
+    }
+    Ok(passes)
+}
+
+// for shaders written when every built-in uniform had a bind group of its own, with the tile
+// in group 4 and pass inputs and --texture images in group 7
+const LAYOUT_MIGRATION: &str = "The built-in uniforms share group 0, one binding each in \
+    --uniforms order, the --tile cell is in group 1 and pass inputs and --texture images are in \
+    group 2, so shaders declaring a group per uniform need their declarations moved. \
+    Press B to print each pass's layout.";
+
+// how many bind groups a pass needs, up to the last one its shader declares a binding in,
+// or none if the shader doesn't parse
+// errors on a declared binding that nothing is bound to, instead of leaving it for pipeline
+// creation to complain about the layout
+fn pass_groups(source: &PassSource, bound: &[Vec<u32>]) -> Result<Option<u32>, String> {
+    let declared = match declared_bindings(&source.source) {
+        Some(declared) => declared,
+        None => return Ok(None),
+    };
+    for &(group, binding) in &declared {
+        match bound.get(group as usize) {
+            Some(bindings) if bindings.contains(&binding) => {}
+            Some(bindings) => {
+                return Err(format!(
+                    "Pass `{}` declares group({}) binding({}), but that group only has bindings \
+                    {:?}\n{}",
+                    source.name, group, binding, bindings, LAYOUT_MIGRATION
+                ))
+            }
+            None => {
+                return Err(format!(
+                    "Pass `{}` declares group({}) binding({}), but it only has {} bind groups\n{}",
+                    source.name,
+                    group,
+                    binding,
+                    bound.len(),
+                    LAYOUT_MIGRATION
+                ))
+            }
+        }
+    }
+    Ok(Some(
+        declared
+            .iter()
+            .map(|&(group, _)| group + 1)
+            .max()
+            .unwrap_or(0),
+    ))
+}
+
+// the --texture channels a pass can sample
+// built-in post-processing passes don't get them
+fn pass_channels(uniforms: bool, channels: &[Channel]) -> &[Channel] {
+    if uniforms {
+        channels
+    } else {
+        &[]
+    }
+}
+
+#[derive(Debug)]
+struct Pass {
+    pipeline: RenderPipeline,
+    // bind groups in the pipeline's layout, up to the last one the shader declares
+    // only these get set when drawing
+    groups: u32,
+    // whether the built-in uniforms are bound, see PassSource
+    uniforms: bool,
+    // whether it's the --feedback pass, which draws into the feedback buffer instead of a
+    // target of its own
+    feedback: bool,
+    // outputs of earlier passes whose targets are bound as inputs
+    inputs: Vec<PassInput>,
+    input_layout: BindGroupLayout,
+    input_bind_group: Option<BindGroup>,
+    // draws --background-image first, for the shader's last pass to blend over
+    background: Option<RenderPipeline>,
+    // --layers drawn over the shader's last pass, once it's done
+    layers: Vec<CompositeLayer>,
+    // None for the final pass, which renders to the surface, and the --feedback pass
+    target: Option<PassTarget>,
+    // for `[[location(1)]]` onwards, when the shader writes more than one output
+    extra_targets: Vec<PassTarget>,
+    // what it draws into before resolving to its targets, with --msaa
+    multisampled: Option<Multisampled>,
+    // whether it blends into what it drew before for --accumulate, instead of clearing it
+    accumulate: bool,
+    // whether it tests against the --depth buffer
+    depth: bool,
+    // the pipeline layout's bindings, printed for debugging mismatched declarations
+    layout: String,
+    // files the shader included, for --watch
+    includes: Vec<PathBuf>,
+}
+
+impl Pass {
+    fn input_views<'a>(passes: &'a [Pass], inputs: &[PassInput]) -> Vec<&'a TextureView> {
+        inputs
+            .iter()
+            .map(|input| {
+                passes[input.pass]
+                    .output(input.location)
+                    .expect("Pass inputs must be intermediate passes")
+                    .view()
+            })
+            .collect()
+    }
+
+    // the target of a `[[location(n)]]` output, if it's offscreen
+    fn output(&self, location: u32) -> Option<&PassTarget> {
+        match location {
+            0 => self.target.as_ref(),
+            location => self.extra_targets.get(location as usize - 1),
+        }
+    }
+
+    // how many `[[location(n)]]` outputs the shader writes
+    fn outputs(&self) -> u32 {
+        1 + self.extra_targets.len() as u32
+    }
+
+    // whether it's the final pass, drawn to the surface in the surface's format
+    // the --feedback pass has no target of its own either, but draws into the feedback buffer
+    fn to_surface(&self) -> bool {
+        self.target.is_none() && !self.feedback
+    }
+}
+
+// whether frames can be copied out of the window's surface, for captures and picking
+// wgpu can't ask the surface, but its Metal and GL surfaces can only be rendered to, while DX12's
+// can be copied from, as can Vulkan's on every driver in practice
+fn surface_can_copy(backend: Backend) -> bool {
+    matches!(backend, Backend::Vulkan | Backend::Dx12)
+}
+
+// for when the adapter doesn't say which surface format it prefers
+const FALLBACK_SURFACE_FORMAT: TextureFormat = TextureFormat::Bgra8UnormSrgb;
+
+// smallest the quad can be scaled down to while running
+const MIN_QUAD_SCALE: f32 = 0.05;
+// how much - and = scale the quad by
+const QUAD_SCALE_STEP: f32 = 1.1;
+
+// most time between clicks for them to count as a double or triple click
+const CLICK_INTERVAL: Duration = Duration::from_millis(400);
+
+// the slowest and fastest time can be played at, where f32 seconds are still precise enough
+const MIN_TIME_SCALE: f32 = 1.0 / 64.0;
+const MAX_TIME_SCALE: f32 = 64.0;
+
+// how far time moves each time . is pressed while paused, about a frame at 60fps
+// the clock's time, so it's scaled like the rest
+const TIME_STEP: Duration = Duration::from_millis(16);
+
+// whether a uniform is a UniformRing, bound with a dynamic offset
+fn is_ring(name: UniformName) -> bool {
+    matches!(name, UniformName::Time | UniformName::Mouse)
+}
+
+// bind group of the TileUniform, which changes between draws
+const TILE_GROUP: u32 = 1;
+
+// binding of the --storage buffer in group 0, clear of the uniforms so more can be added
+const STORAGE_BINDING: u32 = 16;
+
+// bytes in the --storage buffer, rounded up as storage buffers are read in whole u32s
+fn storage_size(config: &Config) -> Option<u64> {
+    config.storage.map(|size| size.div_ceil(4) * 4)
+}
+
+// the --storage buffer is the --compute shader's to write, if there is one
+fn storage_access(config: &Config) -> StorageAccess {
+    if config.compute.is_some() {
+        StorageAccess::ReadOnly
+    } else {
+        StorageAccess::Fragment
+    }
+}
+
+// the shader time of the next frame, from set_time if it's been called, which stays put
+// whatever the clock says, or else a --gif recording's, which steps by exactly one frame however
+// long frames take to render, or else the clock's
+fn shader_time(
+    time_override: Option<Duration>,
+    recording: Option<Duration>,
+    clock: Duration,
+) -> Duration {
+    time_override.or(recording).unwrap_or(clock)
+}
+
+// wrap back to 0 at --time-max, so f32 time in shaders never gets too big to be precise
+fn wrap_time(elapsed: Duration, time_max: Option<Duration>) -> Duration {
+    match time_max {
+        Some(time_max) => Duration::from_nanos((elapsed.as_nanos() % time_max.as_nanos()) as u64),
+        None => elapsed,
+    }
+}
+
 #[derive(Debug)]
 pub(super) struct State {
-    surface: Surface,
+    output: Output,
     device: Device,
     queue: Queue,
     size: PhysicalSize<u32>,
+    scale_factor: f64,
     surface_config: SurfaceConfiguration,
-    render_pipeline: RenderPipeline,
-    render_pipeline_layout: PipelineLayout,
-    vertex_buffer: Buffer,
-    index_buffer: Buffer,
-    num_indices: u32,
+    // the surface can be switched between these, for debugging colours
+    surface_formats: Vec<TextureFormat>,
+    passes: Vec<Pass>,
+    // every built-in uniform except the tile's, one per binding
+    uniforms_bind_group_layout: BindGroupLayout,
+    // None until the shader's loaded, as the buffers are grown to fit it before binding them
+    uniforms_bind_group: Option<BindGroup>,
+    tile_bind_group_layout: BindGroupLayout,
+    // the built-in uniforms' layouts, to check the shader's declarations against
+    host_uniforms: Vec<HostUniform>,
+    sampler: Sampler,
+    // images from --texture
+    channels: Vec<Channel>,
+    // how the channels were sampled, for reloading them the same way
+    sampling: Sampling,
+    // reloads --texture files when they change
+    texture_watcher: TextureWatcher,
+    // decoding into the last channel, with --video
+    video: Option<VideoTexture>,
+    // analysed into the audio uniform, with --audio
+    audio_file: Option<Audio>,
+    background: Option<Background>,
+    compositor: Compositor,
+    // drawn by the --feedback pass, and sampled by it and the shader's passes
+    feedback: Option<Feedback>,
+    // for the shader's passes, at the size they render at, with --depth
+    depth: Option<TextureView>,
+    // from --reference, drawn over the finished frame when comparing
+    reference: Option<Reference>,
+    mipmap_generator: MipmapGenerator,
+    overlay: Overlay,
+    // covers the screen, for post-processing and everything drawn over the shader
+    quad: Mesh,
+    // --quad-scale of the screen, for the shader's passes that don't use --fullscreen-tri
+    shader_quad: Mesh,
+    quad_scale: f32,
+    // fraction of the window's resolution the shader's passes render at, for --scale
+    render_scale: f32,
+    adaptive_scale: Option<AdaptiveScale>,
     background_colour: Color,
+    // when the clock last started counting from time_base, as shader time moves on from there
+    // at time_scale
     start_time: Instant,
-    time: UniformBinding<TimeUniform>,
-    mouse: UniformBinding<MouseUniform>,
+    // shader time at start_time, from --start-time and any time scaled at a different rate
+    time_base: Duration,
+    // how fast shader time goes compared to the clock, from --time-scale
+    // changing it adds the time so far to time_base, so time carries on from where it was
+    time_scale: f32,
+    // when time was paused, or None while it's running
+    // start_time is moved on by the pause on resuming, so time carries on from where it stopped
+    paused_at: Option<Instant>,
+    // shader time given with set_time, which stays put whatever the clock or pausing says
+    time_override: Option<Duration>,
+    // shader files dropped on the window since the last update, which loads the first
+    dropped_files: Vec<PathBuf>,
+    // every file the shader and --compute shader include, shared with the --watch thread
+    // so it watches them too
+    includes: Arc<Mutex<Vec<String>>>,
+    // frames rendered since startup
+    frame: u64,
+    // when the last frame was updated, for measuring frame times
+    // None until the first, so startup isn't counted as a frame
+    last_frame: Option<Instant>,
+    // shader time at the last update, before wrapping at --time-max, for the time uniform's
+    // delta
+    last_elapsed: Duration,
+    // whether the shader's been loaded since the last update, for the time uniform
+    just_reloaded: bool,
+    // frames rendered with the current shader, counting from 0 for the first
+    frames_since_reload: u32,
+    // frames averaged by --accumulate, including the one being drawn
+    samples: u32,
+    // whether anything that changes the image besides time has changed since the last update,
+    // like the mouse, the window size or a custom uniform, so --accumulate starts over
+    dirty: bool,
+    // rewritten every frame, so they get a ring of buffer regions instead of a single buffer
+    time: UniformRing<TimeUniform>,
+    mouse: UniformRing<MouseUniform>,
+    // for the mouse uniform's idle time
+    last_mouse_activity: Instant,
+    // when the left button was last pressed, while it might still be followed by another click
+    last_click: Option<Instant>,
+    // when each of the left, right and middle buttons went down, while they're held
+    button_presses: [Option<Instant>; 3],
+    // in physical pixels, for the mouse uniform's path length
+    // None while the cursor is outside the window, so its jump back in isn't counted
+    last_cursor: Option<PhysicalPosition<f64>>,
+    orbit_camera: OrbitCamera,
+    camera: UniformBuffer<CameraUniform>,
+    frame_times: UniformBuffer<FrameTimesUniform>,
+    // one per cell of the --tile grid, in the same order as TileUniform's index
+    tiles: Vec<UniformBinding<TileUniform>>,
+    lod: UniformBuffer<LodUniform>,
+    custom: UniformBuffer<CustomUniform>,
+    // saved sets of custom uniform values, for the number keys
+    presets: PresetBank,
+    // the custom uniform slot [ and ] change
+    selected_param: usize,
+    adapter: UniformBuffer<AdapterUniform>,
+    rng: UniformBuffer<RngUniform>,
+    display: UniformBuffer<DisplayUniform>,
+    window: UniformBuffer<WindowUniform>,
+    resolution: UniformBuffer<ResolutionUniform>,
+    date: UniformBuffer<DateUniform>,
+    keyboard: UniformBuffer<KeyboardUniform>,
+    audio: UniformBuffer<AudioUniform>,
+    // read and written by the shader, with --storage
+    storage: Option<Buffer>,
+    // writes the storage buffer before each frame's passes, with --compute
+    compute: Option<Compute>,
+    // sets custom uniforms, with --osc
+    osc: Option<OscListener>,
+    // sets custom uniforms, with --midi
+    midi: Option<MidiListener>,
+    // sets custom uniforms every frame, with --script
+    script: Option<Script>,
+    // steadies the frame rate, with --pace
+    pacer: Option<FramePacer>,
+    // where the beat uniforms are counted from
+    tempo: Tempo,
+    // writes every frame out, with --raw or --pipe
+    raw: Option<RawWriter>,
+    // describe the frames beside each capture file, with --capture-metadata
+    capture_metadata: Vec<MetadataWriter>,
+    // of the shader as last loaded, for the metadata, or empty without any
+    shader_hash: String,
+    // collects frames until it has enough, with --gif
+    gif: Option<GifRecorder>,
+    // whether to read back the pixel under the cursor after the next frame
+    pick_pixel: bool,
+    // where to save the next frame as a PNG
+    screenshot: Option<PathBuf>,
+    // whether the surface can be reconfigured for frames to be copied out of it
+    can_copy: bool,
+    // whether the last one couldn't be saved, for --output's exit code
+    screenshot_failed: bool,
+    // whether encoding the --gif went wrong, for recording it without a window
+    gif_failed: bool,
+    // scales frames down for the window icon, with --live-icon
+    icon: Option<IconPreview>,
+    // frame rate readout for the window title, with --show-fps
+    fps_title: Option<FpsTitle>,
+    // whether a --gif recording is done, and the program should exit
+    finished: bool,
     config: Config,
 }
 
 impl State {
     // need async for creating some wgpu types
-    pub(super) async fn new(window: &Window, config: Config) -> Self {
+    // errors if there's no GPU to use or the shader doesn't load, and panics for anything else
+    pub(super) async fn new(target: Target<'_>, config: Config) -> Result<Self, StateError> {
         // make sure dimensions are nonzero (or crash)
-        let size = window.inner_size();
+        let (window_size, scale_factor) = match target {
+            Target::Window(window) => (window.inner_size(), window.scale_factor()),
+            Target::Offscreen(size) => (size, 1.0),
+        };
+        // a window can report 0 before it's shown, e.g. while it goes fullscreen, and resize
+        // catches up once it has a real size
+        let window_size = PhysicalSize::new(window_size.width.max(1), window_size.height.max(1));
 
         // GET GPU DEVICE
         log::debug!("Setting up GPU device");
 
         // instance is a handle to the GPU
-        // Backends::all = Vulkan, Metal, DX12, Browser WebGPU
-        let instance = wgpu::Instance::new(Backends::all()); // for making adapters and surfaces
-                                                             // SAFETY: window has to allow creating surface and reference must remain valid
-                                                             // until surface dropped
-        let surface = unsafe { instance.create_surface(window) };
-        let adapter = instance
-            .request_adapter(&RequestAdapterOptions {
-                power_preference: PowerPreference::default(),
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            })
-            .await
-            .expect("Could not find GPU adapter");
+        // Backends::all = Vulkan, Metal, DX12, Browser WebGPU, unless --backend picks one
+        // debug builds of wgpu turn on backend validation themselves, but release builds don't
+        // have a switch for it, so ask the Vulkan loader for its validation layer directly
+        if config.validate && !cfg!(debug_assertions) {
+            std::env::set_var("VK_INSTANCE_LAYERS", "VK_LAYER_KHRONOS_validation");
+        }
+        // for making adapters and surfaces
+        let instance = wgpu::Instance::new(config.backend.backends());
+        let surface = match target {
+            // SAFETY: window has to allow creating surface and reference must remain valid
+            // until surface dropped
+            Target::Window(window) => Some(unsafe { instance.create_surface(window) }),
+            Target::Offscreen(_) => None,
+        };
+        // a discrete GPU if there is one, but an integrated one will do
+        let mut adapter = None;
+        for power_preference in [PowerPreference::HighPerformance, PowerPreference::LowPower] {
+            adapter = instance
+                .request_adapter(&RequestAdapterOptions {
+                    power_preference,
+                    compatible_surface: surface.as_ref(),
+                    force_fallback_adapter: config.fallback_adapter,
+                })
+                .await;
+            if adapter.is_some() {
+                break;
+            }
+            log::warn!("No GPU adapter found for {:?}", power_preference);
+        }
+        let adapter = adapter.ok_or(StateError::NoAdapter {
+            fallback: config.fallback_adapter,
+            backend: config.backend,
+        })?;
+        // fail early if the shader needs something the GPU can't do
+        let texture_files: Vec<_> = config
+            .texture
+            .iter()
+            .map(TextureFile::try_read)
+            .chain(config.texture_array.iter().map(TextureFile::read_array))
+            .chain(config.volume.iter().map(TextureFile::read_volume))
+            .collect::<Result<_, _>>()
+            .map_err(StateError::Texture)?;
+        let background_file = config
+            .background_image
+            .as_ref()
+            .map(TextureFile::try_read)
+            .transpose()
+            .map_err(StateError::Texture)?;
+        let reference_file = config
+            .reference
+            .as_ref()
+            .map(TextureFile::try_read)
+            .transpose()
+            .map_err(StateError::Texture)?;
+        // rather than wgpu's defaults, which are less than most GPUs manage
+        let max_texture_dimension = adapter.limits().max_texture_dimension_2d;
+        log::info!(
+            "GPU supports textures up to {} pixels across",
+            max_texture_dimension
+        );
+        for file in texture_files
+            .iter()
+            .chain(&background_file)
+            .chain(&reference_file)
+        {
+            let (width, height) = file.dimensions();
+            if width > max_texture_dimension || height > max_texture_dimension {
+                return Err(StateError::Texture(format!(
+                    "Texture {} is {}x{}, but this GPU only supports textures up to {} pixels across",
+                    file.path(),
+                    width,
+                    height,
+                    max_texture_dimension
+                )));
+            }
+        }
+        let size = texture_size(window_size, max_texture_dimension);
+        for file in &texture_files {
+            let max_layers = Limits::default().max_texture_array_layers;
+            let max_volume = Limits::default().max_texture_dimension_3d;
+            let (width, height) = file.dimensions();
+            if file.dimension() == TextureViewDimension::D3 {
+                if width.max(height).max(file.layers()) > max_volume {
+                    return Err(StateError::Texture(format!(
+                        "Volume {} is {}x{}x{}, but volumes can only be {} voxels across",
+                        file.path(),
+                        width,
+                        height,
+                        file.layers(),
+                        max_volume
+                    )));
+                }
+            } else if file.layers() > max_layers {
+                return Err(StateError::Texture(format!(
+                    "Texture array {} has {} images, but only {} layers are allowed",
+                    file.path(),
+                    file.layers(),
+                    max_layers
+                )));
+            }
+            if !adapter.features().contains(file.required_features()) {
+                return Err(StateError::Texture(format!(
+                    "Texture {} uses {:?}, which this GPU adapter doesn't support",
+                    file.path(),
+                    file.format()
+                )));
+            }
+        }
+        let features = config
+            .features
+            .iter()
+            .fold(Features::empty(), |features, &feature| features | feature);
+        let missing = features - adapter.features();
+        if !missing.is_empty() {
+            let missing: Vec<_> = config
+                .features
+                .iter()
+                .filter(|&&feature| missing.contains(feature))
+                .map(|&feature| feature_name(feature))
+                .collect();
+            return Err(StateError::Config(format!(
+                "GPU adapter doesn't support requested features: {}",
+                missing.join(", ")
+            )));
+        }
         // request a device with that adapter
         // devices are where the magic happens
         let (device, queue) = adapter
             .request_device(
                 &DeviceDescriptor {
-                    features: Features::empty(), // no features
-                    limits: Limits::default(),
+                    // only features asked for on the command line, or needed by textures
+                    features: texture_files
+                        .iter()
+                        .chain(&background_file)
+                        .chain(&reference_file)
+                        .fold(features, |features, file| {
+                            features | file.required_features()
+                        }),
+                    // the built-in uniforms can need more uniform buffers than the default,
+                    // so allow as many as the GPU can handle
+                    // and the same for the --storage buffer's size and --compute's --workgroups
+                    // the uniforms, tile, pass inputs and feedback fit the default 4 bind groups
+                    limits: Limits {
+                        max_texture_dimension_2d: max_texture_dimension,
+                        max_uniform_buffers_per_shader_stage: adapter
+                            .limits()
+                            .max_uniform_buffers_per_shader_stage,
+                        max_storage_buffers_per_shader_stage: adapter
+                            .limits()
+                            .max_storage_buffers_per_shader_stage,
+                        max_storage_buffer_binding_size: adapter
+                            .limits()
+                            .max_storage_buffer_binding_size,
+                        max_compute_workgroups_per_dimension: adapter
+                            .limits()
+                            .max_compute_workgroups_per_dimension,
+                        ..Limits::default()
+                    },
                     label: None,
                 },
                 None, // trace path
             )
             .await
-            .expect("Could not acquire GPU device");
+            .map_err(|error| StateError::Device {
+                adapter: adapter.get_info().name,
+                error,
+            })?;
+        // each uniform from --uniforms has a buffer of its own, and so does the --tile cell
+        let uniform_buffer_count = config.uniforms.len() as u32 + 1;
+        let max_uniform_buffer_count = device.limits().max_uniform_buffers_per_shader_stage;
+        if uniform_buffer_count > max_uniform_buffer_count {
+            return Err(StateError::Config(format!(
+                "--uniforms binds {} uniforms, and with the --tile cell that's {} uniform buffers, \
+                but {} only allows {} per shader stage, so leave some out of --uniforms",
+                config.uniforms.len(),
+                uniform_buffer_count,
+                adapter.get_info().name,
+                max_uniform_buffer_count
+            )));
+        }
+        // log every error and keep going, instead of panicking at the first one
+        if config.validate {
+            device.on_uncaptured_error(|error| match error {
+                Error::Validation { description, .. } => {
+                    log::error!("GPU validation error: {}", description)
+                }
+                Error::OutOfMemory { source } => log::error!("GPU out of memory: {}", source),
+            });
+        }
         // config for the surface
         log::debug!("Configuring surface");
+        // --raw, --pipe, --gif and --live-icon read frames back from the start, and offscreen
+        // frames are only read back, while screenshots and picking ask for it once they're used
+        let can_copy = surface.is_none() || surface_can_copy(adapter.get_info().backend);
+        let copy_needed = surface.is_none()
+            || config.raw.is_some()
+            || config.pipe.is_some()
+            || config.gif.is_some()
+            || config.live_icon;
+        if copy_needed && !can_copy {
+            return Err(StateError::Readback(adapter.get_info().backend));
+        }
         let surface_config = SurfaceConfiguration {
-            // allows rendering textures to screen
-            usage: TextureUsages::RENDER_ATTACHMENT,
+            // allows rendering textures to screen, and copying frames back out if that's needed
+            usage: if copy_needed {
+                TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC
+            } else {
+                TextureUsages::RENDER_ATTACHMENT
+            },
             // choose texture format to match what the screen prefers
-            format: surface
-                .get_preferred_format(&adapter)
-                .expect("Couldn't get adapter preferred surface format"),
+            // wgpu can't list the surface's other formats, so without a preference this guesses
+            // the one nearly every platform supports
+            // offscreen frames are RGBA like PNGs, so they needn't be swizzled to save them
+            format: match &surface {
+                Some(surface) => surface.get_preferred_format(&adapter).unwrap_or_else(|| {
+                    log::warn!(
+                        "Adapter has no preferred surface format, falling back to {:?}",
+                        FALLBACK_SURFACE_FORMAT
+                    );
+                    FALLBACK_SURFACE_FORMAT
+                }),
+                None => TextureFormat::Rgba8UnormSrgb,
+            },
             width: size.width,
             height: size.height,
-            // vsync on, is the only good option on mobile devices
-            present_mode: PresentMode::Fifo,
+            // vsync on by default, is the only good option on mobile devices
+            // --pace does its own timing instead
+            // wgpu can't list the modes the surface supports, but it falls back to vsync with
+            // a warning if it's given one that isn't
+            present_mode: if config.pace.is_some() {
+                PresentMode::Immediate
+            } else {
+                config.present_mode.present_mode()
+            },
+        };
+        let output = surface.map_or(Output::Offscreen, Output::Surface);
+        output.configure(&device, &surface_config);
+        if let Output::Surface(_) = output {
+            log::info!("Presenting frames with {:?}", surface_config.present_mode);
+        }
+        // wgpu 0.12 can't list what else the surface supports, only its preferred format, but
+        // surfaces take both sRGB and linear versions of it, as long as the adapter can render
+        // to them
+        let surface_formats: Vec<_> = std::iter::once(surface_config.format)
+            .chain(srgb_counterpart(surface_config.format).filter(|&format| {
+                adapter
+                    .get_texture_format_features(format)
+                    .allowed_usages
+                    .contains(TextureUsages::RENDER_ATTACHMENT)
+            }))
+            .collect();
+        log::debug!("Surface formats to cycle through: {:?}", surface_formats);
+        let raw = match (&config.raw, &config.pipe) {
+            (Some(path), _) => Some(RawWriter::create(path).map_err(|e| {
+                StateError::Config(format!("Could not create raw output {}: {}", path, e))
+            })?),
+            (_, Some(path)) => Some(RawWriter::create_pipe(path).map_err(|e| {
+                StateError::Config(format!("Could not open named pipe {}: {}", path, e))
+            })?),
+            (None, None) => None,
         };
-        surface.configure(&device, &surface_config);
+        if config.capture_metadata && config.raw.as_deref() == Some("-") {
+            log::warn!("--raw to stdout has nowhere beside it for --capture-metadata");
+        }
+        let capture_metadata: Vec<_> = config
+            .raw
+            .iter()
+            .chain(&config.pipe)
+            .chain(&config.gif)
+            .filter(|&path| config.capture_metadata && path != "-")
+            .map(|path| {
+                MetadataWriter::create(path).map_err(|e| {
+                    StateError::Config(format!(
+                        "Could not create capture metadata for {}: {}",
+                        path, e
+                    ))
+                })
+            })
+            .collect::<Result<_, _>>()?;
 
         log::debug!("Setting up uniform bindings");
 
+        if let Some(scissor) = config.scissor {
+            let right = scissor.x.saturating_add(scissor.width);
+            let bottom = scissor.y.saturating_add(scissor.height);
+            if right > size.width || bottom > size.height {
+                log::warn!(
+                    "Scissor rectangle goes outside the {}x{} window, so it'll be clamped",
+                    size.width,
+                    size.height
+                );
+            }
+        }
+
         // TIME BINDING
         let start_time = Instant::now();
-        let time_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-            label: Some("Time Buffer Bind Group Layout"),
-            entries: &[BindGroupLayoutEntry {
-                binding: 0,
-                visibility: ShaderStages::VERTEX_FRAGMENT,
-                ty: BindingType::Buffer {
-                    ty: BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            }],
-        });
-        let time = TimeUniform::new(start_time).make_binding(&device, &time_bind_group_layout);
+        let time_base = config.start_time.unwrap_or_default();
+        let time = TimeUniform::new(time_base).make_ring(&device, 0);
 
         // MOUSE BINDING
-        let mouse_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-            label: Some("Mouse Buffer Bind Group Layout"),
-            entries: &[BindGroupLayoutEntry {
-                binding: 0,
-                visibility: ShaderStages::VERTEX_FRAGMENT,
-                ty: BindingType::Buffer {
-                    ty: BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            }],
-        });
+        let mouse = MouseUniform::new().make_ring(&device, 0);
 
-        let mouse = MouseUniform::new().make_binding(&device, &mouse_bind_group_layout);
+        // CAMERA BINDING
+        let orbit_camera = OrbitCamera::new(size.width, size.height);
+        let camera = CameraUniform::new(orbit_camera.view_projection()).make_buffer(&device);
 
-        // Collect bind group layouts into one pipeline layout
-        let render_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
-            label: Some("Render Pipeline Layout"),
-            // collect bind groups here
-            // first elem is `[[group(0)]]` etc
-            bind_group_layouts: &[&time_bind_group_layout, &mouse_bind_group_layout],
-            push_constant_ranges: &[],
-        });
+        // FRAME TIMES BINDING
+        let frame_times = FrameTimesUniform::new().make_buffer(&device);
 
-        // Make geometry buffers
-        let vertex_buffer = device.create_buffer_init(&util::BufferInitDescriptor {
-            label: Some("Vertex Buffer"),
-            contents: bytemuck::cast_slice(VERTICES),
-            usage: BufferUsages::VERTEX,
-        });
-        let index_buffer = device.create_buffer_init(&util::BufferInitDescriptor {
-            label: Some("Index Buffer"),
-            contents: bytemuck::cast_slice(INDICES),
-            usage: BufferUsages::INDEX,
+        // TILE BINDINGS
+        // these never change, so each cell gets its own buffer and bind group
+        let tile_bind_group_layout =
+            uniform_bind_group_layout(&device, "Tile Buffer Bind Group Layout");
+        let Tile { rows, columns } = config.tile;
+        let tiles = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .map(|(column, row)| {
+                TileUniform::new(column, row, columns, rows)
+                    .make_binding(&device, &tile_bind_group_layout)
+            })
+            .collect();
+
+        // LOD BINDING
+        // the shader's passes are what get mipmaps, and they render at --scale
+        let render_size = scaled_size(size, config.scale);
+        let mip_levels = if config.mipmaps {
+            mip_level_count(render_size.width, render_size.height)
+        } else {
+            1
+        };
+        let lod = LodUniform::new(config.lod_bias, mip_levels).make_buffer(&device);
+
+        // CUSTOM BINDING
+        if config.custom_uniforms.len() > config.params {
+            return Err(StateError::Config(format!(
+                "Too many custom uniforms: {} are named, but there are only {} slots",
+                config.custom_uniforms.len(),
+                config.params
+            )));
+        }
+        let mut custom = CustomUniform::new(config.params);
+        for param in &config.param {
+            if param.index >= custom.len() {
+                return Err(StateError::Config(format!(
+                    "--param sets slot {}, but there are only {} slots",
+                    param.index, config.params
+                )));
+            }
+            custom.set(param.index, param.value);
+        }
+        let presets = PresetBank::load(&config).map_err(StateError::Config)?;
+        if let Some(name) = &config.preset {
+            let preset = presets.find(name).ok_or_else(|| {
+                StateError::Config(format!(
+                    "No preset called `{}` in {}",
+                    name,
+                    presets.path().display()
+                ))
+            })?;
+            for (index, &value) in preset.values().iter().enumerate() {
+                custom.set(index, value);
+            }
+        }
+        let custom = custom.make_buffer(&device, 0);
+        let osc = config.osc.and_then(|port| {
+            OscListener::spawn(port, config.custom_uniforms.clone())
+                .map_err(|error| log::error!("Couldn't listen for OSC on port {}: {}", port, error))
+                .ok()
         });
-        let num_indices = INDICES.len() as u32;
+        if let Some(mapping) = config
+            .midi_map
+            .iter()
+            .find(|mapping| mapping.slot >= config.params)
+        {
+            return Err(StateError::Config(format!(
+                "MIDI controller {} is mapped to slot {}, but there are only {} slots",
+                mapping.controller, mapping.slot, config.params
+            )));
+        }
+        let midi = config
+            .midi
+            .then(|| MidiListener::spawn(config.midi_map.clone()))
+            .transpose()
+            .map_err(|error| {
+                StateError::Config(format!("Couldn't start MIDI thread: {}", error))
+            })?;
+        let script = config
+            .script
+            .as_deref()
+            .map(|path| {
+                Script::load(path, config.custom_uniforms.clone()).map_err(|error| {
+                    StateError::Config(format!("Failed loading script {}: {}", path, error))
+                })
+            })
+            .transpose()?;
 
-        // LOAD SHADER
-        let shader = new_shader(&device, &config.path);
+        // ADAPTER BINDING
+        let adapter_uniform =
+            AdapterUniform::new(adapter.get_info().backend, max_texture_dimension)
+                .make_buffer(&device);
 
-        // COLLECT BIND GROUPS AND SHADERS INTO PIPELINE
+        // RNG BINDING
+        let rng = RngUniform::new(config.seed).make_buffer(&device);
 
-        let render_pipeline =
-            new_pipeline(&device, &surface_config, &render_pipeline_layout, shader);
+        // DISPLAY BINDING
+        let refresh_rate = match target {
+            Target::Window(window) => refresh_rate(window).unwrap_or_else(|| {
+                log::info!(
+                    "Can't tell the monitor's refresh rate, so assuming {}Hz",
+                    FALLBACK_REFRESH_RATE
+                );
+                FALLBACK_REFRESH_RATE
+            }),
+            // no monitor to ask
+            Target::Offscreen(_) => 0.0,
+        };
+        let display = DisplayUniform::new(refresh_rate).make_buffer(&device);
 
-        // a bluish colour as default
-        let background_colour = Color {
-            r: 0.1,
-            g: 0.2,
-            b: 0.3,
-            a: 1.0,
+        // WINDOW BINDING
+        let (position, desktop_size) = match target {
+            Target::Window(window) => window_placement(window),
+            Target::Offscreen(_) => ([0.0; 2], [0.0; 2]),
         };
-        Self {
-            surface,
-            device,
-            queue,
-            size,
-            surface_config,
-            render_pipeline,
-            render_pipeline_layout,
-            vertex_buffer,
-            index_buffer,
-            num_indices,
-            background_colour,
-            start_time,
-            time,
-            mouse,
-            config,
-        }
-    }
+        let window_uniform = WindowUniform::new(position, desktop_size).make_buffer(&device);
 
-    pub(super) fn refresh_shader(&mut self) {
-        self.render_pipeline = new_pipeline(
-            &self.device,
-            &self.surface_config,
-            &self.render_pipeline_layout,
-            new_shader(&self.device, &self.config.path),
-        )
-    }
+        // RESOLUTION BINDING
+        let resolution =
+            ResolutionUniform::new(render_size.width, render_size.height, config.scale)
+                .make_buffer(&device);
 
-    pub(super) fn resize(&mut self, new_size: PhysicalSize<u32>) {
+        // DATE BINDING
+        let date = DateUniform::new().make_buffer(&device);
+
+        // KEYBOARD BINDING
+        let keyboard = KeyboardUniform::new().make_buffer(&device);
+
+        // AUDIO BINDING
+        let audio = AudioUniform::new().make_buffer(&device);
+        // only played in a window, since offscreen renders don't run in real time
+        let audio_file = config
+            .audio
+            .as_ref()
+            .map(|path| Audio::load(Path::new(path), matches!(target, Target::Window(_))))
+            .transpose()
+            .map_err(StateError::Audio)?;
+
+        // STORAGE BINDING
+        // starts zeroed, and then keeps whatever the shader writes from frame to frame
+        let storage = storage_size(&config).map(|size| {
+            device.create_buffer(&BufferDescriptor {
+                label: Some("Storage Buffer"),
+                size,
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        });
+
+        // the rings need an offset to say which region to read
+        let dynamic: Vec<_> = config.uniforms.iter().map(|&name| is_ring(name)).collect();
+        let host_layout = |name: UniformName| match name {
+            UniformName::Time => host_layout::<TimeUniform>(),
+            UniformName::Mouse => host_layout::<MouseUniform>(),
+            UniformName::Camera => host_layout::<CameraUniform>(),
+            UniformName::FrameTimes => host_layout::<FrameTimesUniform>(),
+            UniformName::Lod => host_layout::<LodUniform>(),
+            // a single array of vec4s
+            UniformName::Custom => (custom.uniform().as_bytes().len() as u64, &[0][..]),
+            UniformName::Adapter => host_layout::<AdapterUniform>(),
+            UniformName::Rng => host_layout::<RngUniform>(),
+            UniformName::Display => host_layout::<DisplayUniform>(),
+            UniformName::Window => host_layout::<WindowUniform>(),
+            UniformName::Resolution => host_layout::<ResolutionUniform>(),
+            UniformName::Date => host_layout::<DateUniform>(),
+            UniformName::Keyboard => host_layout::<KeyboardUniform>(),
+            UniformName::Audio => host_layout::<AudioUniform>(),
+        };
+        let (tile_size, tile_offsets) = host_layout::<TileUniform>();
+        let host_uniforms = config
+            .uniforms
+            .iter()
+            .zip(0..)
+            .map(|(&name, binding)| {
+                let (size, offsets) = host_layout(name);
+                HostUniform {
+                    group: 0,
+                    binding,
+                    name: name
+                        .to_possible_value()
+                        .expect("Uniform names are all visible")
+                        .get_name()
+                        .to_owned(),
+                    size,
+                    offsets,
+                }
+            })
+            .chain(std::iter::once(HostUniform {
+                group: TILE_GROUP,
+                binding: 0,
+                name: "tile".to_owned(),
+                size: tile_size,
+                offsets: tile_offsets,
+            }))
+            .collect();
+        let uniforms_bind_group_layout = uniforms_bind_group_layout(
+            &device,
+            "Uniforms Bind Group Layout",
+            &dynamic,
+            storage
+                .as_ref()
+                .map(|_| (STORAGE_BINDING, storage_access(&config))),
+        );
+        // the same uniforms, for the compute shader to read while it writes the storage buffer
+        let compute = config
+            .compute
+            .as_ref()
+            .and(storage_size(&config))
+            .map(|size| Compute::new(&device, &dynamic, STORAGE_BINDING, size));
+
+        // for passes sampling earlier passes' output
+        let address_mode = config.wrap.address_mode();
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("Pass Sampler"),
+            address_mode_u: address_mode,
+            address_mode_v: address_mode,
+            address_mode_w: address_mode,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            // blend between mip levels, for --mipmaps
+            mipmap_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+        let mipmap_generator = MipmapGenerator::new(&device);
+        let video = config
+            .video
+            .as_ref()
+            .map(|input| VideoTexture::open(&device, input));
+        let sampling = Sampling {
+            address_mode,
+            anisotropy: anisotropy(&adapter, config.anisotropy),
+        };
+        let channels = texture_files
+            .iter()
+            .map(|file| {
+                Channel::new(
+                    &device,
+                    &queue,
+                    file,
+                    sampling,
+                    config.mipmaps.then_some(&mipmap_generator),
+                )
+            })
+            .chain(video.iter().map(|video| video.channel(&device, sampling)))
+            .chain(
+                config
+                    .blue_noise
+                    .then(|| blue_noise_channel(&device, &queue)),
+            )
+            .collect();
+        let background = background_file
+            .as_ref()
+            .map(|file| Background::new(&device, &queue, file));
+        let compositor = Compositor::new(&device);
+        // at the size the shader's passes render at, like their targets
+        let feedback = config.feedback.as_ref().map(|_| {
+            let PhysicalSize { width, height } = scaled_size(size, config.scale);
+            Feedback::new(&device, &sampler, width, height)
+        });
+        // alpha only decides coverage of more than one sample
+        if config.alpha_to_coverage && config.msaa == 1 {
+            return Err(StateError::Config(
+                "--alpha-to-coverage needs --msaa 4 to have samples to cover".to_owned(),
+            ));
+        }
+        let depth = config.depth.then(|| {
+            let PhysicalSize { width, height } = scaled_size(size, config.scale);
+            depth_view(&device, width, height, config.msaa)
+        });
+        let reference = reference_file
+            .as_ref()
+            .map(|file| Reference::new(&device, &queue, file, surface_config.format));
+
+        // Make geometry buffers
+        let quad = Mesh::quad(&device, !config.no_indices);
+        let shader_quad = match &config.mesh {
+            Some(mesh) => Mesh::load_obj(&device, Path::new(mesh), !config.no_indices)
+                .map_err(StateError::Mesh)?,
+            None => Mesh::scaled_quad(&device, !config.no_indices, config.quad_scale),
+        };
+
+        let overlay = Overlay::new(
+            &device,
+            surface_config.format,
+            size.width,
+            size.height,
+            scale_factor,
+            // hidden until toggled on
+            0,
+        );
+
+        // a bluish colour as default
+        let background_colour = config.clear_color.map_or(
+            Color {
+                r: 0.1,
+                g: 0.2,
+                b: 0.3,
+                a: 1.0,
+            },
+            |ClearColor { r, g, b }| Color { r, g, b, a: 1.0 },
+        );
+        let mut state = Self {
+            output,
+            device,
+            queue,
+            size,
+            scale_factor,
+            surface_config,
+            surface_formats,
+            // filled in by loading the shader
+            passes: Vec::new(),
+            uniforms_bind_group_layout,
+            // bound by loading the shader
+            uniforms_bind_group: None,
+            tile_bind_group_layout,
+            host_uniforms,
+            sampler,
+            channels,
+            sampling,
+            texture_watcher: TextureWatcher::new(&config.texture),
+            video,
+            audio_file,
+            background,
+            compositor,
+            feedback,
+            depth,
+            reference,
+            mipmap_generator,
+            overlay,
+            quad,
+            shader_quad,
+            quad_scale: config.quad_scale,
+            render_scale: config.scale,
+            adaptive_scale: config.adaptive_scale.map(|fps| {
+                AdaptiveScale::new(
+                    Duration::from_secs_f64(1.0 / fps),
+                    config.min_scale,
+                    config.scale,
+                )
+            }),
+            background_colour,
+            start_time,
+            time_base,
+            time_scale: config.time_scale,
+            paused_at: None,
+            time_override: None,
+            dropped_files: Vec::new(),
+            includes: Arc::default(),
+            frame: 0,
+            last_frame: None,
+            last_elapsed: config.start_time.unwrap_or_default(),
+            // set by loading the shader
+            just_reloaded: false,
+            frames_since_reload: 0,
+            samples: 0,
+            dirty: false,
+            time,
+            mouse,
+            last_mouse_activity: Instant::now(),
+            last_click: None,
+            button_presses: [None; 3],
+            last_cursor: None,
+            orbit_camera,
+            camera,
+            frame_times,
+            tiles,
+            lod,
+            custom,
+            presets,
+            selected_param: 0,
+            adapter: adapter_uniform,
+            rng,
+            display,
+            window: window_uniform,
+            resolution,
+            date,
+            keyboard,
+            audio,
+            storage,
+            compute,
+            osc,
+            midi,
+            script,
+            pacer: config.pace.map(FramePacer::new),
+            tempo: Tempo::new(config.bpm, config.beat_offset),
+            raw,
+            capture_metadata,
+            shader_hash: String::new(),
+            gif: config
+                .gif
+                .as_deref()
+                .zip(config.duration)
+                .map(|(path, duration)| GifRecorder::new(path, duration, config.fps)),
+            pick_pixel: false,
+            screenshot: None,
+            can_copy,
+            screenshot_failed: false,
+            gif_failed: false,
+            icon: config.live_icon.then(IconPreview::default),
+            fps_title: config.show_fps.then(|| FpsTitle::new(&config.title)),
+            finished: false,
+            config,
+        };
+        // LOAD SHADER AND COLLECT BIND GROUPS AND SHADERS INTO PIPELINES
+        state.refresh_shader()?;
+        Ok(state)
+    }
+
+    // bind group layouts for the built-in uniforms
+    // first elem is `[[group(0)]]` etc
+    fn uniform_layouts(&self) -> [&BindGroupLayout; 2] {
+        [
+            &self.uniforms_bind_group_layout,
+            &self.tile_bind_group_layout,
+        ]
+    }
+
+    // where the rings in group 0 were last written, in binding order
+    fn dynamic_offsets(&self) -> Vec<DynamicOffset> {
+        self.config
+            .uniforms
+            .iter()
+            .filter_map(|name| match name {
+                UniformName::Time => Some(self.time.offset()),
+                UniformName::Mouse => Some(self.mouse.offset()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // bind groups matching uniform_layouts
+    // the tile's bind group changes between draws, so this has the first tile's
+    fn uniform_bind_groups(&self) -> [&BindGroup; 2] {
+        [
+            self.uniforms_bind_group
+                .as_ref()
+                .expect("Uniforms are bound when the shader loads"),
+            self.tiles[0].bind_group(),
+        ]
+    }
+
+    // group 0's buffers in binding order, in the order from --uniforms
+    // unlisted uniforms are still updated, but don't take up a binding
+    fn uniform_buffers(&self) -> Vec<BindingResource<'_>> {
+        self.config
+            .uniforms
+            .iter()
+            .map(|name| match name {
+                UniformName::Time => self.time.binding(),
+                UniformName::Mouse => self.mouse.binding(),
+                UniformName::Camera => self.camera.buffer().as_entire_binding(),
+                UniformName::FrameTimes => self.frame_times.buffer().as_entire_binding(),
+                UniformName::Lod => self.lod.buffer().as_entire_binding(),
+                UniformName::Custom => self.custom.buffer().as_entire_binding(),
+                UniformName::Adapter => self.adapter.buffer().as_entire_binding(),
+                UniformName::Rng => self.rng.buffer().as_entire_binding(),
+                UniformName::Display => self.display.buffer().as_entire_binding(),
+                UniformName::Window => self.window.buffer().as_entire_binding(),
+                UniformName::Resolution => self.resolution.buffer().as_entire_binding(),
+                UniformName::Date => self.date.buffer().as_entire_binding(),
+                UniformName::Keyboard => self.keyboard.buffer().as_entire_binding(),
+                UniformName::Audio => self.audio.buffer().as_entire_binding(),
+            })
+            .collect()
+    }
+
+    // grow group 0's buffers to at least the size the shader's passes declare them at, as
+    // binding a smaller one fails, then bind them again, as growing replaces the buffers
+    // run on every load, since an edit can make a struct bigger
+    fn bind_uniforms(&mut self) {
+        let declared_sizes = declared_uniform_sizes(&self.config);
+        for (&name, binding) in self.config.uniforms.iter().zip(0..) {
+            let min_size = declared_sizes.get(&(0, binding)).copied().unwrap_or(0);
+            let device = &self.device;
+            match name {
+                UniformName::Time => self.time.grow(device, min_size),
+                UniformName::Mouse => self.mouse.grow(device, min_size),
+                UniformName::Camera => self.camera.grow(device, min_size),
+                UniformName::FrameTimes => self.frame_times.grow(device, min_size),
+                UniformName::Lod => self.lod.grow(device, min_size),
+                UniformName::Custom => {
+                    if min_size > self.custom.size() {
+                        self.custom = self.custom.uniform().clone().make_buffer(device, min_size);
+                    }
+                }
+                UniformName::Adapter => self.adapter.grow(device, min_size),
+                UniformName::Rng => self.rng.grow(device, min_size),
+                UniformName::Display => self.display.grow(device, min_size),
+                UniformName::Window => self.window.grow(device, min_size),
+                UniformName::Resolution => self.resolution.grow(device, min_size),
+                UniformName::Date => self.date.grow(device, min_size),
+                UniformName::Keyboard => self.keyboard.grow(device, min_size),
+                UniformName::Audio => self.audio.grow(device, min_size),
+            }
+        }
+        let bind_group = uniforms_bind_group(
+            &self.device,
+            "Uniforms Bind Group",
+            &self.uniforms_bind_group_layout,
+            self.uniform_buffers(),
+            self.storage
+                .as_ref()
+                .map(|buffer| (STORAGE_BINDING, buffer)),
+        );
+        self.uniforms_bind_group = Some(bind_group);
+        let compute_bind_group = match (&self.compute, &self.storage) {
+            (Some(compute), Some(storage)) => {
+                Some(compute.build_bind_group(&self.device, self.uniform_buffers(), storage))
+            }
+            _ => None,
+        };
+        if let (Some(compute), Some(bind_group)) = (&mut self.compute, compute_bind_group) {
+            compute.set_bind_group(bind_group);
+        }
+    }
+
+    // the part of the window the shader is drawn in, as x, y, width and height
+    // the whole window, unless --aspect shrinks it to fit and centres it
+    fn letterbox(&self) -> (u32, u32, u32, u32) {
+        let PhysicalSize { width, height } = self.size;
+        let aspect = match self.config.aspect {
+            Some(aspect) => aspect,
+            None => return (0, 0, width, height),
+        };
+        // u64 so the cross multiplication can't overflow
+        let (aspect_width, aspect_height) = (aspect.width as u64, aspect.height as u64);
+        if width as u64 * aspect_height > height as u64 * aspect_width {
+            // window is too wide, so pillarbox
+            let fitted = (height as u64 * aspect_width / aspect_height).max(1) as u32;
+            ((width - fitted) / 2, 0, fitted, height)
+        } else {
+            // window is too tall, so letterbox
+            let fitted = (width as u64 * aspect_height / aspect_width).max(1) as u32;
+            (0, (height - fitted) / 2, width, fitted)
+        }
+    }
+
+    // where the shader's allowed to draw, as x, y, width and height
+    // the letterbox, cut down to the --scissor rectangle if there is one
+    fn scissor_rect(&self) -> (u32, u32, u32, u32) {
+        let (x, y, width, height) = self.letterbox();
+        let scissor = match self.config.scissor {
+            Some(scissor) => scissor,
+            None => return (x, y, width, height),
+        };
+        // clamped to the letterbox, which is inside the window
+        let left = scissor.x.clamp(x, x + width);
+        let top = scissor.y.clamp(y, y + height);
+        let right = scissor
+            .x
+            .saturating_add(scissor.width)
+            .clamp(left, x + width);
+        let bottom = scissor
+            .y
+            .saturating_add(scissor.height)
+            .clamp(top, y + height);
+        (left, top, right - left, bottom - top)
+    }
+
+    // rebuild the passes from the shader on disk, keeping the old ones if it has a mistake
+    // wgpu reports validation errors to a handler that panics by default, so they're caught
+    // in an error scope instead, and the new passes only replace the old after it's empty
+    fn refresh_shader(&mut self) -> Result<(), String> {
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let passes = new_passes(
+            &self.device,
+            &self.surface_config,
+            self.render_size(),
+            &self.uniform_layouts(),
+            &self.host_uniforms,
+            PassImages {
+                sampler: &self.sampler,
+                channels: &self.channels,
+                background: self.background.as_ref(),
+                compositor: &self.compositor,
+                feedback: self.feedback.as_ref(),
+            },
+            &self.config,
+        );
+        let compute_pipeline = match (&self.compute, &self.config.compute) {
+            (Some(compute), Some(path)) => {
+                Some(compute.build_pipeline(&self.device, &self.config, path))
+            }
+            _ => None,
+        };
+        let validation = pollster::block_on(self.device.pop_error_scope());
+        let passes = passes?;
+        let compute_pipeline = compute_pipeline.transpose()?;
+        if let Some(error) = validation {
+            return Err(error.to_string());
+        }
+        self.passes = passes;
+        if let (Some(compute), Some(pipeline)) = (&mut self.compute, compute_pipeline) {
+            compute.set_pipeline(pipeline);
+        }
+        self.list_includes();
+        self.bind_uniforms();
+        self.just_reloaded = true;
+        self.frames_since_reload = 0;
+        self.dirty = true;
+        if !self.capture_metadata.is_empty() {
+            self.shader_hash = shader_hash(&self.config);
+        }
+        Ok(())
+    }
+
+    // note down what the shader includes, after it's loaded
+    fn list_includes(&mut self) {
+        let mut includes: Vec<String> = self
+            .passes
+            .iter()
+            .flat_map(|pass| &pass.includes)
+            .chain(self.compute.iter().flat_map(Compute::includes))
+            .map(|path| path.display().to_string())
+            .collect();
+        // layers and passes often share a library
+        includes.sort();
+        includes.dedup();
+        *self.includes.lock().expect("The watcher never panics") = includes;
+    }
+
+    // every file the shader includes, kept up to date as it reloads, for --watch
+    pub(super) fn includes(&self) -> Arc<Mutex<Vec<String>>> {
+        Arc::clone(&self.includes)
+    }
+
+    // reload the shader from disk, as after an edit
+    // mouse and camera state carry over, and time does too if asked
+    pub(super) fn reload(&mut self) {
+        // reading, parsing and building pipelines all happen here, so this is the whole wait
+        let reload_start = Instant::now();
+        if let Err(error) = self.refresh_shader() {
+            log::error!(
+                "Failed reloading shader, keeping the last one that worked: {}",
+                error
+            );
+            return;
+        }
+        log::info!(
+            "Reloaded shader in {:.1}ms",
+            reload_start.elapsed().as_secs_f64() * 1000.0
+        );
+        // the script may have been edited too
+        if let Some(path) = &self.config.script {
+            self.script = Script::load(path, self.config.custom_uniforms.clone())
+                .map_err(|error| log::error!("Failed reloading script {}: {}", path, error))
+                .ok();
+        }
+        if self.config.reset_time_on_reload {
+            self.reset_time();
+        }
+    }
+
+    // switch to the first shader file dropped on the window, keeping the current one if it
+    // doesn't load
+    fn load_dropped_file(&mut self) {
+        let mut dropped = mem::take(&mut self.dropped_files).into_iter();
+        let path = match dropped.next() {
+            Some(path) => path,
+            None => return,
+        };
+        for ignored in dropped {
+            log::warn!(
+                "Ignoring dropped file {}, as only the first of several is loaded",
+                ignored.display()
+            );
+        }
+        // the dropped file takes the place of whatever the shader came from
+        let previous_path = mem::replace(&mut self.config.path, path.display().to_string());
+        let previous_example = self.config.example.take();
+        let previous_manifest = self.config.manifest.take();
+        if let Err(error) = self.refresh_shader() {
+            log::error!(
+                "Failed loading dropped shader {}, keeping the last one that worked: {}",
+                path.display(),
+                error
+            );
+            self.config.path = previous_path;
+            self.config.example = previous_example;
+            self.config.manifest = previous_manifest;
+            return;
+        }
+        log::info!("Loaded dropped shader {}", path.display());
+        // it's the one to run next time there's no --path, as if it had been given one
+        remember_shader(&self.config.path);
+        if self.config.reset_time_on_reload {
+            self.reset_time();
+        }
+    }
+
+    // hold shader time at this long from the clock's start, for every frame from the next
+    // update on, so renders come out the same however long they take
+    pub(super) fn set_time(&mut self, time: Duration) {
+        self.time_override = Some(time);
+        self.dirty = true;
+    }
+
+    // start the animation over from --start-time, with the frame count, random sequence and
+    // storage buffer all back where they began
+    pub(super) fn reset_time(&mut self) {
+        self.start_time = Instant::now();
+        self.time_base = self.config.start_time.unwrap_or_default();
+        // stay paused, at the start
+        if self.paused_at.is_some() {
+            self.paused_at = Some(Instant::now());
+        }
+        self.last_elapsed = self.config.start_time.unwrap_or_default();
+        self.frame = 0;
+        // replay the same random sequence
+        *self.rng.uniform_mut() = RngUniform::new(self.config.seed);
+        // and start the simulation over
+        if let (Some(storage), Some(size)) = (&self.storage, storage_size(&self.config)) {
+            let zeroes = vec![0; size as usize];
+            self.queue.write_buffer(storage, 0, &zeroes);
+        }
+    }
+
+    pub(super) fn resize(&mut self, new_size: PhysicalSize<u32>) {
         if new_size.width > 0 && new_size.height > 0 {
+            let new_size = texture_size(new_size, self.device.limits().max_texture_dimension_2d);
             self.size = new_size;
             self.surface_config.width = new_size.width;
             self.surface_config.height = new_size.height;
-            self.surface.configure(&self.device, &self.surface_config);
+            self.output.configure(&self.device, &self.surface_config);
+            self.resize_render();
+            self.orbit_camera.resize(new_size.width, new_size.height);
+            self.overlay.resize(
+                &self.device,
+                &self.queue,
+                new_size.width,
+                new_size.height,
+                self.scale_factor,
+            );
+        }
+    }
+
+    // the shader's passes and the uniforms describing them, after the size they render at changes
+    fn resize_render(&mut self) {
+        self.resize_passes();
+        let PhysicalSize { width, height } = self.render_size();
+        if self.config.mipmaps {
+            self.lod
+                .uniform_mut()
+                .update_levels(mip_level_count(width, height));
+            self.queue.write_buffer(
+                self.lod.buffer(),
+                0,
+                bytemuck::cast_slice(&[*self.lod.uniform()]),
+            );
+        }
+        self.resolution
+            .uniform_mut()
+            .update_size(width, height, self.render_scale);
+        self.queue.write_buffer(
+            self.resolution.buffer(),
+            0,
+            bytemuck::cast_slice(&[*self.resolution.uniform()]),
+        );
+    }
+
+    // the size the shader's own passes render at, the window's cut down by --scale
+    fn render_size(&self) -> PhysicalSize<u32> {
+        scaled_size(self.size, self.render_scale)
+    }
+
+    pub(super) fn rescale(&mut self, scale_factor: f64, new_size: PhysicalSize<u32>) {
+        self.scale_factor = scale_factor;
+        self.resize(new_size);
+    }
+
+    // the window has a new position, and may be on a monitor with a different refresh rate
+    pub(super) fn moved(&mut self, window: &Window) {
+        self.display
+            .uniform_mut()
+            .update_refresh_rate(refresh_rate(window).unwrap_or(FALLBACK_REFRESH_RATE));
+        self.queue.write_buffer(
+            self.display.buffer(),
+            0,
+            bytemuck::cast_slice(&[*self.display.uniform()]),
+        );
+        let (position, desktop_size) = window_placement(window);
+        self.window.uniform_mut().update(position, desktop_size);
+        self.queue.write_buffer(
+            self.window.buffer(),
+            0,
+            bytemuck::cast_slice(&[*self.window.uniform()]),
+        );
+    }
+
+    pub(super) fn toggle_overlay(&mut self) {
+        self.overlay.toggle(&self.queue, OverlayLayer::Grid);
+    }
+
+    pub(super) fn toggle_ndc_overlay(&mut self) {
+        self.overlay.toggle(&self.queue, OverlayLayer::Ndc);
+    }
+
+    // print the colour under the cursor once the next frame is drawn
+    // returns whether the cursor's in the window to pick from
+    pub(super) fn pick_pixel(&mut self) -> bool {
+        if self.last_cursor.is_none() {
+            return false;
+        }
+        self.pick_pixel = self.enable_copy();
+        true
+    }
+
+    // save the next frame, as it appears in the window
+    pub(super) fn screenshot(&mut self) {
+        if self.enable_copy() {
+            self.screenshot = Some(screenshot_path(&self.config));
+        }
+    }
+
+    // let frames be copied out of the surface from now on, for screenshots and picking
+    // returns whether they can be
+    fn enable_copy(&mut self) -> bool {
+        if self.surface_config.usage.contains(TextureUsages::COPY_SRC) {
+            return true;
+        }
+        if !self.can_copy {
+            log::warn!(
+                "This backend can't copy frames out of the window to save or pick from, \
+                try another --backend"
+            );
+            return false;
+        }
+        self.surface_config.usage |= TextureUsages::COPY_SRC;
+        self.output.configure(&self.device, &self.surface_config);
+        true
+    }
+
+    // a tap on the beat, returning the tempo it sets if it isn't the first
+    pub(super) fn tap_tempo(&mut self) -> Option<f64> {
+        self.tempo.tap()
+    }
+
+    // set the custom uniforms to the preset in a number key's slot, from 0
+    // returns its name, or None for an empty slot
+    pub(super) fn recall_preset(&mut self, slot: usize) -> Option<String> {
+        let preset = self.presets.slot(slot)?;
+        for (index, &value) in preset.values().iter().enumerate() {
+            self.custom.uniform_mut().set(index, value);
+        }
+        self.queue
+            .write_buffer(self.custom.buffer(), 0, self.custom.uniform().as_bytes());
+        self.dirty = true;
+        Some(preset.name().to_owned())
+    }
+
+    // move the slot [ and ] change to the next custom uniform, or the previous one, wrapping
+    // around, and log what's in it
+    pub(super) fn select_param(&mut self, forward: bool) {
+        let count = self.custom.uniform().len();
+        if count == 0 {
+            log::warn!("There are no custom uniform slots to select, see --params");
+            return;
+        }
+        self.selected_param = if forward {
+            (self.selected_param + 1) % count
+        } else {
+            (self.selected_param + count - 1) % count
+        };
+        self.log_param(self.selected_param);
+    }
+
+    pub(super) fn selected_param(&self) -> usize {
+        self.selected_param
+    }
+
+    // add to a custom uniform slot's value, and log the new one
+    // it's uploaded with the other custom uniforms in the next update
+    pub(super) fn adjust_param(&mut self, index: usize, delta: f32) {
+        if index >= self.custom.uniform().len() {
+            log::warn!("There's no custom uniform slot {} to change", index);
+            return;
+        }
+        let value = self.custom.uniform().get(index) + delta;
+        self.custom.uniform_mut().set(index, value);
+        self.dirty = true;
+        self.log_param(index);
+    }
+
+    fn log_param(&self, index: usize) {
+        let value = self.custom.uniform().get(index);
+        match self.config.custom_uniforms.get(index) {
+            Some(name) => log::info!("Custom uniform {} `{}` is {:.3}", index, name, value),
+            None => log::info!("Custom uniform {} is {:.3}", index, value),
+        }
+    }
+
+    // keep the custom uniforms' values in a number key's slot, from 0, and in the presets file
+    pub(super) fn save_preset(&mut self, slot: usize) {
+        let values = self.custom.uniform().values();
+        match self.presets.save(slot, values) {
+            Ok(name) => log::info!("Saved preset `{}`", name),
+            Err(error) => log::warn!(
+                "Failed saving preset to {}: {}",
+                self.presets.path().display(),
+                error
+            ),
         }
     }
 
+    // print every pass's bind groups, to diff against the shader's declarations
+    pub(super) fn print_layout(&self) {
+        for pass in &self.passes {
+            println!("{}", pass.layout);
+        }
+    }
+
+    // show or hide the --reference image, returning whether there is one
+    pub(super) fn toggle_reference(&mut self) -> bool {
+        match &mut self.reference {
+            Some(reference) => {
+                reference.toggle();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub(super) fn grow_quad(&mut self) {
+        self.scale_quad(QUAD_SCALE_STEP);
+    }
+
+    pub(super) fn shrink_quad(&mut self) {
+        self.scale_quad(1.0 / QUAD_SCALE_STEP);
+    }
+
+    // grow or shrink the shader's quad by a factor, up to filling the window
+    fn scale_quad(&mut self, factor: f32) {
+        if self.config.mesh.is_some() {
+            log::info!("The --mesh can't be scaled");
+            return;
+        }
+        self.quad_scale = (self.quad_scale * factor).clamp(MIN_QUAD_SCALE, 1.0);
+        log::info!("Quad scale is now {:.3}", self.quad_scale);
+        self.shader_quad =
+            Mesh::scaled_quad(&self.device, !self.config.no_indices, self.quad_scale);
+        self.dirty = true;
+    }
+
+    // stop or restart time, returning whether it's now paused
+    pub(super) fn toggle_pause(&mut self) -> bool {
+        match self.paused_at.take() {
+            Some(paused_at) => self.start_time += paused_at.elapsed(),
+            None => self.paused_at = Some(Instant::now()),
+        }
+        self.paused_at.is_some()
+    }
+
+    // shader time by the clock, from where time_base left off, at the time scale
+    fn clock_time(&self) -> Duration {
+        let counted = self.paused_at.unwrap_or_else(Instant::now) - self.start_time;
+        self.time_base + counted.mul_f64(f64::from(self.time_scale))
+    }
+
+    // play time at a different speed from now on, carrying on from the current time
+    pub(super) fn set_time_scale(&mut self, scale: f32) {
+        self.time_base = self.clock_time();
+        self.start_time = self.paused_at.unwrap_or_else(Instant::now);
+        self.time_scale = scale.clamp(MIN_TIME_SCALE, MAX_TIME_SCALE);
+        log::info!("Time scale is now {}", self.time_scale);
+    }
+
+    pub(super) fn time_scale(&self) -> f32 {
+        self.time_scale
+    }
+
+    // move paused time on by one TIME_STEP, returning false if time isn't paused
+    // it's added to time_base rather than taken off start_time, which can't go back further
+    // than the platform's clock does
+    pub(super) fn step_time(&mut self) -> bool {
+        if self.paused_at.is_none() {
+            return false;
+        }
+        self.time_base = self.time_base.saturating_add(TIME_STEP);
+        self.dirty = true;
+        true
+    }
+
+    pub(super) fn reset_zoom(&mut self) {
+        self.mouse.uniform_mut().reset_zoom();
+        self.dirty = true;
+    }
+
+    pub(super) fn reset_path_length(&mut self) {
+        self.mouse.uniform_mut().reset_path_length();
+        self.dirty = true;
+    }
+
+    // switch the surface to its next format, rebuilding everything that draws to it
+    pub(super) fn cycle_surface_format(&mut self) {
+        let current = self
+            .surface_formats
+            .iter()
+            .position(|&format| format == self.surface_config.format)
+            .unwrap_or(0);
+        let format = self.surface_formats[(current + 1) % self.surface_formats.len()];
+        let previous = self.surface_config.format;
+        self.surface_config.format = format;
+        self.output.configure(&self.device, &self.surface_config);
+        // pipelines are built for one target format
+        if let Err(error) = self.refresh_shader() {
+            log::error!("Failed rebuilding shader for {:?}: {}", format, error);
+            self.surface_config.format = previous;
+            self.output.configure(&self.device, &self.surface_config);
+            return;
+        }
+        log::info!("Surface format is now {:?}", format);
+        self.overlay = Overlay::new(
+            &self.device,
+            format,
+            self.size.width,
+            self.size.height,
+            self.scale_factor,
+            self.overlay.layers(),
+        );
+        if let Some(reference) = &mut self.reference {
+            reference.set_format(&self.device, format);
+        }
+    }
+
+    // offscreen pass targets follow the surface size, so rebuild them and their bind groups
+    fn resize_passes(&mut self) {
+        self.dirty = true;
+        let render_size = self.render_size();
+        if let Some(feedback) = &mut self.feedback {
+            feedback.resize(
+                &self.device,
+                &self.sampler,
+                render_size.width,
+                render_size.height,
+            );
+        }
+        if let Some(depth) = &mut self.depth {
+            *depth = depth_view(
+                &self.device,
+                render_size.width,
+                render_size.height,
+                self.config.msaa,
+            );
+        }
+        for pass in &mut self.passes {
+            let PhysicalSize { width, height } = if pass.uniforms {
+                render_size
+            } else {
+                self.size
+            };
+            let new_target = || {
+                PassTarget::new(
+                    &self.device,
+                    "Pass Target",
+                    width,
+                    height,
+                    self.config.mipmaps && pass.uniforms,
+                )
+            };
+            if pass.target.is_some() {
+                pass.target = Some(new_target());
+            }
+            for target in &mut pass.extra_targets {
+                *target = new_target();
+            }
+            if let Some(multisampled) = &mut pass.multisampled {
+                multisampled.resize(&self.device, width, height);
+            }
+        }
+        self.rebind_inputs();
+    }
+
+    // remake the passes' input bind groups, after the textures in them have been replaced
+    fn rebind_inputs(&mut self) {
+        for index in 0..self.passes.len() {
+            let pass = &self.passes[index];
+            if pass.input_bind_group.is_some() {
+                let inputs = Pass::input_views(&self.passes, &pass.inputs);
+                let bind_group = input_bind_group(
+                    &self.device,
+                    &pass.input_layout,
+                    &self.sampler,
+                    &inputs,
+                    pass_channels(pass.uniforms, &self.channels),
+                );
+                self.passes[index].input_bind_group = Some(bind_group);
+            }
+            let (earlier, rest) = self.passes.split_at_mut(index);
+            for layer in &mut rest[0].layers {
+                let view = earlier[layer.source()]
+                    .target
+                    .as_ref()
+                    .expect("Layers render to offscreen targets")
+                    .view();
+                self.compositor
+                    .rebind(&self.device, layer, view, &self.sampler);
+            }
+        }
+    }
+
+    // upload any --texture files edited since the last check
+    fn reload_textures(&mut self) {
+        let mut replaced = false;
+        for index in self.texture_watcher.changed(&self.config.texture) {
+            let file = match TextureFile::try_read(&self.config.texture[index]) {
+                Ok(file) => file,
+                Err(error) => {
+                    log::error!("{}", error);
+                    continue;
+                }
+            };
+            // the device was only asked for the features the first files needed
+            let missing = file.required_features() - self.device.features();
+            if !missing.is_empty() {
+                log::error!(
+                    "Texture {} changed to {:?}, which needs {:?} that the device wasn't made with",
+                    file.path(),
+                    file.format(),
+                    missing
+                );
+                continue;
+            }
+            self.dirty = true;
+            // --texture files are the first channels, in order
+            replaced |= self.channels[index].reload(
+                &self.device,
+                &self.queue,
+                &file,
+                self.sampling,
+                self.config.mipmaps.then_some(&self.mipmap_generator),
+            );
+        }
+        if replaced {
+            self.rebind_inputs();
+        }
+    }
+
+    // a new --live-icon preview for the window, if one's been made since last time
+    pub(super) fn take_icon(&mut self) -> Option<winit::window::Icon> {
+        self.icon.as_mut().and_then(IconPreview::take)
+    }
+
+    // a new window title with the --show-fps readout, if it's due for one
+    pub(super) fn take_title(&mut self) -> Option<String> {
+        self.fps_title.as_mut().and_then(FpsTitle::take)
+    }
+
+    // whether there's nothing left to do, as after recording a --gif
+    pub(super) fn finished(&self) -> bool {
+        self.finished
+    }
+
+    // since the mouse last moved, clicked or scrolled
+    pub(super) fn mouse_idle_time(&self) -> Duration {
+        self.last_mouse_activity.elapsed()
+    }
+
     pub(super) fn current_size(&self) -> PhysicalSize<u32> {
         self.size
     }
 
     pub(super) fn input(&mut self, event: &WindowEvent) -> bool {
+        if let WindowEvent::CursorMoved { .. }
+        | WindowEvent::MouseInput { .. }
+        | WindowEvent::MouseWheel { .. } = event
+        {
+            self.last_mouse_activity = Instant::now();
+            // the mouse and camera uniforms change, so the average would mix old and new
+            self.dirty = true;
+        }
         // bool represents whether the event has been fully processed
         match *event {
             WindowEvent::CursorMoved { position, .. } => {
                 self.mouse.uniform_mut().update_position(
-                    (position.x / self.size.width as f64) as f32,
-                    (position.y / self.size.height as f64) as f32,
+                    position.x,
+                    position.y,
+                    [self.size.width as f64, self.size.height as f64],
+                    self.scale_factor,
                 );
-                self.background_colour.r = position.x / self.size.width as f64;
-                self.background_colour.g = position.y / self.size.height as f64;
+                self.orbit_camera.cursor_moved(position.x, position.y);
+                // drag the --reference split with the right mouse button
+                match &mut self.reference {
+                    Some(reference)
+                        if reference.comparing() && self.button_presses[1].is_some() =>
+                    {
+                        reference.set_split((position.x / self.size.width as f64) as f32);
+                    }
+                    _ => {}
+                }
+                if let Some(last) = self.last_cursor {
+                    let distance = (position.x - last.x).hypot(position.y - last.y);
+                    self.mouse.uniform_mut().add_path_length(distance as f32);
+                }
+                self.last_cursor = Some(position);
+                // moving over the window counts, in case it started under the cursor without
+                // an event to say it entered
+                self.mouse.uniform_mut().update_hovering(true);
+                true
+            }
+            WindowEvent::CursorEntered { .. } => {
+                self.mouse.uniform_mut().update_hovering(true);
+                true
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.last_cursor = None;
+                self.mouse.uniform_mut().update_hovering(false);
+                false
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                let index = match button {
+                    MouseButton::Left => 0,
+                    MouseButton::Right => 1,
+                    MouseButton::Middle => 2,
+                    MouseButton::Other(_) => return false,
+                };
+                let press = &mut self.button_presses[index];
+                match state {
+                    // a second press without a release keeps the hold going from the first
+                    ElementState::Pressed => *press = press.or_else(|| Some(Instant::now())),
+                    ElementState::Released => *press = None,
+                }
+                // a right click moves the --reference split straight to the cursor
+                match &mut self.reference {
+                    Some(reference) if button == MouseButton::Right => {
+                        reference.set_split(self.mouse.uniform().position()[0]);
+                    }
+                    _ => {}
+                }
+                if button != MouseButton::Left {
+                    return true;
+                }
+                // drag with the left mouse button to orbit the camera
+                self.orbit_camera
+                    .set_dragging(state == ElementState::Pressed);
+                if state == ElementState::Pressed {
+                    let follows_last = self
+                        .last_click
+                        .is_some_and(|last| last.elapsed() < CLICK_INTERVAL);
+                    self.mouse.uniform_mut().click(follows_last);
+                    self.last_click = Some(Instant::now());
+                }
+                true
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let lines = scroll_lines(delta);
+                self.orbit_camera.scroll(lines);
+                self.mouse.uniform_mut().scroll(lines);
+                true
+            }
+            // the keys are left for main to handle too, as shortcuts
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                ..
+            } => {
+                self.keyboard
+                    .uniform_mut()
+                    .set(key as u32, state == ElementState::Pressed);
+                false
+            }
+            WindowEvent::Focused(false) => {
+                self.keyboard.uniform_mut().clear();
+                false
+            }
+            // each file dropped at once comes in its own event, so they're gathered up until the
+            // next update
+            WindowEvent::DroppedFile(ref path) => {
+                self.dropped_files.push(path.clone());
                 true
             }
-            // WindowEvent::CursorEntered { .. } => {
-            //    self.mouse_uniform.update_hovering(true);
-            //    true
-            //}
-            // WindowEvent::CursorLeft { .. } => {
-            //    self.mouse_uniform.update_hovering(false);
-            //    true
-            //}
-            // WindowEvent::MouseInput {
-            //    state: (),
-            //    button: (),
-            //    ..
-            //} => {
-            //    todo!()
-            //}
             _ => false,
         }
     }
 
     pub(super) fn update(&mut self) {
-        self.time.uniform_mut().update_time(self.start_time);
+        self.load_dropped_file();
+        // scaled by --time-scale like the clock, so a slowed recording plays back slowed
+        let recording = self.gif.as_ref().map(|gif| {
+            self.config.start_time.unwrap_or_default()
+                + gif.next_frame_time().mul_f64(f64::from(self.time_scale))
+        });
+        let elapsed = shader_time(self.time_override, recording, self.clock_time());
+        // shader time rather than the wall clock's, so it's 0 while paused and exact for --gif
+        // it's 0 when time starts over too, instead of going backwards
+        let delta = elapsed.saturating_sub(mem::replace(&mut self.last_elapsed, elapsed));
+        let elapsed = wrap_time(elapsed, self.config.time_max);
+        self.time.uniform_mut().update_time(elapsed);
+        let (beat, beat_phase) = self.tempo.beat(elapsed);
+        self.time.uniform_mut().update_beat(beat, beat_phase);
+        if let Some(video) = &mut self.video {
+            video.update(&self.queue, elapsed);
+        }
+        if let Some(audio_file) = &mut self.audio_file {
+            // the audio plays at its own speed, so it's silent while time is scaled
+            let paused = self.paused_at.is_some() || self.time_scale != 1.0;
+            audio_file.update(self.audio.uniform_mut(), elapsed, paused);
+        }
+        self.reload_textures();
+        self.time
+            .uniform_mut()
+            .update_strobe(self.frame, self.config.strobe_period);
+        self.time
+            .uniform_mut()
+            .set_just_reloaded(mem::take(&mut self.just_reloaded));
+        self.time
+            .uniform_mut()
+            .set_frames_since_reload(self.frames_since_reload);
+        self.frames_since_reload = self.frames_since_reload.wrapping_add(1);
+        self.time.uniform_mut().update_frame(self.frame, delta);
+        self.frame += 1;
+        let now = Instant::now();
+        // the first frame has nothing before it to time it from
+        let frame_time = self
+            .last_frame
+            .replace(now)
+            .map(|last_frame| now - last_frame);
+        if let Some(frame_time) = frame_time {
+            self.frame_times
+                .uniform_mut()
+                .push(frame_time.as_secs_f32());
+            if let Some(fps_title) = &mut self.fps_title {
+                fps_title.push(frame_time);
+            }
+        }
+        let new_scale = frame_time
+            .zip(self.adaptive_scale.as_mut())
+            .and_then(|(frame_time, adaptive)| adaptive.push(frame_time.as_secs_f64()));
+        if let Some(scale) = new_scale {
+            log::debug!("Render scale is now {:.2}", scale);
+            self.render_scale = scale;
+            self.resize_render();
+        }
+        self.mouse
+            .uniform_mut()
+            .update_idle_time(self.last_mouse_activity.elapsed().as_secs_f32());
+        self.mouse.uniform_mut().update_buttons(self.button_presses);
+        // too long since the last click for another one to add to the count
+        if let Some(last_click) = self.last_click {
+            if last_click.elapsed() >= CLICK_INTERVAL {
+                self.mouse.uniform_mut().reset_clicks();
+                self.last_click = None;
+            }
+        }
+        self.mouse.write(&self.queue);
+        self.camera
+            .uniform_mut()
+            .update_view_proj(self.orbit_camera.view_projection());
+        self.queue.write_buffer(
+            self.camera.buffer(),
+            0,
+            bytemuck::cast_slice(&[*self.camera.uniform()]),
+        );
+        self.queue.write_buffer(
+            self.frame_times.buffer(),
+            0,
+            bytemuck::cast_slice(&[*self.frame_times.uniform()]),
+        );
+        let custom_before = self.custom.uniform().as_bytes().to_vec();
+        if let Some(osc) = &self.osc {
+            osc.poll(self.custom.uniform_mut());
+        }
+        if let Some(midi) = &self.midi {
+            midi.poll(self.custom.uniform_mut());
+        }
+        // after the other inputs, so a script can override them
+        if let Some(script) = &mut self.script {
+            let result = script.run(
+                elapsed.as_secs_f64(),
+                self.frame,
+                self.mouse.uniform().position(),
+                self.custom.uniform_mut(),
+            );
+            // stop until the next reload, rather than logging the same error every frame
+            if let Err(error) = result {
+                log::error!("Script failed, stopping it until reload: {}", error);
+                self.script = None;
+            }
+        }
+        if self.custom.uniform().as_bytes() != custom_before {
+            self.dirty = true;
+        }
+        self.queue
+            .write_buffer(self.custom.buffer(), 0, self.custom.uniform().as_bytes());
+        // last, so anything that changed this frame starts the average over from this one
+        if mem::take(&mut self.dirty) {
+            self.samples = 0;
+        }
+        self.samples = self.samples.saturating_add(1);
+        self.time.uniform_mut().set_samples(self.samples);
+        self.time.write(&self.queue);
+        self.queue.write_buffer(
+            self.rng.buffer(),
+            0,
+            bytemuck::cast_slice(&[*self.rng.uniform()]),
+        );
+        self.rng.uniform_mut().step();
+        self.date.uniform_mut().update();
         self.queue.write_buffer(
-            self.time.buffer(),
+            self.date.buffer(),
             0,
-            bytemuck::cast_slice(&[*self.time.uniform()]),
+            bytemuck::cast_slice(&[*self.date.uniform()]),
         );
         self.queue.write_buffer(
-            self.mouse.buffer(),
+            self.keyboard.buffer(),
             0,
-            bytemuck::cast_slice(&[*self.mouse.uniform()]),
+            bytemuck::cast_slice(&[*self.keyboard.uniform()]),
         );
+        self.queue.write_buffer(
+            self.audio.buffer(),
+            0,
+            bytemuck::cast_slice(&[*self.audio.uniform()]),
+        );
+    }
+
+    // describe a captured frame beside each capture, for --capture-metadata
+    fn write_metadata(&mut self, frame: &Frame) {
+        let metadata = FrameMetadata {
+            shader: self.config.example.as_deref().unwrap_or(&self.config.path),
+            shader_hash: &self.shader_hash,
+            // already counted in update
+            frame: self.frame.saturating_sub(1),
+            time: self.time.uniform().seconds(),
+            mouse: self.mouse.uniform().position(),
+            resolution: [frame.width, frame.height],
+        };
+        self.capture_metadata
+            .retain_mut(|writer| match writer.write(&metadata) {
+                Ok(()) => true,
+                Err(e) => {
+                    log::error!("Could not write {}, stopping: {}", writer.path(), e);
+                    false
+                }
+            });
+    }
+
+    // a pass's shader, with its bind groups, over the part of the target it's drawn in
+    fn draw_pass<'pass>(
+        &'pass self,
+        render_pass: &mut RenderPass<'pass>,
+        pass: &'pass Pass,
+        uniform_bind_groups: [&'pass BindGroup; 2],
+        dynamic_offsets: &[DynamicOffset],
+    ) {
+        render_pass.set_pipeline(&pass.pipeline);
+        if pass.accumulate {
+            let weight = 1.0 / self.samples.max(1) as f64;
+            render_pass.set_blend_constant(Color {
+                r: weight,
+                g: weight,
+                b: weight,
+                a: weight,
+            });
+        }
+        if pass.uniforms {
+            for (group, bind_group) in (0..pass.groups).zip(uniform_bind_groups) {
+                let offsets = if group == 0 { dynamic_offsets } else { &[] };
+                render_pass.set_bind_group(group, bind_group, offsets);
+            }
+            // inputs come after the uniforms
+            let input_group = uniform_bind_groups.len() as u32;
+            match &pass.input_bind_group {
+                Some(input_bind_group) if input_group < pass.groups => {
+                    render_pass.set_bind_group(input_group, input_bind_group, &[]);
+                }
+                _ => {}
+            }
+            // the feedback pass reads what it drew last frame, and the rest what it drew this one
+            match &self.feedback {
+                Some(feedback) if FEEDBACK_GROUP < pass.groups => {
+                    let bind_group = if pass.feedback {
+                        feedback.previous()
+                    } else {
+                        feedback.latest()
+                    };
+                    render_pass.set_bind_group(FEEDBACK_GROUP, bind_group, &[]);
+                }
+                _ => {}
+            }
+            // keep out of the --aspect bars and outside --scissor, which stay the cleared
+            // background colour
+            // they're in window pixels, and the shader's passes render at --scale
+            let render_size = self.render_size();
+            let (x, y, width, height) = self.letterbox();
+            let (scissor_x, scissor_y, scissor_width, scissor_height) =
+                scaled_rect(self.scissor_rect(), self.render_scale, render_size);
+            render_pass.set_scissor_rect(scissor_x, scissor_y, scissor_width, scissor_height);
+            // draw the shader once in each cell of the grid
+            let Tile { rows, columns } = self.config.tile;
+            let cell_width = width as f32 * self.render_scale / columns as f32;
+            let cell_height = height as f32 * self.render_scale / rows as f32;
+            for (index, tile) in (0..).zip(&self.tiles) {
+                let (row, column) = (index / columns, index % columns);
+                render_pass.set_viewport(
+                    x as f32 * self.render_scale + column as f32 * cell_width,
+                    y as f32 * self.render_scale + row as f32 * cell_height,
+                    cell_width,
+                    cell_height,
+                    0.0,
+                    1.0,
+                );
+                if TILE_GROUP < pass.groups {
+                    render_pass.set_bind_group(TILE_GROUP, tile.bind_group(), &[]);
+                }
+                self.draw_screen(render_pass, &self.shader_quad);
+            }
+            // back to the whole target for anything drawn afterwards
+            render_pass.set_scissor_rect(0, 0, render_size.width, render_size.height);
+            render_pass.set_viewport(
+                0.0,
+                0.0,
+                render_size.width as f32,
+                render_size.height as f32,
+                0.0,
+                1.0,
+            );
+        } else {
+            match &pass.input_bind_group {
+                Some(input_bind_group) if pass.groups > 0 => {
+                    render_pass.set_bind_group(0, input_bind_group, &[]);
+                }
+                _ => {}
+            }
+            // post-processing covers everything the shader's passes drew
+            self.draw_screen(render_pass, &self.quad);
+        }
+    }
+
+    // what's drawn over a pass once its shader is done: --layers, and for the final pass
+    // the --reference image and the overlay
+    fn draw_over_pass<'pass>(&'pass self, render_pass: &mut RenderPass<'pass>, pass: &'pass Pass) {
+        for layer in &pass.layers {
+            layer.draw(render_pass, &self.quad);
+        }
+        if !pass.to_surface() {
+            return;
+        }
+        if let Some(reference) = &self.reference {
+            reference.draw(render_pass, &self.quad, self.size);
+        }
+        if self.overlay.visible() {
+            self.overlay.draw(render_pass, &self.quad);
+        }
+    }
+
+    // draw a pass's shader over the whole viewport
+    fn draw_screen<'pass>(&'pass self, render_pass: &mut RenderPass<'pass>, quad: &'pass Mesh) {
+        match ScreenGeometry::new(&self.config) {
+            ScreenGeometry::Quad => quad.draw(render_pass),
+            // three vertices with one instance
+            ScreenGeometry::Triangle => render_pass.draw(0..3, 0..1),
+        }
     }
 
     pub(super) fn render(&mut self) -> Result<(), SurfaceError> {
         // surface gives us somewhere to render to
-        let output = self.surface.get_current_texture()?;
+        let acquire = tracing::info_span!("acquire").entered();
+        let output = self
+            .output
+            .current_texture(&self.device, &self.surface_config)?;
+        drop(acquire);
+        let encode = tracing::info_span!("encode").entered();
         // TextureView for controlling render code interaction with the texture
         let view = output
-            .texture
+            .texture()
             .create_view(&TextureViewDescriptor::default());
         // encoder builds command buffer and creates commands for sending to GPU
         let mut encoder = self
@@ -331,44 +3166,234 @@ impl State {
             .create_command_encoder(&CommandEncoderDescriptor {
                 label: Some("Render Encoder"),
             });
-        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
-            label: Some("Render Pass"),
-            // where to draw colour to
-            color_attachments: &[
-                // `[[location(0)]]` in the fragment shader's return val is this attachment
-                RenderPassColorAttachment {
-                    // render to the TextureView on the screen's surface
-                    // in other words, render output will be displayed in the window when it's
-                    // submitted and presented
-                    view: &view,
-                    // defaults to &view if multisampling is off
-                    resolve_target: None,
-                    // what to do with colours on the screen from `view`
-                    ops: Operations {
-                        // clear them (because not all screen is covered by objects)
-                        load: LoadOp::Clear(self.background_colour),
-                        // yes we do want to store the result
-                        store: true,
-                    },
+        // last frame's feedback is kept, and the other buffer is drawn over
+        if let Some(feedback) = &mut self.feedback {
+            feedback.swap();
+        }
+        let uniform_bind_groups = self.uniform_bind_groups();
+        let dynamic_offsets = self.dynamic_offsets();
+        // the storage buffer is filled before any pass reads it
+        if let Some(compute) = &self.compute {
+            compute.dispatch(&mut encoder, &dynamic_offsets);
+        }
+        for pass in &self.passes {
+            // render to the pass's offscreen target, or for the final pass,
+            // the TextureView on the screen's surface
+            // in other words, render output will be displayed in the window when it's
+            // submitted and presented
+            let target = match &self.feedback {
+                Some(feedback) if pass.feedback => feedback.target(),
+                _ => pass.target.as_ref().map_or(&view, PassTarget::render_view),
+            };
+            // what to do with colours on the screen from `view`
+            let mut ops = Operations {
+                // clear them (because not all screen is covered by objects)
+                // unless they're the --accumulate average so far
+                load: if pass.accumulate {
+                    LoadOp::Load
+                } else {
+                    LoadOp::Clear(self.background_colour)
                 },
-            ],
-            depth_stencil_attachment: None,
+                // yes we do want to store the result
+                store: true,
+            };
+            let depth = self.depth.as_ref().filter(|_| pass.depth);
+            if pass.extra_targets.is_empty() && depth.is_none() && pass.multisampled.is_none() {
+                let mut render_pass = begin_render_pass(&mut encoder, target, ops, &[], None, None);
+                if let (Some(pipeline), Some(background)) = (&pass.background, &self.background) {
+                    background.draw(&mut render_pass, pipeline, &self.quad);
+                }
+                self.draw_pass(
+                    &mut render_pass,
+                    pass,
+                    uniform_bind_groups,
+                    &dynamic_offsets,
+                );
+                self.draw_over_pass(&mut render_pass, pass);
+            } else {
+                // only the shader writes the extra targets, tests the --depth buffer and draws
+                // --msaa samples, and pipelines have to match every attachment of the render
+                // pass they're drawn in, so everything else drawn to `[[location(0)]]` gets
+                // render passes of its own, before and after
+                if let (Some(pipeline), Some(background)) = (&pass.background, &self.background) {
+                    let mut render_pass =
+                        begin_render_pass(&mut encoder, target, ops, &[], None, None);
+                    background.draw(&mut render_pass, pipeline, &self.quad);
+                    ops.load = LoadOp::Load;
+                }
+                let mut render_pass = begin_render_pass(
+                    &mut encoder,
+                    target,
+                    ops,
+                    &pass.extra_targets,
+                    depth,
+                    pass.multisampled.as_ref(),
+                );
+                self.draw_pass(
+                    &mut render_pass,
+                    pass,
+                    uniform_bind_groups,
+                    &dynamic_offsets,
+                );
+                drop(render_pass);
+                ops.load = LoadOp::Load;
+                let mut render_pass = begin_render_pass(&mut encoder, target, ops, &[], None, None);
+                self.draw_over_pass(&mut render_pass, pass);
+            }
+            // render passes (which own a &mut encoder) are dropped by now, so mipmaps can be
+            // drawn, and the encoder can be .finish()ed
+
+            for target in pass.target.iter().chain(&pass.extra_targets) {
+                self.mipmap_generator
+                    .generate(&self.device, &mut encoder, target);
+            }
+        }
+
+        // the final pass drew to the surface, so that's the finished frame
+        let capture = self.raw.is_some()
+            || self.gif.is_some()
+            || self.screenshot.is_some()
+            || self.icon.as_ref().is_some_and(IconPreview::due);
+        let pending = capture.then(|| {
+            PendingFrame::copy(
+                &self.device,
+                &mut encoder,
+                output.texture(),
+                self.surface_config.format,
+                self.surface_config.width,
+                self.surface_config.height,
+            )
         });
 
-        render_pass.set_pipeline(&self.render_pipeline);
-        render_pass.set_bind_group(0, self.time.bind_group(), &[]);
-        render_pass.set_bind_group(1, self.mouse.bind_group(), &[]);
-        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-        render_pass.set_index_buffer(self.index_buffer.slice(..), IndexFormat::Uint16);
-        // draw three vertices with one instance
-        render_pass.draw_indexed(0..self.num_indices, 0, 0..1_u32);
+        // the pixel under the cursor, if it's in the window
+        let picked = match self.last_cursor {
+            Some(cursor) if mem::take(&mut self.pick_pixel) => {
+                let x = (cursor.x.max(0.0) as u32).min(self.surface_config.width.saturating_sub(1));
+                let y =
+                    (cursor.y.max(0.0) as u32).min(self.surface_config.height.saturating_sub(1));
+                let pending = PendingFrame::copy_region(
+                    &self.device,
+                    &mut encoder,
+                    output.texture(),
+                    self.surface_config.format,
+                    Origin3d { x, y, z: 0 },
+                    1,
+                    1,
+                );
+                Some((x, y, pending))
+            }
+            _ => None,
+        };
+
+        drop(encode);
 
-        // drop render pass (which owns a &mut encoder) so it can be .finish()ed
-        drop(render_pass);
         // submit() takes any IntoIter
+        let submit = tracing::info_span!("submit").entered();
         self.queue.submit(std::iter::once(encoder.finish()));
+        drop(submit);
+        if let Some(pacer) = &mut self.pacer {
+            let _pace = tracing::info_span!("pace").entered();
+            pacer.wait();
+        }
+        let present = tracing::info_span!("present").entered();
         output.present();
+        drop(present);
+
+        let readback = tracing::info_span!("readback").entered();
+        let frame = pending.map(|pending| pending.read(&self.device));
+        if let Some((x, y, pending)) = picked {
+            let pixel = pending.read(&self.device);
+            println!(
+                "Pixel at {}, {}: {}",
+                x,
+                y,
+                pixel_text(&pixel, self.surface_config.format)
+            );
+        }
+        drop(readback);
+        if let Some(frame) = &frame {
+            if let Some(path) = self.screenshot.take() {
+                let saved = write_png(frame, &path, self.config.screenshot_colorspace);
+                self.screenshot_failed = saved.is_err();
+                match saved {
+                    Ok(()) => log::info!("Saved screenshot {}", path.display()),
+                    Err(e) => log::error!("Could not save screenshot {}: {}", path.display(), e),
+                }
+            }
+            if self.raw.is_some() || self.gif.is_some() {
+                self.write_metadata(frame);
+            }
+        }
+        if let (Some(frame), Some(raw)) = (&frame, &mut self.raw) {
+            // most likely the program reading the frames has exited, so stop writing but keep running
+            if let Err(e) = raw.write(frame) {
+                log::error!("Could not write raw frame, stopping: {}", e);
+                self.raw = None;
+            }
+        }
+        if let (Some(frame), Some(icon)) = (&frame, &mut self.icon) {
+            if icon.due() {
+                icon.push(frame);
+            }
+        }
+        let recorded = match (frame, &mut self.gif) {
+            (Some(frame), Some(gif)) => gif.push(frame),
+            _ => false,
+        };
+        if recorded {
+            if let Some(gif) = self.gif.take() {
+                if let Err(e) = gif.encode() {
+                    log::error!("Could not write GIF: {}", e);
+                    self.gif_failed = true;
+                }
+            }
+            self.finished = true;
+        }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // what `render_to_file` relies on for --at, to come out the same however long it takes
+    #[test]
+    fn set_time_overrides_the_clock_and_recording() {
+        let at = Duration::from_millis(1500);
+        for clock in [
+            Duration::ZERO,
+            Duration::from_secs(7),
+            Duration::from_secs(3600),
+        ] {
+            assert_eq!(shader_time(Some(at), None, clock), at);
+            assert_eq!(
+                shader_time(Some(at), Some(Duration::from_secs(2)), clock),
+                at
+            );
+        }
+    }
+
+    #[test]
+    fn clock_runs_without_set_time() {
+        let clock = Duration::from_secs(7);
+        assert_eq!(shader_time(None, None, clock), clock);
+        let recording = Duration::from_secs(2);
+        assert_eq!(shader_time(None, Some(recording), clock), recording);
+    }
+
+    #[test]
+    fn time_max_wraps_set_time() {
+        let time_max = Some(Duration::from_secs(10));
+        assert_eq!(
+            wrap_time(Duration::from_millis(12_500), time_max),
+            Duration::from_millis(2500)
+        );
+        assert_eq!(wrap_time(Duration::from_secs(10), time_max), Duration::ZERO);
+        assert_eq!(
+            wrap_time(Duration::from_millis(12_500), None),
+            Duration::from_millis(12_500)
+        );
+    }
+}
@@ -0,0 +1,166 @@
+use wgpu::{util::DeviceExt, *};
+
+use super::super::config::Filter;
+use super::textures::{Channel, Sampling};
+
+// width and height of the tile, which repeats seamlessly
+const SIZE: usize = 64;
+const AREA: usize = SIZE * SIZE;
+// how far apart the noise tries to keep points, in pixels
+const SIGMA: f32 = 1.5;
+// past this many pixels away, a point's effect on crowding is too small to matter
+const RADIUS: usize = 6;
+// fraction of pixels in the starting pattern, which the rest are ranked around
+const INITIAL_DENSITY: usize = 10;
+
+/// A tileable blue noise texture, for --blue-noise
+///
+/// Made with the void-and-cluster method, which ranks pixels by repeatedly picking the one
+/// furthest from those already picked, so thresholding it at any level spreads points evenly.
+/// It's generated the same every time, instead of being shipped as an image.
+pub(super) fn blue_noise_channel(device: &Device, queue: &Queue) -> Channel {
+    let pixels: Vec<u8> = void_and_cluster()
+        .into_iter()
+        .map(|rank| (rank * 256 / AREA) as u8)
+        .collect();
+    let texture = device.create_texture_with_data(
+        queue,
+        &TextureDescriptor {
+            label: Some("Blue Noise Texture"),
+            size: Extent3d {
+                width: SIZE as u32,
+                height: SIZE as u32,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            // ranks, not colours, so no sRGB decoding
+            format: TextureFormat::R8Unorm,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+        },
+        &pixels,
+    );
+    // blending between neighbours would blur the noise back into lower frequencies
+    let sampling = Sampling {
+        address_mode: AddressMode::Repeat,
+        anisotropy: None,
+    };
+    Channel::with_texture(
+        device,
+        &texture,
+        TextureViewDimension::D2,
+        Filter::Nearest,
+        sampling,
+    )
+}
+
+/// How crowded each pixel is by the chosen ones around it, wrapping at the edges
+struct Energy {
+    // gaussian falloff by x and y distance, with wrapping
+    kernel: Vec<f32>,
+    energy: Vec<f32>,
+    chosen: Vec<bool>,
+}
+
+impl Energy {
+    fn new() -> Self {
+        let falloff = |distance: usize| {
+            let distance = distance.min(SIZE - distance) as f32;
+            distance * distance
+        };
+        let kernel = (0..AREA)
+            .map(|index| {
+                let squared = falloff(index % SIZE) + falloff(index / SIZE);
+                (-squared / (2.0 * SIGMA * SIGMA)).exp()
+            })
+            .collect();
+        Self {
+            kernel,
+            energy: vec![0.0; AREA],
+            chosen: vec![false; AREA],
+        }
+    }
+
+    fn set(&mut self, pixel: usize, chosen: bool) {
+        self.chosen[pixel] = chosen;
+        let sign = if chosen { 1.0 } else { -1.0 };
+        let (x, y) = (pixel % SIZE, pixel / SIZE);
+        // offsets from -RADIUS to RADIUS, kept positive by wrapping around
+        let offsets = || (SIZE - RADIUS..SIZE).chain(0..=RADIUS);
+        for dy in offsets() {
+            for dx in offsets() {
+                let index = (y + dy) % SIZE * SIZE + (x + dx) % SIZE;
+                self.energy[index] += sign * self.kernel[dy * SIZE + dx];
+            }
+        }
+    }
+
+    // the chosen pixel with the most chosen ones around it
+    fn tightest_cluster(&self) -> usize {
+        self.extreme(true, |a, b| a > b)
+    }
+
+    // the unchosen pixel furthest from the chosen ones
+    fn largest_void(&self) -> usize {
+        self.extreme(false, |a, b| a < b)
+    }
+
+    // the first pixel that's `chosen` or not, with energy beating all the others
+    fn extreme(&self, chosen: bool, beats: impl Fn(f32, f32) -> bool) -> usize {
+        let mut best: Option<usize> = None;
+        for pixel in (0..AREA).filter(|&pixel| self.chosen[pixel] == chosen) {
+            if best.is_none_or(|best| beats(self.energy[pixel], self.energy[best])) {
+                best = Some(pixel);
+            }
+        }
+        best.expect("There are pixels on both sides")
+    }
+}
+
+// every pixel's rank from 0 to AREA - 1
+fn void_and_cluster() -> Vec<usize> {
+    let mut energy = Energy::new();
+    // a fixed xorshift, so the noise is the same every run
+    let mut state: u32 = 0x9e37_79b9;
+    let mut placed = 0;
+    while placed < AREA / INITIAL_DENSITY {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        let pixel = state as usize % AREA;
+        if !energy.chosen[pixel] {
+            energy.set(pixel, true);
+            placed += 1;
+        }
+    }
+    // even out the starting points, moving the most crowded into the emptiest gap until the
+    // emptiest gap is where it came from
+    loop {
+        let cluster = energy.tightest_cluster();
+        energy.set(cluster, false);
+        let void = energy.largest_void();
+        energy.set(void, true);
+        if void == cluster {
+            break;
+        }
+    }
+    let mut ranks = vec![0; AREA];
+    // rank the starting points by taking them away, most crowded first, on a copy
+    let starting = energy.chosen.clone();
+    let starting_energy = energy.energy.clone();
+    for rank in (0..placed).rev() {
+        let cluster = energy.tightest_cluster();
+        energy.set(cluster, false);
+        ranks[cluster] = rank;
+    }
+    energy.chosen = starting;
+    energy.energy = starting_energy;
+    // then the rest by filling the emptiest gap each time
+    for rank in placed..AREA {
+        let void = energy.largest_void();
+        energy.set(void, true);
+        ranks[void] = rank;
+    }
+    ranks
+}
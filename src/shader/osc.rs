@@ -0,0 +1,103 @@
+use rosc::{decoder, OscPacket, OscType};
+use std::{
+    net::UdpSocket,
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::Duration,
+};
+
+use super::uniforms::CustomUniform;
+
+// how long to wait before receiving again after the socket errors, doubling each time it
+// errors again, so a broken socket doesn't spin a core and flood the log
+const RETRY_DELAY: Duration = Duration::from_millis(100);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Receives OSC messages on a background thread, for setting custom uniforms
+#[derive(Debug)]
+pub(super) struct OscListener {
+    // custom uniform index and its new value
+    receiver: Receiver<(usize, f32)>,
+}
+
+impl OscListener {
+    // `names` are the custom uniforms' names, in slot order
+    pub(super) fn spawn(port: u16, names: Vec<String>) -> std::io::Result<Self> {
+        let socket = UdpSocket::bind(("0.0.0.0", port))?;
+        let (sender, receiver) = mpsc::channel();
+        thread::Builder::new()
+            .name("OSC Listener".to_owned())
+            .spawn(move || listen(&socket, &names, &sender))?;
+        log::info!("Listening for OSC on port {}", port);
+        Ok(Self { receiver })
+    }
+
+    // apply every value received since the last poll
+    pub(super) fn poll(&self, uniform: &mut CustomUniform) {
+        for (index, value) in self.receiver.try_iter() {
+            uniform.set(index, value);
+        }
+    }
+}
+
+fn listen(socket: &UdpSocket, names: &[String], sender: &Sender<(usize, f32)>) {
+    let mut buffer = [0; decoder::MTU];
+    let mut retry_delay = RETRY_DELAY;
+    loop {
+        let size = match socket.recv(&mut buffer) {
+            Ok(size) => size,
+            Err(error) => {
+                log::error!(
+                    "Failed to receive OSC packet, trying again in {:?}: {}",
+                    retry_delay,
+                    error
+                );
+                thread::sleep(retry_delay);
+                retry_delay = (retry_delay * 2).min(MAX_RETRY_DELAY);
+                continue;
+            }
+        };
+        retry_delay = RETRY_DELAY;
+        match decoder::decode_udp(&buffer[..size]) {
+            Ok((_, packet)) => {
+                if !handle_packet(packet, names, sender) {
+                    // the window has closed
+                    return;
+                }
+            }
+            Err(error) => log::warn!("Ignoring malformed OSC packet: {}", error),
+        }
+    }
+}
+
+// returns false once nothing is receiving values any more
+fn handle_packet(packet: OscPacket, names: &[String], sender: &Sender<(usize, f32)>) -> bool {
+    match packet {
+        OscPacket::Message(message) => {
+            let name = message.addr.trim_start_matches('/');
+            let index = match names.iter().position(|existing| existing == name) {
+                Some(index) => index,
+                None => {
+                    log::warn!("No custom uniform named `{}`", name);
+                    return true;
+                }
+            };
+            let value = match message.args.first() {
+                Some(OscType::Float(value)) => *value,
+                Some(OscType::Double(value)) => *value as f32,
+                Some(OscType::Int(value)) => *value as f32,
+                Some(OscType::Long(value)) => *value as f32,
+                Some(OscType::Bool(value)) => f32::from(u8::from(*value)),
+                _ => {
+                    log::warn!("OSC message to `{}` has no numeric argument", message.addr);
+                    return true;
+                }
+            };
+            sender.send((index, value)).is_ok()
+        }
+        OscPacket::Bundle(bundle) => bundle
+            .content
+            .into_iter()
+            .all(|packet| handle_packet(packet, names, sender)),
+    }
+}
@@ -0,0 +1,88 @@
+use wgpu::*;
+use winit::{dpi::PhysicalSize, window::Window};
+
+/// What a State draws for
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Target<'a> {
+    Window(&'a Window),
+    // frames this size that are only read back, for --output
+    Offscreen(PhysicalSize<u32>),
+}
+
+/// Where finished frames go
+#[derive(Debug)]
+pub(super) enum Output {
+    // the window's surface, shown as each frame is presented
+    Surface(Surface),
+    // a new texture for each frame, made to the surface configuration and never shown
+    Offscreen,
+}
+
+impl Output {
+    pub(super) fn configure(&self, device: &Device, config: &SurfaceConfiguration) {
+        if let Output::Surface(surface) = self {
+            surface.configure(device, config);
+        }
+    }
+
+    // the texture to draw the next frame into
+    // the surface goes outdated while the window changes state, so that gets one retry after
+    // reconfiguring, instead of dropping the frame
+    pub(super) fn current_texture(
+        &self,
+        device: &Device,
+        config: &SurfaceConfiguration,
+    ) -> Result<OutputTexture, SurfaceError> {
+        let surface = match self {
+            Output::Surface(surface) => surface,
+            Output::Offscreen => {
+                let texture = device.create_texture(&TextureDescriptor {
+                    label: Some("Offscreen Frame"),
+                    size: Extent3d {
+                        width: config.width,
+                        height: config.height,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: TextureDimension::D2,
+                    format: config.format,
+                    usage: config.usage,
+                });
+                return Ok(OutputTexture::Offscreen(texture));
+            }
+        };
+        let texture = match surface.get_current_texture() {
+            Err(SurfaceError::Outdated) => {
+                log::debug!("Surface outdated, reconfiguring");
+                surface.configure(device, config);
+                surface.get_current_texture()
+            }
+            result => result,
+        };
+        texture.map(OutputTexture::Surface)
+    }
+}
+
+/// A frame's texture from an Output
+#[derive(Debug)]
+pub(super) enum OutputTexture {
+    Surface(SurfaceTexture),
+    Offscreen(Texture),
+}
+
+impl OutputTexture {
+    pub(super) fn texture(&self) -> &Texture {
+        match self {
+            OutputTexture::Surface(surface) => &surface.texture,
+            OutputTexture::Offscreen(texture) => texture,
+        }
+    }
+
+    // show the frame in the window, while offscreen frames are only read back
+    pub(super) fn present(self) {
+        if let OutputTexture::Surface(surface) = self {
+            surface.present();
+        }
+    }
+}
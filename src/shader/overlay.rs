@@ -0,0 +1,273 @@
+use bytemuck::{Pod, Zeroable};
+use std::mem;
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    *,
+};
+
+use super::{
+    geometry::{Mesh, ScreenGeometry},
+    new_pipeline,
+    uniforms::{
+        bindings::{uniform_bind_group_layout, Uniform, UniformBinding},
+        OverlayUniform,
+    },
+};
+
+/// Something the overlay can draw, as a bit of the overlay uniform's `layers`
+#[derive(Clone, Copy, Debug)]
+pub(super) enum OverlayLayer {
+    // grid and crosshair, for lining up shader coordinates
+    Grid = 1,
+    // axes, tick labels and the -1..1 bounds of normalized device coordinates
+    Ndc = 2,
+}
+
+// the grid's spacing, as a fraction of the screen
+const GRID_SPACING: f32 = 0.1;
+// faint white grid lines, and a stronger red crosshair
+const GRID_COLOUR: [f32; 4] = [1.0, 1.0, 1.0, 0.35];
+const CROSSHAIR_COLOUR: [f32; 4] = [1.0, 0.2, 0.2, 0.8];
+
+/// An end of one of the grid's lines
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct LineVertex {
+    // in physical pixels from the bottom left
+    position: [f32; 2],
+    // straight alpha, less than the line's own at its antialiased edges
+    colour: [f32; 4],
+}
+
+impl LineVertex {
+    const ATTRIBS: [VertexAttribute; 2] = vertex_attr_array![0 => Float32x2, 1 => Float32x4];
+
+    fn desc<'pipeline>() -> VertexBufferLayout<'pipeline> {
+        VertexBufferLayout {
+            array_stride: mem::size_of::<LineVertex>() as BufferAddress,
+            step_mode: VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+// a line `line_width` pixels wide through `centre` pixels across a `size` pixel screen, down
+// it if `vertical` or else along it, as one-pixel lines through each column (or row) of pixels
+// it touches, fainter at its edges by how little of those pixels it covers
+fn push_line(
+    vertices: &mut Vec<LineVertex>,
+    size: [u32; 2],
+    centre: f32,
+    line_width: f32,
+    colour: [f32; 4],
+    vertical: bool,
+) {
+    let (across, length) = if vertical {
+        (size[0], size[1] as f32)
+    } else {
+        (size[1], size[0] as f32)
+    };
+    // cut off at the edges of the screen
+    let first = (centre - line_width * 0.5 - 0.5).floor().max(0.0) as u32;
+    let last = ((centre + line_width * 0.5 + 0.5).ceil().max(0.0) as u32).min(across);
+    for pixel in first..last {
+        // through the middle of the pixels, so each lights exactly one column
+        let along = pixel as f32 + 0.5;
+        let coverage = (line_width * 0.5 + 0.5 - (along - centre).abs()).clamp(0.0, 1.0);
+        if coverage <= 0.0 {
+            continue;
+        }
+        let [red, green, blue, alpha] = colour;
+        let colour = [red, green, blue, alpha * coverage];
+        let (start, end) = if vertical {
+            ([along, 0.0], [along, length])
+        } else {
+            ([0.0, along], [length, along])
+        };
+        vertices.push(LineVertex {
+            position: start,
+            colour,
+        });
+        vertices.push(LineVertex {
+            position: end,
+            colour,
+        });
+    }
+}
+
+// a grid every tenth of the screen, and a crosshair through the centre over it
+// line widths are in logical pixels so they look the same on HiDPI screens
+fn grid_lines(width: u32, height: u32, scale_factor: f64) -> Vec<LineVertex> {
+    let size = [width, height];
+    let line_width = scale_factor as f32;
+    let mut vertices = Vec::new();
+    let lines = (0.5 / GRID_SPACING).round() as i32;
+    for step in -lines..=lines {
+        let offset = 0.5 + step as f32 * GRID_SPACING;
+        let (x, y) = (offset * width as f32, offset * height as f32);
+        push_line(&mut vertices, size, x, line_width, GRID_COLOUR, true);
+        push_line(&mut vertices, size, y, line_width, GRID_COLOUR, false);
+    }
+    let (x, y) = (0.5 * width as f32, 0.5 * height as f32);
+    let crosshair_width = line_width * 2.0;
+    push_line(
+        &mut vertices,
+        size,
+        x,
+        crosshair_width,
+        CROSSHAIR_COLOUR,
+        true,
+    );
+    push_line(
+        &mut vertices,
+        size,
+        y,
+        crosshair_width,
+        CROSSHAIR_COLOUR,
+        false,
+    );
+    vertices
+}
+
+/// Debug guides drawn over the final pass, each of which can be shown or hidden
+#[derive(Debug)]
+pub(super) struct Overlay {
+    // the NDC guides, drawn by a shader over the whole screen
+    pipeline: RenderPipeline,
+    // the grid, drawn as lines
+    line_pipeline: RenderPipeline,
+    lines: Buffer,
+    line_vertices: u32,
+    uniform: UniformBinding<OverlayUniform>,
+}
+
+impl Overlay {
+    // `layers` are the bits of the OverlayLayers to start with
+    pub(super) fn new(
+        device: &Device,
+        format: TextureFormat,
+        width: u32,
+        height: u32,
+        scale_factor: f64,
+        layers: u32,
+    ) -> Self {
+        let bind_group_layout = uniform_bind_group_layout(device, "Overlay Bind Group Layout");
+        let uniform = OverlayUniform::new(width, height, scale_factor, layers)
+            .make_binding(device, &bind_group_layout);
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Overlay Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let shader = device.create_shader_module(&ShaderModuleDescriptor {
+            label: Some("Overlay Shader"),
+            source: ShaderSource::Wgsl(include_str!("overlay.wgsl").into()),
+        });
+        let line_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Overlay Line Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &shader,
+                entry_point: "vs_line",
+                buffers: &[LineVertex::desc()],
+            },
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: "fs_line",
+                // blend over the top of whatever the shader drew
+                targets: &[ColorTargetState {
+                    format,
+                    blend: Some(BlendState::ALPHA_BLENDING),
+                    write_mask: ColorWrites::ALL,
+                }],
+            }),
+            primitive: PrimitiveState {
+                // each pair of vertices is a line one pixel wide
+                topology: PrimitiveTopology::LineList,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+        });
+        let (lines, line_vertices) = line_buffer(device, width, height, scale_factor);
+        // blend over the top of whatever the shader drew
+        let pipeline = new_pipeline(
+            device,
+            format,
+            &pipeline_layout,
+            shader,
+            BlendState::ALPHA_BLENDING,
+            ScreenGeometry::Quad,
+        );
+        Self {
+            pipeline,
+            line_pipeline,
+            lines,
+            line_vertices,
+            uniform,
+        }
+    }
+
+    pub(super) fn toggle(&mut self, queue: &Queue, layer: OverlayLayer) {
+        self.uniform.uniform_mut().toggle_layers(layer as u32);
+        self.write(queue);
+    }
+
+    pub(super) fn layers(&self) -> u32 {
+        self.uniform.uniform().layers()
+    }
+
+    pub(super) fn visible(&self) -> bool {
+        self.layers() != 0
+    }
+
+    // keep line spacing and widths in step with the window
+    pub(super) fn resize(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        width: u32,
+        height: u32,
+        scale_factor: f64,
+    ) {
+        *self.uniform.uniform_mut() =
+            OverlayUniform::new(width, height, scale_factor, self.layers());
+        self.write(queue);
+        (self.lines, self.line_vertices) = line_buffer(device, width, height, scale_factor);
+    }
+
+    fn write(&self, queue: &Queue) {
+        queue.write_buffer(
+            self.uniform.buffer(),
+            0,
+            bytemuck::cast_slice(&[*self.uniform.uniform()]),
+        );
+    }
+
+    // draw into a render pass, over whatever the pass drew
+    // the NDC guides go over the grid
+    pub(super) fn draw<'pass>(&'pass self, render_pass: &mut RenderPass<'pass>, quad: &'pass Mesh) {
+        render_pass.set_bind_group(0, self.uniform.bind_group(), &[]);
+        if self.layers() & OverlayLayer::Grid as u32 != 0 {
+            render_pass.set_pipeline(&self.line_pipeline);
+            render_pass.set_vertex_buffer(0, self.lines.slice(..));
+            render_pass.draw(0..self.line_vertices, 0..1);
+        }
+        if self.layers() & OverlayLayer::Ndc as u32 != 0 {
+            render_pass.set_pipeline(&self.pipeline);
+            quad.draw(render_pass);
+        }
+    }
+}
+
+// the grid's lines for a window this size, and how many vertices there are
+fn line_buffer(device: &Device, width: u32, height: u32, scale_factor: f64) -> (Buffer, u32) {
+    let vertices = grid_lines(width, height, scale_factor);
+    let buffer = device.create_buffer_init(&BufferInitDescriptor {
+        label: Some("Overlay Line Buffer"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: BufferUsages::VERTEX,
+    });
+    (buffer, vertices.len() as u32)
+}
@@ -0,0 +1,156 @@
+// Debug overlay drawn over the shader's output
+// a grid every tenth of the screen and a crosshair through the centre, drawn as lines
+// and/or the axes and -1..1 bounds of normalized device coordinates, with labelled ticks,
+// drawn over the whole screen
+
+struct Overlay {
+    // size of the render target in physical pixels
+    resolution: vec2<f32>;
+    // physical pixels per logical pixel
+    scale_factor: f32;
+    // which of the layers below to draw, as bits
+    layers: u32;
+};
+
+[[group(0), binding(0)]]
+var<uniform> overlay: Overlay;
+
+struct VertexInput {
+    [[location(0)]] position: vec3<f32>;
+    [[location(1)]] tex_coords: vec2<f32>;
+};
+
+struct VertexOutput {
+    [[builtin(position)]] clip_position: vec4<f32>;
+    [[location(0)]] tex_coords: vec2<f32>;
+};
+
+[[stage(vertex)]]
+fn vs_main(model: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.tex_coords = model.tex_coords;
+    out.clip_position = vec4<f32>(model.position, 1.0);
+    return out;
+}
+
+// distance in pixels to the nearest of a set of lines `spacing` apart in uv space
+fn grid_distance(uv: f32, spacing: f32, pixels: f32) -> f32 {
+    let lines = uv / spacing;
+    return abs(lines - round(lines)) * spacing * pixels;
+}
+
+// how much of a pixel is covered by a line `width` pixels wide, `distance` pixels away
+// fades over a single pixel at the edge of the line, for antialiasing
+fn coverage(distance: f32, width: f32) -> f32 {
+    return clamp(width * 0.5 + 0.5 - distance, 0.0, 1.0);
+}
+
+// 3x5 pixel font for the NDC labels, one bit per pixel, rows from the top, high bits first
+// codes 0 to 2 are the digits 0, 1 and 5, then 3 is `-`, 4 is `.`, 5 is `x` and 6 is `y`
+fn glyph(code: u32) -> u32 {
+    var glyphs: array<u32, 7> = array<u32, 7>(31599u, 11415u, 31183u, 448u, 2u, 2728u, 23246u);
+    return glyphs[code];
+}
+
+// whether a pixel is lit in a label with its top left corner at `origin`, both in pixels
+// `text` holds `count` glyph codes packed a nibble each, first glyph lowest
+fn label(pixel: vec2<f32>, origin: vec2<f32>, text: u32, count: u32) -> f32 {
+    // font pixels are two logical pixels across
+    let cell = floor((pixel - origin) / (2.0 * overlay.scale_factor));
+    if (cell.x < 0.0 || cell.y < 0.0 || cell.y > 4.0 || cell.x >= f32(count * 4u)) {
+        return 0.0;
+    }
+    // a blank column after each glyph
+    let column = u32(cell.x) % 4u;
+    if (column == 3u) {
+        return 0.0;
+    }
+    let code = (text >> (u32(cell.x) / 4u * 4u)) & 15u;
+    let bit = 14u - u32(cell.y) * 3u - column;
+    return f32((glyph(code) >> bit) & 1u);
+}
+
+// a label just below and right of a point in normalized device coordinates,
+// nudged back onto the screen if it would go off the edge
+fn ndc_label(pixel: vec2<f32>, ndc: vec2<f32>, text: u32, count: u32) -> f32 {
+    let size = 2.0 * overlay.scale_factor;
+    let extent = vec2<f32>(f32(count * 4u - 1u), 5.0) * size;
+    let margin = vec2<f32>(2.0 * size);
+    let anchor = (vec2<f32>(ndc.x, -ndc.y) * 0.5 + 0.5) * overlay.resolution;
+    let origin = clamp(anchor + margin, margin, overlay.resolution - extent - margin);
+    return label(pixel, origin, text, count);
+}
+
+// x and y axes with ticks every 0.5, labelled, and the -1..1 bounds around the edge
+fn ndc_layer(tex_coords: vec2<f32>) -> vec4<f32> {
+    let width = overlay.scale_factor;
+    let ndc = tex_coords * 2.0 - 1.0;
+    // in pixels from the top left, like the labels are laid out in
+    let pixel = vec2<f32>(tex_coords.x, 1.0 - tex_coords.y) * overlay.resolution;
+    // pixels per NDC unit
+    let scale = overlay.resolution * 0.5;
+
+    let x_axis = coverage(abs(ndc.y) * scale.y, width * 2.0);
+    let y_axis = coverage(abs(ndc.x) * scale.x, width * 2.0);
+    // ticks every 0.5 along each axis
+    let tick_length = 6.0 * width;
+    let x_ticks = coverage(grid_distance(ndc.x, 0.5, scale.x), width)
+        * step(abs(ndc.y) * scale.y, tick_length);
+    let y_ticks = coverage(grid_distance(ndc.y, 0.5, scale.y), width)
+        * step(abs(ndc.x) * scale.x, tick_length);
+    // the bounds are the edges of the viewport, so only the inner half of the line shows
+    let edge = min(min(pixel.x, overlay.resolution.x - pixel.x), min(pixel.y, overlay.resolution.y - pixel.y));
+    let bounds = coverage(edge, width * 4.0);
+
+    // glyph codes packed as in `label`: -1, -0.5, 0, 0.5, 1, x and y
+    var text = ndc_label(pixel, vec2<f32>(0.0, 0.0), 0u, 1u);
+    text = max(text, ndc_label(pixel, vec2<f32>(-1.0, 0.0), 19u, 2u));
+    text = max(text, ndc_label(pixel, vec2<f32>(-0.5, 0.0), 9219u, 4u));
+    text = max(text, ndc_label(pixel, vec2<f32>(0.5, 0.0), 576u, 3u));
+    text = max(text, ndc_label(pixel, vec2<f32>(1.0, 0.0), 1u, 1u));
+    text = max(text, ndc_label(pixel, vec2<f32>(0.0, -1.0), 19u, 2u));
+    text = max(text, ndc_label(pixel, vec2<f32>(0.0, -0.5), 9219u, 4u));
+    text = max(text, ndc_label(pixel, vec2<f32>(0.0, 0.5), 576u, 3u));
+    text = max(text, ndc_label(pixel, vec2<f32>(0.0, 1.0), 1u, 1u));
+    text = max(text, ndc_label(pixel, vec2<f32>(0.9, 0.15), 5u, 1u));
+    text = max(text, ndc_label(pixel, vec2<f32>(0.05, 1.0), 6u, 1u));
+
+    // red x axis and green y axis, like most 3D tools, inside yellow bounds, with white text
+    var colour = vec3<f32>(1.0, 0.9, 0.2);
+    colour = mix(colour, vec3<f32>(1.0, 0.25, 0.25), max(x_axis, x_ticks));
+    colour = mix(colour, vec3<f32>(0.3, 1.0, 0.3), max(y_axis, y_ticks));
+    colour = mix(colour, vec3<f32>(1.0), text);
+    let lines = max(max(x_axis, x_ticks), max(y_axis, y_ticks));
+    return vec4<f32>(colour, max(max(bounds, lines), text) * 0.9);
+}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    // only drawn while the NDC layer is shown
+    return ndc_layer(in.tex_coords);
+}
+
+struct LineVertex {
+    // in physical pixels from the bottom left
+    [[location(0)]] position: vec2<f32>;
+    [[location(1)]] colour: vec4<f32>;
+};
+
+struct LineOutput {
+    [[builtin(position)]] clip_position: vec4<f32>;
+    [[location(0)]] colour: vec4<f32>;
+};
+
+// the grid's lines, one pixel wide each, laid out and antialiased on the CPU
+[[stage(vertex)]]
+fn vs_line(line: LineVertex) -> LineOutput {
+    var out: LineOutput;
+    out.clip_position = vec4<f32>(line.position / overlay.resolution * 2.0 - 1.0, 0.0, 1.0);
+    out.colour = line.colour;
+    return out;
+}
+
+[[stage(fragment)]]
+fn fs_line(in: LineOutput) -> [[location(0)]] vec4<f32> {
+    return in.colour;
+}
@@ -0,0 +1,48 @@
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+// how strongly the next sleep corrects the last frame's error, and the running total of errors
+const PROPORTIONAL: f64 = 0.5;
+const INTEGRAL: f64 = 0.1;
+
+/// Sleeps before each present so frames come out at a steady rate, for --pace
+///
+/// Sleeping overshoots by a varying amount, so the sleep is adjusted by how far off the
+/// previous frames were, like a PI controller.
+#[derive(Debug)]
+pub(super) struct FramePacer {
+    // seconds between frames
+    interval: f64,
+    last_present: Instant,
+    // sum of recent errors, clamped so a long stall doesn't cause a burst of fast frames
+    integral: f64,
+    // added to the next sleep
+    correction: f64,
+}
+
+impl FramePacer {
+    pub(super) fn new(fps: f64) -> Self {
+        Self {
+            interval: 1.0 / fps,
+            last_present: Instant::now(),
+            integral: 0.0,
+            correction: 0.0,
+        }
+    }
+
+    // wait until it's time for the next frame to be presented
+    pub(super) fn wait(&mut self) {
+        let target = self.interval + self.correction;
+        let elapsed = self.last_present.elapsed().as_secs_f64();
+        if target > elapsed {
+            thread::sleep(Duration::from_secs_f64(target - elapsed));
+        }
+        let now = Instant::now();
+        let error = self.interval - (now - self.last_present).as_secs_f64();
+        self.integral = (self.integral + error).clamp(-self.interval, self.interval);
+        self.correction = PROPORTIONAL * error + INTEGRAL * self.integral;
+        self.last_present = now;
+    }
+}
@@ -0,0 +1,595 @@
+use std::{
+    fmt,
+    num::NonZeroU32,
+    path::{Path, PathBuf},
+};
+use wgpu::*;
+
+use super::{
+    include::{line_marker, parse_line_marker},
+    textures::Channel,
+};
+
+// marks the start of a new pass, followed by the pass's name
+const PASS_MARKER: &str = "//!pass";
+// inside a pass, names an earlier pass whose output should be bound for sampling
+const INPUT_MARKER: &str = "//!input";
+
+// most earlier passes one pass can sample, so the inputs stay below CHANNEL_BINDING
+const MAX_INPUTS: usize = 16;
+// binding of the first --texture channel, in the same group as pass inputs
+const CHANNEL_BINDING: u32 = 2 * MAX_INPUTS as u32;
+
+// format for offscreen pass targets
+// a float format, so intermediate passes can store values outside 0..1
+pub(super) const PASS_TARGET_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+// format of the --depth buffer
+pub(super) const DEPTH_FORMAT: TextureFormat = TextureFormat::Depth32Float;
+
+/// One section of a (possibly multi-pass) shader file
+///
+/// A file with no `//!pass` markers is a single pass that renders to the screen.
+/// Otherwise, everything before the first marker is shared by every pass,
+/// and each marker starts a new pass that runs in file order:
+///
+/// ```wgsl
+/// // shared uniforms and helper functions
+/// //!pass buffer_a
+/// // ... vs_main and fs_main for buffer_a
+/// //!pass main
+/// //!input buffer_a
+/// // ... vs_main and fs_main for the final pass
+/// ```
+///
+/// Every pass except the last renders into its own offscreen texture.
+/// The last pass renders to the screen.
+/// `--manifest` lists passes in separate files instead, which work the same way.
+/// A pass can sample the outputs of earlier passes by listing them with `//!input`,
+/// and the `n`th input is bound in group 2, after the built-in uniforms,
+/// as a `texture_2d<f32>` at binding `2n`
+/// with a `sampler` at binding `2n + 1`.
+/// Up to 16 inputs can be listed.
+///
+/// A pass whose `fs_main` returns a struct with several `[[location(n)]]` members
+/// renders each of them to its own target.
+/// `//!input buffer_a` samples `[[location(0)]]`, and `//!input buffer_a.1` samples
+/// `[[location(1)]]`, and so on.
+/// The screen only shows the last pass's `[[location(0)]]`.
+///
+/// The same group holds the `--texture` images, for every pass of the shader file.
+/// The `k`th texture is at binding `32 + 2k` with its own sampler at binding `32 + 2k + 1`.
+/// `--texture-array` directories are numbered after the `--texture` images,
+/// and bound as `texture_2d_array<f32>`, so they're sampled with a layer index too.
+/// `--volume`s follow, as `texture_3d<f32>` sampled with `vec3<f32>` coordinates,
+/// with z going from the first slice to the last.
+/// A `--video` comes next, as a `texture_2d<f32>` showing the frame for the current time.
+/// `--blue-noise` comes last, as a `texture_2d<f32>` of tileable noise in its red channel.
+/// With `--feedback`, the feedback buffer is bound in group 3, see `Feedback`.
+/// Textures are addressed with y pointing down, the opposite way to `tex_coords`,
+/// so sample them at `vec2<f32>(tex_coords.x, 1.0 - tex_coords.y)`.
+#[derive(Debug)]
+pub(super) struct PassSource {
+    pub name: String,
+    // outputs of earlier passes this pass samples from
+    pub inputs: Vec<PassInput>,
+    pub source: String,
+    // whether the built-in uniforms are bound before the inputs
+    // built-in post-processing passes don't use them, so their inputs are in group 0
+    pub uniforms: bool,
+    // files the source had inlined with `//!include`, for --watch
+    pub includes: Vec<PathBuf>,
+}
+
+/// One output of an earlier pass, to be sampled by a later one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct PassInput {
+    // index of the pass
+    pub pass: usize,
+    // the `[[location(n)]]` it's written to
+    pub location: u32,
+}
+
+impl PassInput {
+    // a pass's usual output, at `[[location(0)]]`
+    pub(super) fn output(pass: usize) -> Self {
+        Self { pass, location: 0 }
+    }
+}
+
+// an `//!input` split into a pass name and the location after the `.`, if it has one
+fn input_location(input: &str) -> Option<(&str, u32)> {
+    let (name, location) = input.rsplit_once('.')?;
+    Some((name, location.parse().ok()?))
+}
+
+#[derive(Debug)]
+pub(super) enum PassError {
+    MissingName { line: usize },
+    DuplicateName(String),
+    InputOutsidePass { line: usize },
+    UnknownInput { pass: String, input: String },
+    TooManyInputs { pass: String },
+}
+
+impl fmt::Display for PassError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PassError::MissingName { line } => {
+                write!(f, "line {}: `{}` needs a pass name", line, PASS_MARKER)
+            }
+            PassError::DuplicateName(name) => write!(f, "pass `{}` is declared twice", name),
+            PassError::InputOutsidePass { line } => write!(
+                f,
+                "line {}: `{}` must come after a `{}` marker",
+                line, INPUT_MARKER, PASS_MARKER
+            ),
+            PassError::UnknownInput { pass, input } => write!(
+                f,
+                "pass `{}` reads `{}`, which isn't an earlier pass",
+                pass, input
+            ),
+            PassError::TooManyInputs { pass } => {
+                write!(f, "pass `{}` has more than {} inputs", pass, MAX_INPUTS)
+            }
+        }
+    }
+}
+
+/// Split a shader file into passes along its `//!pass` markers
+pub(super) fn split_passes(source: &str) -> Result<Vec<PassSource>, PassError> {
+    let mut prelude = String::new();
+    // name, input names, body
+    let mut sections: Vec<(String, Vec<String>, String)> = Vec::new();
+    // which file and line the line after this one came from, going by the line markers
+    // each pass's own lines end up after the prelude, so they're marked again at its start
+    let mut next_line: Option<(usize, &str)> = None;
+
+    for (index, line) in source.lines().enumerate() {
+        let trimmed = line.trim();
+        next_line = match parse_line_marker(trimmed) {
+            Some(marked) => Some(marked),
+            None => next_line.map(|(number, path)| (number + 1, path)),
+        };
+        // for errors, in the file as written if there are markers to say where that is
+        let line_number = next_line.map_or(index + 1, |(number, _)| number - 1);
+        if let Some(name) = trimmed.strip_prefix(PASS_MARKER) {
+            let name = name.trim();
+            if name.is_empty() {
+                return Err(PassError::MissingName { line: line_number });
+            }
+            if sections.iter().any(|(existing, ..)| existing == name) {
+                return Err(PassError::DuplicateName(name.to_owned()));
+            }
+            let body = next_line.map_or_else(String::new, |(number, path)| {
+                line_marker(number, Path::new(path))
+            });
+            sections.push((name.to_owned(), Vec::new(), body));
+        } else if let Some(input) = trimmed.strip_prefix(INPUT_MARKER) {
+            let (_, inputs, _) = sections
+                .last_mut()
+                .ok_or(PassError::InputOutsidePass { line: line_number })?;
+            inputs.push(input.trim().to_owned());
+        } else {
+            // plain code goes in the current pass, or the shared prelude if there isn't one yet
+            let body = match sections.last_mut() {
+                Some((_, _, body)) => body,
+                None => &mut prelude,
+            };
+            body.push_str(line);
+            body.push('\n');
+        }
+    }
+
+    if sections.is_empty() {
+        // no markers, so the whole file is one pass
+        return Ok(vec![PassSource {
+            name: "main".to_owned(),
+            inputs: Vec::new(),
+            source: prelude,
+            uniforms: true,
+            includes: Vec::new(),
+        }]);
+    }
+
+    let mut passes: Vec<PassSource> = Vec::with_capacity(sections.len());
+    for (name, input_names, body) in sections {
+        let inputs = resolve_inputs(&passes, &name, input_names)?;
+        passes.push(PassSource {
+            name,
+            inputs,
+            source: format!("{}{}", prelude, body),
+            uniforms: true,
+            includes: Vec::new(),
+        });
+    }
+    Ok(passes)
+}
+
+/// Find the earlier passes a pass named `pass` reads, by their names
+///
+/// A name can end in `.n` to read the pass's `[[location(n)]]` rather than its first output.
+pub(super) fn resolve_inputs(
+    passes: &[PassSource],
+    pass: &str,
+    input_names: Vec<String>,
+) -> Result<Vec<PassInput>, PassError> {
+    if input_names.len() > MAX_INPUTS {
+        return Err(PassError::TooManyInputs {
+            pass: pass.to_owned(),
+        });
+    }
+    input_names
+        .into_iter()
+        .map(|input| {
+            // only earlier passes have finished rendering by the time this one runs
+            // a pass named with a `.` in it takes priority over a location of another pass
+            let position = |name: &str| passes.iter().position(|pass| pass.name == name);
+            position(&input)
+                .map(PassInput::output)
+                .or_else(|| {
+                    let (name, location) = input_location(&input)?;
+                    Some(PassInput {
+                        pass: position(name)?,
+                        location,
+                    })
+                })
+                .ok_or_else(|| PassError::UnknownInput {
+                    pass: pass.to_owned(),
+                    input,
+                })
+        })
+        .collect()
+}
+
+// number of mip levels needed to shrink a texture down to 1x1
+pub(super) fn mip_level_count(width: u32, height: u32) -> u32 {
+    32 - width.max(height).leading_zeros()
+}
+
+/// Offscreen texture that an intermediate pass renders into
+#[derive(Debug)]
+pub(super) struct PassTarget {
+    // whole texture, including any mip levels, for sampling
+    view: TextureView,
+    // each mip level on its own
+    // the pass renders into level 0, and the rest are generated from it
+    mip_views: Vec<TextureView>,
+}
+
+impl PassTarget {
+    pub(super) fn new(
+        device: &Device,
+        label: &str,
+        width: u32,
+        height: u32,
+        mipmaps: bool,
+    ) -> Self {
+        let mip_level_count = if mipmaps {
+            mip_level_count(width, height)
+        } else {
+            1
+        };
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some(label),
+            size: Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: PASS_TARGET_FORMAT,
+            // rendered to by its own pass, then sampled by later ones
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+        });
+        let view = texture.create_view(&TextureViewDescriptor::default());
+        let mip_views = (0..mip_level_count)
+            .map(|level| {
+                texture.create_view(&TextureViewDescriptor {
+                    base_mip_level: level,
+                    mip_level_count: NonZeroU32::new(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        Self { view, mip_views }
+    }
+
+    pub(super) fn view(&self) -> &TextureView {
+        &self.view
+    }
+
+    // where the pass draws to
+    pub(super) fn render_view(&self) -> &TextureView {
+        &self.mip_views[0]
+    }
+
+    pub(super) fn mip_views(&self) -> &[TextureView] {
+        &self.mip_views
+    }
+}
+
+// every binding in the input bind group, for this many inputs and channels
+pub(super) fn input_bindings(input_count: usize, channel_count: usize) -> Vec<u32> {
+    let inputs = 0..input_count as u32;
+    let channels = (0..channel_count as u32).map(|channel| CHANNEL_BINDING / 2 + channel);
+    inputs
+        .chain(channels)
+        .flat_map(|index| [2 * index, 2 * index + 1])
+        .collect()
+}
+
+/// Layout for the bind group holding a pass's inputs and texture channels,
+/// as texture/sampler pairs
+pub(super) fn input_bind_group_layout(
+    device: &Device,
+    input_count: usize,
+    channels: &[Channel],
+) -> BindGroupLayout {
+    device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+        label: Some("Pass Input Bind Group Layout"),
+        entries: &input_layout_entries(input_count, channels),
+    })
+}
+
+// the entries of input_bind_group_layout, kept to describe the layout with
+pub(super) fn input_layout_entries(
+    input_count: usize,
+    channels: &[Channel],
+) -> Vec<BindGroupLayoutEntry> {
+    let inputs = (0..input_count as u32).map(|index| (index, TextureViewDimension::D2));
+    let channels = (0..)
+        .zip(channels)
+        .map(|(channel, texture)| (CHANNEL_BINDING / 2 + channel, texture.dimension()));
+    inputs
+        .chain(channels)
+        .flat_map(|(index, view_dimension)| {
+            [
+                BindGroupLayoutEntry {
+                    binding: 2 * index,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 2 * index + 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ]
+        })
+        .collect()
+}
+
+// what's at a binding in the input bind group, like `input 0` or `channel 1 sampler`
+pub(super) fn input_binding_name(binding: u32) -> String {
+    let index = binding / 2;
+    let name = if index < CHANNEL_BINDING / 2 {
+        format!("input {}", index)
+    } else {
+        format!("channel {}", index - CHANNEL_BINDING / 2)
+    };
+    if binding % 2 == 1 {
+        name + " sampler"
+    } else {
+        name
+    }
+}
+
+/// Bind the given texture views as inputs, and the channels after them,
+/// matching [`input_bind_group_layout`]
+pub(super) fn input_bind_group(
+    device: &Device,
+    layout: &BindGroupLayout,
+    sampler: &Sampler,
+    inputs: &[&TextureView],
+    channels: &[Channel],
+) -> BindGroup {
+    let inputs = inputs
+        .iter()
+        .zip(0_u32..)
+        .map(|(&input, index)| (2 * index, input, sampler));
+    let channels = channels.iter().zip(0_u32..).map(|(channel, index)| {
+        (
+            CHANNEL_BINDING + 2 * index,
+            channel.view(),
+            channel.sampler(),
+        )
+    });
+    let entries: Vec<_> = inputs
+        .chain(channels)
+        .flat_map(|(binding, view, sampler)| {
+            [
+                BindGroupEntry {
+                    binding,
+                    resource: BindingResource::TextureView(view),
+                },
+                BindGroupEntry {
+                    binding: binding + 1,
+                    resource: BindingResource::Sampler(sampler),
+                },
+            ]
+        })
+        .collect();
+    device.create_bind_group(&BindGroupDescriptor {
+        label: Some("Pass Input Bind Group"),
+        layout,
+        entries: &entries,
+    })
+}
+
+// a depth buffer for the shader's passes to test against, with --depth
+// cleared at the start of each pass, so only one pass's geometry is tested at a time
+// with --msaa, it has as many samples as the colours it's drawn with
+pub(super) fn depth_view(device: &Device, width: u32, height: u32, samples: u32) -> TextureView {
+    device
+        .create_texture(&TextureDescriptor {
+            label: Some("Depth Buffer"),
+            size: Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: samples,
+            dimension: TextureDimension::D2,
+            format: DEPTH_FORMAT,
+            usage: TextureUsages::RENDER_ATTACHMENT,
+        })
+        .create_view(&TextureViewDescriptor::default())
+}
+
+/// Multisampled colour attachments for one of the shader's passes, with --msaa
+///
+/// The pass draws into these, and they're resolved into its usual targets at the end of its
+/// render pass, so later passes and the screen only ever see the resolved colours.
+#[derive(Debug)]
+pub(super) struct Multisampled {
+    // one per output, `[[location(0)]]` first
+    views: Vec<TextureView>,
+    // of `[[location(0)]]`, the rest being PASS_TARGET_FORMAT like their targets
+    format: TextureFormat,
+    samples: u32,
+}
+
+impl Multisampled {
+    pub(super) fn new(
+        device: &Device,
+        format: TextureFormat,
+        outputs: u32,
+        (width, height): (u32, u32),
+        samples: u32,
+    ) -> Self {
+        let mut multisampled = Self {
+            views: Vec::new(),
+            format,
+            samples,
+        };
+        multisampled.views = (0..outputs)
+            .map(|location| multisampled.view_for(device, location, width, height))
+            .collect();
+        multisampled
+    }
+
+    // after the pass's targets are resized, since a resolve target has to match in size
+    pub(super) fn resize(&mut self, device: &Device, width: u32, height: u32) {
+        self.views = (0..self.views.len() as u32)
+            .map(|location| self.view_for(device, location, width, height))
+            .collect();
+    }
+
+    // what the pass draws `[[location(n)]]` into
+    pub(super) fn view(&self, location: usize) -> &TextureView {
+        &self.views[location]
+    }
+
+    fn view_for(&self, device: &Device, location: u32, width: u32, height: u32) -> TextureView {
+        device
+            .create_texture(&TextureDescriptor {
+                label: Some("Multisampled Target"),
+                size: Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: self.samples,
+                dimension: TextureDimension::D2,
+                format: if location == 0 {
+                    self.format
+                } else {
+                    PASS_TARGET_FORMAT
+                },
+                // only ever resolved, never sampled
+                usage: TextureUsages::RENDER_ATTACHMENT,
+            })
+            .create_view(&TextureViewDescriptor::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_without_markers_is_one_pass() {
+        let passes = split_passes("fn fs_main() {}\n").expect("it splits");
+        assert_eq!(passes.len(), 1);
+        assert_eq!(passes[0].name, "main");
+        assert_eq!(passes[0].source, "fn fs_main() {}\n");
+        assert!(passes[0].inputs.is_empty());
+    }
+
+    #[test]
+    fn passes_share_the_prelude_and_read_earlier_outputs() {
+        let source = "\
+            struct Shared {};\n\
+            //!pass buffer_a\n\
+            fn a() {}\n\
+            //!pass main\n\
+            //!input buffer_a\n\
+            //!input buffer_a.1\n\
+            fn b() {}\n";
+        let passes = split_passes(source).expect("it splits");
+        let names: Vec<_> = passes.iter().map(|pass| pass.name.as_str()).collect();
+        assert_eq!(names, ["buffer_a", "main"]);
+        assert_eq!(passes[0].source, "struct Shared {};\nfn a() {}\n");
+        assert_eq!(passes[1].source, "struct Shared {};\nfn b() {}\n");
+        assert_eq!(
+            passes[1].inputs,
+            [
+                PassInput::output(0),
+                PassInput {
+                    pass: 0,
+                    location: 1
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn bad_markers_are_errors() {
+        assert!(matches!(
+            split_passes("//!input a\n"),
+            Err(PassError::InputOutsidePass { line: 1 })
+        ));
+        assert!(matches!(
+            split_passes("\n//!pass\n"),
+            Err(PassError::MissingName { line: 2 })
+        ));
+        assert!(matches!(
+            split_passes("//!pass a\n//!pass a\n"),
+            Err(PassError::DuplicateName(name)) if name == "a"
+        ));
+        // only earlier passes have rendered by the time a pass runs
+        assert!(matches!(
+            split_passes("//!pass a\n//!input b\n//!pass b\n"),
+            Err(PassError::UnknownInput { pass, input }) if pass == "a" && input == "b"
+        ));
+    }
+
+    #[test]
+    fn passes_are_marked_with_their_lines_in_the_file() {
+        let source = format!(
+            "{}fn shared() {{}}\n//!pass main\nfn b() {{}}\n",
+            line_marker(1, Path::new("shader.wgsl"))
+        );
+        let passes = split_passes(&source).expect("it splits");
+        let body = passes[0]
+            .source
+            .split_once("fn shared() {}\n")
+            .expect("the prelude comes first")
+            .1;
+        assert_eq!(
+            body,
+            format!("{}fn b() {{}}\n", line_marker(3, Path::new("shader.wgsl")))
+        );
+    }
+}
@@ -0,0 +1,57 @@
+use wgpu::TextureFormat;
+
+use super::{
+    super::config::Config,
+    passes::{PassInput, PassSource},
+};
+
+// built-in passes that run after the shader's own passes, in this order
+// each one reads the output of the pass before it
+pub(super) fn post_process_passes(
+    config: &Config,
+    surface_format: TextureFormat,
+    first_input: usize,
+) -> Vec<PassSource> {
+    let mut effects = Vec::new();
+    // the shader's output is the running average, or smaller than the window, so it's copied
+    // first to get it to the full size the rest draw at
+    if config.accumulate || render_scaled(config) {
+        let name = if config.accumulate {
+            "accumulate"
+        } else {
+            "upscale"
+        };
+        effects.push((name, include_str!("copy.wgsl").to_owned()));
+    }
+    if config.fxaa {
+        effects.push(("fxaa", include_str!("fxaa.wgsl").to_owned()));
+    }
+    // dither last, because it needs to see the final colours
+    if config.dither {
+        let srgb = surface_format.describe().srgb;
+        effects.push((
+            "dither",
+            format!(
+                "let SRGB_SURFACE: bool = {};\n{}",
+                srgb,
+                include_str!("dither.wgsl")
+            ),
+        ));
+    }
+    effects
+        .into_iter()
+        .enumerate()
+        .map(|(offset, (name, source))| PassSource {
+            name: name.to_owned(),
+            inputs: vec![PassInput::output(first_input + offset)],
+            source,
+            uniforms: false,
+            includes: Vec::new(),
+        })
+        .collect()
+}
+
+// whether the shader's passes might render at a --scale smaller than the window
+pub(super) fn render_scaled(config: &Config) -> bool {
+    config.scale < 1.0 || config.adaptive_scale.is_some()
+}
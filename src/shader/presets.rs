@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use super::super::config::Config;
+
+/// A named set of custom uniform values
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(super) struct Preset {
+    name: String,
+    // every custom uniform slot in order, named or not
+    values: Vec<f32>,
+}
+
+// the sidecar file's layout, an array of tables so presets keep their order
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct PresetFile {
+    #[serde(default)]
+    preset: Vec<Preset>,
+}
+
+/// A shader's presets, kept in a TOML file beside it, for recalling with number keys or
+/// --preset
+///
+/// The file is `<shader>.presets.toml`, or `<example>.presets.toml` in the working directory
+/// for a built-in example, and holds a `[[preset]]` table per preset with its `name` and
+/// `values`, one per custom uniform slot.
+#[derive(Debug)]
+pub(super) struct PresetBank {
+    path: PathBuf,
+    presets: Vec<Preset>,
+}
+
+impl PresetBank {
+    // the shader's presets, or none if it doesn't have a file yet
+    // errors if the file's there but can't be read
+    pub(super) fn load(config: &Config) -> Result<Self, String> {
+        let path = match &config.example {
+            Some(example) => PathBuf::from(format!("{}.presets.toml", example)),
+            None => PathBuf::from(format!("{}.presets.toml", config.path)),
+        };
+        let presets = match fs::read_to_string(&path) {
+            Ok(text) => {
+                let file: PresetFile = toml::from_str(&text).map_err(|error| {
+                    format!("Failed parsing presets {}: {}", path.display(), error)
+                })?;
+                file.preset
+            }
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(error) => {
+                return Err(format!(
+                    "Failed reading presets {}: {}",
+                    path.display(),
+                    error
+                ))
+            }
+        };
+        Ok(Self { path, presets })
+    }
+
+    pub(super) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // the preset for a number key, counting from 0 in the file's order
+    pub(super) fn slot(&self, slot: usize) -> Option<&Preset> {
+        self.presets.get(slot)
+    }
+
+    pub(super) fn find(&self, name: &str) -> Option<&Preset> {
+        self.presets.iter().find(|preset| preset.name == name)
+    }
+
+    // overwrite a slot's values, keeping its name, or add a preset named after its number key
+    // on the end if there aren't that many yet, then write the file
+    // returns the preset's name
+    pub(super) fn save(&mut self, slot: usize, values: Vec<f32>) -> io::Result<&str> {
+        let index = match self.presets.get_mut(slot) {
+            Some(preset) => {
+                preset.values = values;
+                slot
+            }
+            None => {
+                self.presets.push(Preset {
+                    name: (self.presets.len() + 1).to_string(),
+                    values,
+                });
+                self.presets.len() - 1
+            }
+        };
+        let file = PresetFile {
+            preset: self.presets.clone(),
+        };
+        let text = toml::to_string(&file).expect("Presets should serialise as TOML");
+        fs::write(&self.path, text)?;
+        Ok(&self.presets[index].name)
+    }
+}
+
+impl Preset {
+    pub(super) fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(super) fn values(&self) -> &[f32] {
+        &self.values
+    }
+}
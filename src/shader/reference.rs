@@ -0,0 +1,71 @@
+use wgpu::*;
+use winit::dpi::PhysicalSize;
+
+use super::{background::Background, geometry::Mesh, textures::TextureFile};
+
+/// A --reference image to compare the shader against, shown to the right of a split
+/// It's stretched over the window the same way as --background-image
+#[derive(Debug)]
+pub(super) struct Reference {
+    image: Background,
+    // for the surface's format, as it's drawn in the last pass
+    pipeline: RenderPipeline,
+    comparing: bool,
+    // 0..1 across the window, where the shader stops and the reference starts
+    split: f32,
+}
+
+impl Reference {
+    pub(super) fn new(
+        device: &Device,
+        queue: &Queue,
+        file: &TextureFile,
+        format: TextureFormat,
+    ) -> Self {
+        let image = Background::new(device, queue, file);
+        let pipeline = image.pipeline(device, format);
+        Self {
+            image,
+            pipeline,
+            comparing: false,
+            split: 0.5,
+        }
+    }
+
+    // the pipeline is made for one target format
+    pub(super) fn set_format(&mut self, device: &Device, format: TextureFormat) {
+        self.pipeline = self.image.pipeline(device, format);
+    }
+
+    pub(super) fn toggle(&mut self) {
+        self.comparing = !self.comparing;
+    }
+
+    pub(super) fn comparing(&self) -> bool {
+        self.comparing
+    }
+
+    // 0..1 across the window
+    pub(super) fn set_split(&mut self, split: f32) {
+        self.split = split.clamp(0.0, 1.0);
+    }
+
+    // draw into a render pass, covering whatever the pass drew right of the split
+    pub(super) fn draw<'pass>(
+        &'pass self,
+        render_pass: &mut RenderPass<'pass>,
+        quad: &'pass Mesh,
+        size: PhysicalSize<u32>,
+    ) {
+        if !self.comparing {
+            return;
+        }
+        let left = (self.split * size.width as f32) as u32;
+        if left >= size.width || size.height == 0 {
+            return;
+        }
+        render_pass.set_scissor_rect(left, 0, size.width - left, size.height);
+        self.image.draw(render_pass, &self.pipeline, quad);
+        render_pass.set_scissor_rect(0, 0, size.width, size.height);
+    }
+}
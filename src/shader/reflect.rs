@@ -0,0 +1,308 @@
+use naga::{front::wgsl, Binding, Module, ShaderStage, StorageClass, TypeInner};
+use std::collections::HashMap;
+use wgpu::{
+    BindGroupLayoutEntry, BindingType, BufferBindingType, ShaderStages, TextureSampleType,
+    TextureViewDimension,
+};
+
+/// A uniform buffer bound by the program, to check against what the shader declares
+#[derive(Debug, Clone)]
+pub(super) struct HostUniform {
+    pub(super) group: u32,
+    pub(super) binding: u32,
+    pub(super) name: String,
+    // of the Rust struct, before any padding to fit the shader
+    pub(super) size: u64,
+    // where each of the Rust struct's fields starts, leaving out its padding
+    pub(super) offsets: &'static [u32],
+}
+
+/// A uniform buffer declared by the shader, laid out by WGSL's rules
+#[derive(Debug)]
+pub(super) struct DeclaredUniform {
+    pub(super) name: String,
+    pub(super) size: u64,
+    // each member's name and offset, if it's a struct
+    pub(super) members: Vec<(String, u32)>,
+}
+
+// the uniform buffers a module declares, by group and binding
+fn module_uniforms(module: &Module) -> HashMap<(u32, u32), DeclaredUniform> {
+    module
+        .global_variables
+        .iter()
+        .filter(|(_, global)| global.class == StorageClass::Uniform)
+        .filter_map(|(_, global)| {
+            let binding = global.binding.as_ref()?;
+            let ty = &module.types[global.ty];
+            let members = match &ty.inner {
+                TypeInner::Struct { members, .. } => members
+                    .iter()
+                    .map(|member| {
+                        let name = member.name.clone().unwrap_or_else(|| "_".to_owned());
+                        (name, member.offset)
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            };
+            let uniform = DeclaredUniform {
+                name: global.name.clone().unwrap_or_else(|| "_".to_owned()),
+                size: ty.inner.span(&module.constants) as u64,
+                members,
+            };
+            Some(((binding.group, binding.binding), uniform))
+        })
+        .collect()
+}
+
+// the uniform buffers a pass's source declares, or none if it doesn't parse
+// parse errors are left for creating the shader module to report
+pub(super) fn declared_uniforms(source: &str) -> HashMap<(u32, u32), DeclaredUniform> {
+    wgsl::parse_str(source)
+        .map(|module| module_uniforms(&module))
+        .unwrap_or_default()
+}
+
+// naga's description of why the pass's source doesn't parse, pointing at the line, or None
+// if it does
+pub(super) fn parse_error(source: &str) -> Option<String> {
+    wgsl::parse_str(source)
+        .err()
+        .map(|error| error.emit_to_string(source))
+}
+
+// every group and binding the pass's source declares a resource at, in order
+// or none if it doesn't parse, as parse errors are left for creating the shader module
+pub(super) fn declared_bindings(source: &str) -> Option<Vec<(u32, u32)>> {
+    let module = wgsl::parse_str(source).ok()?;
+    let mut bindings: Vec<_> = module
+        .global_variables
+        .iter()
+        .filter_map(|(_, global)| global.binding.as_ref())
+        .map(|binding| (binding.group, binding.binding))
+        .collect();
+    bindings.sort_unstable();
+    Some(bindings)
+}
+
+// how many colour targets the pass's `fragment` entry point writes, one past its highest
+// `[[location(n)]]`, or none if it doesn't parse or has no such entry point
+pub(super) fn fragment_outputs(source: &str, fragment: &str) -> Option<u32> {
+    let module = wgsl::parse_str(source).ok()?;
+    let entry_point = module
+        .entry_points
+        .iter()
+        .find(|entry| entry.stage == ShaderStage::Fragment && entry.name == fragment)?;
+    let result = entry_point.function.result.as_ref()?;
+    let locations = match (&result.binding, &module.types[result.ty].inner) {
+        (Some(binding), _) => vec![binding],
+        // a struct of outputs, each with its own location
+        (None, TypeInner::Struct { members, .. }) => members
+            .iter()
+            .filter_map(|member| member.binding.as_ref())
+            .collect(),
+        (None, _) => Vec::new(),
+    };
+    locations
+        .into_iter()
+        .filter_map(|binding| match binding {
+            Binding::Location { location, .. } => Some(location + 1),
+            Binding::BuiltIn(_) => None,
+        })
+        .max()
+}
+
+// what's wrong if the source doesn't declare the vertex and fragment entry points a pipeline
+// is built from, listing the ones it does declare, or None if they're there
+// also None if it doesn't parse, as parse errors are left for creating the shader module
+pub(super) fn missing_entry_point(source: &str, vertex: &str, fragment: &str) -> Option<String> {
+    missing_entry_points(
+        source,
+        &[
+            (ShaderStage::Vertex, vertex),
+            (ShaderStage::Fragment, fragment),
+        ],
+    )
+}
+
+// the same for the compute entry point of a --compute shader
+pub(super) fn missing_compute_entry_point(source: &str, compute: &str) -> Option<String> {
+    missing_entry_points(source, &[(ShaderStage::Compute, compute)])
+}
+
+// the `workgroup_size` the --compute shader's entry point declares, as X, Y and Z, or None if
+// it doesn't parse or has no such entry point
+pub(super) fn workgroup_size(source: &str, compute: &str) -> Option<[u32; 3]> {
+    let module = wgsl::parse_str(source).ok()?;
+    module
+        .entry_points
+        .iter()
+        .find(|entry| entry.stage == ShaderStage::Compute && entry.name == compute)
+        .map(|entry| entry.workgroup_size)
+}
+
+fn missing_entry_points(source: &str, entry_points: &[(ShaderStage, &str)]) -> Option<String> {
+    let module = wgsl::parse_str(source).ok()?;
+    let &(stage, name) = entry_points.iter().find(|&&(stage, name)| {
+        !module
+            .entry_points
+            .iter()
+            .any(|entry| entry.stage == stage && entry.name == name)
+    })?;
+    let stage_name = |stage| match stage {
+        ShaderStage::Vertex => "vertex",
+        ShaderStage::Fragment => "fragment",
+        ShaderStage::Compute => "compute",
+    };
+    let declared: Vec<_> = module
+        .entry_points
+        .iter()
+        .map(|entry| format!("`{}` ({})", entry.name, stage_name(entry.stage)))
+        .collect();
+    let declared = if declared.is_empty() {
+        "none".to_owned()
+    } else {
+        declared.join(", ")
+    };
+    Some(format!(
+        "needs a {} entry point called `{}`, but the entry points it declares are: {}",
+        stage_name(stage),
+        name,
+        declared
+    ))
+}
+
+// host structs are padded to a multiple of this, which WGSL structs don't need to be
+const HOST_PADDING: u64 = 16;
+
+// warn about every uniform the pass declares with a different layout than the program binds
+// the host structs are `#[repr(C)]` copies of the WGSL ones, so a different size or a member
+// starting somewhere else means one of them has changed without the other, and fields past
+// the change will be misread
+// members are compared by position, as the shader can name them however it likes
+pub(super) fn check_uniform_layouts(pass: &str, source: &str, host: &[HostUniform]) {
+    let declared = declared_uniforms(source);
+    for uniform in host {
+        let shader = match declared.get(&(uniform.group, uniform.binding)) {
+            Some(shader) => shader,
+            None => continue,
+        };
+        let same_size = shader.size.div_ceil(HOST_PADDING) * HOST_PADDING == uniform.size;
+        let same_offsets = shader
+            .members
+            .iter()
+            .zip(uniform.offsets)
+            .all(|((_, offset), host_offset)| offset == host_offset);
+        if same_size && same_offsets {
+            continue;
+        }
+        let members: Vec<_> = shader
+            .members
+            .iter()
+            .map(|(name, offset)| format!("{} at {}", name, offset))
+            .collect();
+        let host_offsets: Vec<_> = uniform
+            .offsets
+            .iter()
+            .map(|offset| offset.to_string())
+            .collect();
+        log::warn!(
+            "Pass `{}` declares `{}` at group({}) binding({}) with {} bytes ({}), \
+            but the {} uniform has {} bytes with fields at {}, so its fields may be misread",
+            pass,
+            shader.name,
+            uniform.group,
+            uniform.binding,
+            shader.size,
+            members.join(", "),
+            uniform.name,
+            uniform.size,
+            host_offsets.join(", ")
+        );
+    }
+}
+
+// the type a shader declares to match a binding, like `var<uniform>` or `texture_2d<f32>`
+fn binding_declaration(ty: &BindingType) -> String {
+    match ty {
+        BindingType::Buffer { ty, .. } => match ty {
+            BufferBindingType::Uniform => "var<uniform>".to_owned(),
+            BufferBindingType::Storage { read_only: true } => "var<storage, read>".to_owned(),
+            BufferBindingType::Storage { read_only: false } => {
+                "var<storage, read_write>".to_owned()
+            }
+        },
+        BindingType::Sampler(_) => "sampler".to_owned(),
+        BindingType::Texture {
+            sample_type,
+            view_dimension,
+            multisampled,
+        } => {
+            let dimension = match view_dimension {
+                TextureViewDimension::D1 => "1d",
+                TextureViewDimension::D2 => "2d",
+                TextureViewDimension::D2Array => "2d_array",
+                TextureViewDimension::Cube => "cube",
+                TextureViewDimension::CubeArray => "cube_array",
+                TextureViewDimension::D3 => "3d",
+            };
+            let sample = match sample_type {
+                TextureSampleType::Float { .. } => "f32",
+                TextureSampleType::Sint => "i32",
+                TextureSampleType::Uint => "u32",
+                TextureSampleType::Depth => return format!("texture_depth_{}", dimension),
+            };
+            let multisampled = if *multisampled { "multisampled_" } else { "" };
+            format!("texture_{}{}<{}>", multisampled, dimension, sample)
+        }
+        BindingType::StorageTexture { .. } => "texture_storage".to_owned(),
+    }
+}
+
+// the stages that can see a binding, like `vertex, fragment`
+fn stage_names(visibility: ShaderStages) -> String {
+    let stages: Vec<_> = [
+        (ShaderStages::VERTEX, "vertex"),
+        (ShaderStages::FRAGMENT, "fragment"),
+        (ShaderStages::COMPUTE, "compute"),
+    ]
+    .into_iter()
+    .filter(|&(stage, _)| visibility.contains(stage))
+    .map(|(_, name)| name)
+    .collect();
+    stages.join(", ")
+}
+
+// a pass's pipeline layout, one line per binding with what's bound, its type and visibility,
+// to compare against the shader's declarations
+// the groups are in order from group(0), each binding named for what's bound there
+pub(super) fn describe_layout(
+    pass: &str,
+    groups: &[Vec<(String, BindGroupLayoutEntry)>],
+) -> String {
+    let mut description = format!("Pass `{}`:", pass);
+    if groups.is_empty() {
+        description.push_str("\n  no bind groups");
+    }
+    for (group, entries) in (0..).zip(groups) {
+        for (name, entry) in entries {
+            let dynamic = match entry.ty {
+                BindingType::Buffer {
+                    has_dynamic_offset: true,
+                    ..
+                } => " with a dynamic offset",
+                _ => "",
+            };
+            description.push_str(&format!(
+                "\n  [[group({}), binding({})]] {}: {}{}, visible to {}",
+                group,
+                entry.binding,
+                name,
+                binding_declaration(&entry.ty),
+                dynamic,
+                stage_names(entry.visibility)
+            ));
+        }
+    }
+    description
+}
@@ -0,0 +1,96 @@
+use std::time::{Duration, Instant};
+use winit::dpi::PhysicalSize;
+
+// how often the scale gets reconsidered, so each change has time to show in the frame times
+const INTERVAL: Duration = Duration::from_millis(500);
+// frames needed before judging a scale, so a couple of slow ones after a change don't count
+const MIN_FRAMES: usize = 5;
+// frame times under this fraction of the target leave room to raise the scale
+const HEADROOM: f64 = 0.8;
+// most the scale goes up by at once, so it creeps back up instead of overshooting
+const MAX_RAISE: f32 = 1.1;
+// smaller changes aren't worth rebuilding the pass targets for
+const MIN_CHANGE: f32 = 0.02;
+
+/// The --scale the shader renders at, lowered and raised to keep frame times near
+/// --adaptive-scale's target
+#[derive(Debug)]
+pub(super) struct AdaptiveScale {
+    // in seconds
+    target: f64,
+    min: f32,
+    max: f32,
+    scale: f32,
+    // seconds each frame took since the scale last changed
+    frame_times: Vec<f64>,
+    last_change: Instant,
+}
+
+impl AdaptiveScale {
+    // starts at the most, and comes down if the shader can't keep up
+    pub(super) fn new(target: Duration, min: f32, max: f32) -> Self {
+        Self {
+            target: target.as_secs_f64(),
+            min: min.min(max),
+            max,
+            scale: max,
+            frame_times: Vec::new(),
+            last_change: Instant::now(),
+        }
+    }
+
+    // count a frame's time, and return the new scale if it's time to change it
+    pub(super) fn push(&mut self, frame_time: f64) -> Option<f32> {
+        self.frame_times.push(frame_time);
+        if self.last_change.elapsed() < INTERVAL || self.frame_times.len() < MIN_FRAMES {
+            return None;
+        }
+        // the median, so hitches like a shader reload don't count
+        self.frame_times.sort_by(f64::total_cmp);
+        let median = self.frame_times[self.frame_times.len() / 2];
+        self.frame_times.clear();
+        self.last_change = Instant::now();
+        // pixels drawn go with the square of the scale, and the time taken roughly with them
+        let ideal = self.scale * (self.target / median).sqrt() as f32;
+        let scale = if median > self.target {
+            ideal
+        } else if median < self.target * HEADROOM {
+            ideal.min(self.scale * MAX_RAISE)
+        } else {
+            return None;
+        };
+        let scale = scale.clamp(self.min, self.max);
+        if (scale - self.scale).abs() < MIN_CHANGE {
+            return None;
+        }
+        self.scale = scale;
+        Some(scale)
+    }
+}
+
+// the size the shader's passes render at, for the window's size and the render scale
+pub(super) fn scaled_size(size: PhysicalSize<u32>, scale: f32) -> PhysicalSize<u32> {
+    PhysicalSize::new(
+        scale_length(size.width, scale).max(1),
+        scale_length(size.height, scale).max(1),
+    )
+}
+
+// a scissor rectangle in window pixels, as x, y, width and height, moved to the scaled size
+// kept at least a pixel across and inside `scaled`, since scissors can't be empty
+pub(super) fn scaled_rect(
+    rect: (u32, u32, u32, u32),
+    scale: f32,
+    scaled: PhysicalSize<u32>,
+) -> (u32, u32, u32, u32) {
+    let (x, y, width, height) = rect;
+    let left = scale_length(x, scale).min(scaled.width - 1);
+    let top = scale_length(y, scale).min(scaled.height - 1);
+    let right = scale_length(x + width, scale).clamp(left + 1, scaled.width);
+    let bottom = scale_length(y + height, scale).clamp(top + 1, scaled.height);
+    (left, top, right - left, bottom - top)
+}
+
+fn scale_length(length: u32, scale: f32) -> u32 {
+    (length as f32 * scale).round() as u32
+}
@@ -0,0 +1,77 @@
+use rhai::{Engine, EvalAltResult, Scope, AST, FLOAT, INT};
+use std::{cell::RefCell, path::PathBuf, rc::Rc};
+
+use super::uniforms::CustomUniform;
+
+/// A Rhai script run every frame, for automating custom uniforms with --script
+///
+/// Scripts can read `time` in seconds, `frame`, `mouse_x` and `mouse_y` in 0..1,
+/// and call `set_param(slot, value)`, where the slot is a number or a --custom-uniforms name.
+pub(super) struct Script {
+    engine: Engine,
+    ast: AST,
+    // kept between frames, so variables a script declares carry over
+    scope: Scope<'static>,
+    // custom uniform index and its new value, from set_param calls this frame
+    writes: Rc<RefCell<Vec<(usize, f32)>>>,
+}
+
+impl std::fmt::Debug for Script {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Script").finish_non_exhaustive()
+    }
+}
+
+impl Script {
+    // `names` are the custom uniforms' names, in slot order
+    pub(super) fn load(path: &str, names: Vec<String>) -> Result<Self, Box<EvalAltResult>> {
+        let mut engine = Engine::new();
+        let writes = Rc::new(RefCell::new(Vec::new()));
+        {
+            let writes = Rc::clone(&writes);
+            engine.register_fn("set_param", move |index: INT, value: FLOAT| {
+                // negative slots are as out of range as ones past the end
+                let index = usize::try_from(index).unwrap_or(usize::MAX);
+                writes.borrow_mut().push((index, value as f32));
+            });
+        }
+        {
+            let writes = Rc::clone(&writes);
+            engine.register_fn("set_param", move |name: &str, value: FLOAT| {
+                match names.iter().position(|existing| existing == name) {
+                    Some(index) => writes.borrow_mut().push((index, value as f32)),
+                    None => log::warn!("No custom uniform named `{}`", name),
+                }
+            });
+        }
+        let ast = engine.compile_file(PathBuf::from(path))?;
+        log::info!("Loaded script {}", path);
+        Ok(Self {
+            engine,
+            ast,
+            scope: Scope::new(),
+            writes,
+        })
+    }
+
+    // run the script once, and apply what it set
+    pub(super) fn run(
+        &mut self,
+        time: f64,
+        frame: u64,
+        mouse: [f32; 2],
+        uniform: &mut CustomUniform,
+    ) -> Result<(), Box<EvalAltResult>> {
+        self.scope
+            .set_or_push("time", time as FLOAT)
+            .set_or_push("frame", frame as INT)
+            .set_or_push("mouse_x", mouse[0] as FLOAT)
+            .set_or_push("mouse_y", mouse[1] as FLOAT);
+        let result = self.engine.run_ast_with_scope(&mut self.scope, &self.ast);
+        // apply whatever was set before any error
+        for (index, value) in self.writes.borrow_mut().drain(..) {
+            uniform.set(index, value);
+        }
+        result
+    }
+}
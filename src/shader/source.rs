@@ -0,0 +1,88 @@
+use naga::{
+    back::wgsl::{self, WriterFlags},
+    front::{glsl, spv},
+    valid::{Capabilities, ValidationFlags, Validator},
+    Module, ShaderStage,
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::include::resolve_includes;
+
+/// A shader file as WGSL, by its extension
+///
+/// `.wgsl` files, or files without an extension, have their `//!include`s inlined.
+/// `.spv` files are translated from SPIR-V, so they go through the same passes, reflection and
+/// checks as WGSL, though as a single pass without markers or includes.
+/// `.frag` and `.glsl` files are GLSL fragment shaders, translated the same way, with their
+/// `main` renamed to `fragment` and the quad's vertex stages added, which pass the UV to
+/// `layout(location = 0) in vec2 tex_coords;`.
+///
+/// Returns the files inlined into it too, which only WGSL has.
+pub(super) fn read_source(path: &Path, fragment: &str) -> Result<(String, Vec<PathBuf>), String> {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("spv") => {
+            let bytes = fs::read(path).map_err(|error| error.to_string())?;
+            Ok((spirv_to_wgsl(&bytes)?, Vec::new()))
+        }
+        Some("frag" | "glsl") => {
+            let source = fs::read_to_string(path).map_err(|error| error.to_string())?;
+            let mut wgsl = glsl_to_wgsl(&source, fragment)?;
+            wgsl.push_str(include_str!("glsl_vertex.wgsl"));
+            Ok((wgsl, Vec::new()))
+        }
+        Some("vert") => Err(
+            "GLSL vertex shaders aren't supported, as the quad's vertex stage is built in, \
+            so run the fragment shader's .frag instead"
+                .to_owned(),
+        ),
+        Some("wgsl") | None => {
+            let source = fs::read_to_string(path).map_err(|error| error.to_string())?;
+            resolve_includes(&source, path)
+        }
+        Some(extension) => Err(format!(
+            "`.{}` isn't a shader format this can read, expected .wgsl, .spv, .frag or .glsl",
+            extension
+        )),
+    }
+}
+
+fn spirv_to_wgsl(bytes: &[u8]) -> Result<String, String> {
+    let module = spv::parse_u8_slice(bytes, &spv::Options::default())
+        .map_err(|error| format!("invalid SPIR-V: {}", error))?;
+    module_to_wgsl(&module).map_err(|error| format!("invalid SPIR-V: {}", error))
+}
+
+fn glsl_to_wgsl(source: &str, fragment: &str) -> Result<String, String> {
+    let mut module = glsl::Parser::default()
+        .parse(&ShaderStage::Fragment.into(), source)
+        .map_err(|errors| {
+            let messages: Vec<_> = errors
+                .iter()
+                .map(|error| match error.meta.to_range() {
+                    Some(range) => format!(
+                        "line {}: {}",
+                        source[..range.start].lines().count().max(1),
+                        error
+                    ),
+                    None => error.to_string(),
+                })
+                .collect();
+            format!("invalid GLSL:\n{}", messages.join("\n"))
+        })?;
+    for entry_point in &mut module.entry_points {
+        entry_point.name = fragment.to_owned();
+    }
+    module_to_wgsl(&module).map_err(|error| format!("invalid GLSL: {}", error))
+}
+
+// validated first, which the WGSL writer needs the results of
+fn module_to_wgsl(module: &Module) -> Result<String, String> {
+    let info = Validator::new(ValidationFlags::all(), Capabilities::all())
+        .validate(module)
+        .map_err(|error| error.to_string())?;
+    wgsl::write_string(module, &info, WriterFlags::empty())
+        .map_err(|error| format!("couldn't translate to WGSL: {}", error))
+}
@@ -0,0 +1,144 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+// taps further apart than this start a new tempo instead of adding to the last one
+const TAP_TIMEOUT: Duration = Duration::from_secs(2);
+// intervals averaged over, so a tempo settles after a few taps and follows changes after more
+const TAP_INTERVALS: usize = 6;
+// intervals this far off the median, as a fraction of it, are missed or doubled taps
+// and are left out of the average
+const TAP_TOLERANCE: f64 = 0.25;
+
+/// A tempo and where its beats fall in shader time, for --bpm and tapping it in with T
+#[derive(Debug)]
+pub(super) struct Tempo {
+    // None until given with --bpm or tapped in
+    bpm: Option<f64>,
+    // shader time in seconds of a downbeat, where beats are counted from
+    offset: f64,
+    // times of the latest taps since the last timeout, oldest first
+    taps: VecDeque<Instant>,
+    // the shader time the beat was last worked out for, and when, to put taps in shader time
+    last_update: (f64, Instant),
+}
+
+impl Tempo {
+    pub(super) fn new(bpm: Option<f64>, offset: f64) -> Self {
+        Self {
+            bpm,
+            offset,
+            taps: VecDeque::new(),
+            last_update: (0.0, Instant::now()),
+        }
+    }
+
+    // the beat count and how far through the beat it is at a shader time
+    // both 0 without a tempo, and the count stays 0 before the first downbeat
+    pub(super) fn beat(&mut self, time: Duration) -> (u32, f32) {
+        let time = time.as_secs_f64();
+        self.last_update = (time, Instant::now());
+        match self.bpm {
+            Some(bpm) => {
+                let beats = (time - self.offset) * bpm / 60.0;
+                (beats.max(0.0) as u32, beats.rem_euclid(1.0) as f32)
+            }
+            None => (0, 0.0),
+        }
+    }
+
+    // a tap on the beat, which sets the tempo from the last few taps' spacing, ignoring any
+    // intervals that stand out from the rest, like from a missed tap
+    // each tap is a downbeat, so the count restarts from it
+    // returns the new tempo, or None for the first tap, which only starts counting
+    pub(super) fn tap(&mut self) -> Option<f64> {
+        let now = Instant::now();
+        if let Some(&last) = self.taps.back() {
+            if now - last > TAP_TIMEOUT {
+                self.taps.clear();
+            }
+        }
+        if self.taps.len() > TAP_INTERVALS {
+            self.taps.pop_front();
+        }
+        self.taps.push_back(now);
+        let (time, updated) = self.last_update;
+        self.offset = time + (now - updated).as_secs_f64();
+        let interval = tap_interval(&self.taps)?;
+        self.bpm = Some(60.0 / interval);
+        self.bpm
+    }
+}
+
+// seconds between beats, averaged over the taps' intervals that are close to their median
+// or None without any intervals
+fn tap_interval(taps: &VecDeque<Instant>) -> Option<f64> {
+    let mut intervals: Vec<_> = taps
+        .iter()
+        .zip(taps.iter().skip(1))
+        .map(|(&earlier, &later)| (later - earlier).as_secs_f64())
+        .collect();
+    intervals.sort_by(f64::total_cmp);
+    let median = *intervals.get(intervals.len() / 2)?;
+    let kept: Vec<_> = intervals
+        .into_iter()
+        .filter(|interval| (interval - median).abs() <= median * TAP_TOLERANCE)
+        .collect();
+    // the median is always kept, so this is never empty
+    Some(kept.iter().sum::<f64>() / kept.len() as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // taps at these many milliseconds from the first
+    fn taps(millis: &[u64]) -> VecDeque<Instant> {
+        let start = Instant::now();
+        millis
+            .iter()
+            .map(|&millis| start + Duration::from_millis(millis))
+            .collect()
+    }
+
+    fn assert_near(interval: Option<f64>, expected: f64) {
+        let interval = interval.expect("there are intervals");
+        assert!(
+            (interval - expected).abs() < 1e-6,
+            "interval is {}, not {}",
+            interval,
+            expected
+        );
+    }
+
+    #[test]
+    fn even_taps_give_their_interval() {
+        assert_near(tap_interval(&taps(&[0, 500, 1000, 1500, 2000])), 0.5);
+    }
+
+    #[test]
+    fn missed_tap_is_left_out() {
+        // the tap at 1500 was missed, so one interval is doubled
+        assert_near(tap_interval(&taps(&[0, 500, 1000, 2000, 2500, 3000])), 0.5);
+    }
+
+    #[test]
+    fn intervals_near_the_median_are_averaged() {
+        assert_near(
+            tap_interval(&taps(&[0, 480, 1000, 1520])),
+            (0.48 + 0.52 + 0.52) / 3.0,
+        );
+    }
+
+    #[test]
+    fn single_interval_is_the_tempo() {
+        assert_near(tap_interval(&taps(&[0, 400])), 0.4);
+    }
+
+    #[test]
+    fn one_tap_has_no_tempo() {
+        assert_eq!(tap_interval(&taps(&[0])), None);
+        assert_eq!(tap_interval(&taps(&[])), None);
+    }
+}
@@ -0,0 +1,787 @@
+use std::{
+    fs,
+    num::{NonZeroU32, NonZeroU8},
+    path::Path,
+    time::{Duration, Instant, SystemTime},
+};
+use wgpu::*;
+
+use super::super::config::{Filter, TextureInput};
+use super::{mipmaps::MipmapGenerator, passes::mip_level_count};
+
+// KTX2 formats that can be uploaded as they are, mostly block compressed
+const KTX2_FORMATS: &[(ktx2::Format, TextureFormat)] = &[
+    (ktx2::Format::R8G8B8A8_UNORM, TextureFormat::Rgba8Unorm),
+    (ktx2::Format::R8G8B8A8_SRGB, TextureFormat::Rgba8UnormSrgb),
+    (
+        ktx2::Format::BC1_RGBA_UNORM_BLOCK,
+        TextureFormat::Bc1RgbaUnorm,
+    ),
+    (
+        ktx2::Format::BC1_RGBA_SRGB_BLOCK,
+        TextureFormat::Bc1RgbaUnormSrgb,
+    ),
+    (ktx2::Format::BC2_UNORM_BLOCK, TextureFormat::Bc2RgbaUnorm),
+    (
+        ktx2::Format::BC2_SRGB_BLOCK,
+        TextureFormat::Bc2RgbaUnormSrgb,
+    ),
+    (ktx2::Format::BC3_UNORM_BLOCK, TextureFormat::Bc3RgbaUnorm),
+    (
+        ktx2::Format::BC3_SRGB_BLOCK,
+        TextureFormat::Bc3RgbaUnormSrgb,
+    ),
+    (ktx2::Format::BC4_UNORM_BLOCK, TextureFormat::Bc4RUnorm),
+    (ktx2::Format::BC4_SNORM_BLOCK, TextureFormat::Bc4RSnorm),
+    (ktx2::Format::BC5_UNORM_BLOCK, TextureFormat::Bc5RgUnorm),
+    (ktx2::Format::BC5_SNORM_BLOCK, TextureFormat::Bc5RgSnorm),
+    (
+        ktx2::Format::BC6H_UFLOAT_BLOCK,
+        TextureFormat::Bc6hRgbUfloat,
+    ),
+    (
+        ktx2::Format::BC6H_SFLOAT_BLOCK,
+        TextureFormat::Bc6hRgbSfloat,
+    ),
+    (ktx2::Format::BC7_UNORM_BLOCK, TextureFormat::Bc7RgbaUnorm),
+    (
+        ktx2::Format::BC7_SRGB_BLOCK,
+        TextureFormat::Bc7RgbaUnormSrgb,
+    ),
+    (
+        ktx2::Format::ETC2_R8G8B8_UNORM_BLOCK,
+        TextureFormat::Etc2Rgb8Unorm,
+    ),
+    (
+        ktx2::Format::ETC2_R8G8B8_SRGB_BLOCK,
+        TextureFormat::Etc2Rgb8UnormSrgb,
+    ),
+    (
+        ktx2::Format::ETC2_R8G8B8A1_UNORM_BLOCK,
+        TextureFormat::Etc2Rgb8A1Unorm,
+    ),
+    (
+        ktx2::Format::ETC2_R8G8B8A1_SRGB_BLOCK,
+        TextureFormat::Etc2Rgb8A1UnormSrgb,
+    ),
+    (
+        ktx2::Format::ETC2_R8G8B8A8_UNORM_BLOCK,
+        TextureFormat::Etc2Rgba8Unorm,
+    ),
+    (
+        ktx2::Format::ETC2_R8G8B8A8_SRGB_BLOCK,
+        TextureFormat::Etc2Rgba8UnormSrgb,
+    ),
+    (
+        ktx2::Format::EAC_R11_UNORM_BLOCK,
+        TextureFormat::EacR11Unorm,
+    ),
+    (
+        ktx2::Format::EAC_R11_SNORM_BLOCK,
+        TextureFormat::EacR11Snorm,
+    ),
+    (
+        ktx2::Format::EAC_R11G11_UNORM_BLOCK,
+        TextureFormat::EacRg11Unorm,
+    ),
+    (
+        ktx2::Format::EAC_R11G11_SNORM_BLOCK,
+        TextureFormat::EacRg11Snorm,
+    ),
+    (
+        ktx2::Format::ASTC_4x4_UNORM_BLOCK,
+        TextureFormat::Astc4x4RgbaUnorm,
+    ),
+    (
+        ktx2::Format::ASTC_4x4_SRGB_BLOCK,
+        TextureFormat::Astc4x4RgbaUnormSrgb,
+    ),
+    (
+        ktx2::Format::ASTC_5x4_UNORM_BLOCK,
+        TextureFormat::Astc5x4RgbaUnorm,
+    ),
+    (
+        ktx2::Format::ASTC_5x4_SRGB_BLOCK,
+        TextureFormat::Astc5x4RgbaUnormSrgb,
+    ),
+    (
+        ktx2::Format::ASTC_5x5_UNORM_BLOCK,
+        TextureFormat::Astc5x5RgbaUnorm,
+    ),
+    (
+        ktx2::Format::ASTC_5x5_SRGB_BLOCK,
+        TextureFormat::Astc5x5RgbaUnormSrgb,
+    ),
+    (
+        ktx2::Format::ASTC_6x5_UNORM_BLOCK,
+        TextureFormat::Astc6x5RgbaUnorm,
+    ),
+    (
+        ktx2::Format::ASTC_6x5_SRGB_BLOCK,
+        TextureFormat::Astc6x5RgbaUnormSrgb,
+    ),
+    (
+        ktx2::Format::ASTC_6x6_UNORM_BLOCK,
+        TextureFormat::Astc6x6RgbaUnorm,
+    ),
+    (
+        ktx2::Format::ASTC_6x6_SRGB_BLOCK,
+        TextureFormat::Astc6x6RgbaUnormSrgb,
+    ),
+    (
+        ktx2::Format::ASTC_8x5_UNORM_BLOCK,
+        TextureFormat::Astc8x5RgbaUnorm,
+    ),
+    (
+        ktx2::Format::ASTC_8x5_SRGB_BLOCK,
+        TextureFormat::Astc8x5RgbaUnormSrgb,
+    ),
+    (
+        ktx2::Format::ASTC_8x6_UNORM_BLOCK,
+        TextureFormat::Astc8x6RgbaUnorm,
+    ),
+    (
+        ktx2::Format::ASTC_8x6_SRGB_BLOCK,
+        TextureFormat::Astc8x6RgbaUnormSrgb,
+    ),
+    (
+        ktx2::Format::ASTC_8x8_UNORM_BLOCK,
+        TextureFormat::Astc8x8RgbaUnorm,
+    ),
+    (
+        ktx2::Format::ASTC_8x8_SRGB_BLOCK,
+        TextureFormat::Astc8x8RgbaUnormSrgb,
+    ),
+    (
+        ktx2::Format::ASTC_10x5_UNORM_BLOCK,
+        TextureFormat::Astc10x5RgbaUnorm,
+    ),
+    (
+        ktx2::Format::ASTC_10x5_SRGB_BLOCK,
+        TextureFormat::Astc10x5RgbaUnormSrgb,
+    ),
+    (
+        ktx2::Format::ASTC_10x6_UNORM_BLOCK,
+        TextureFormat::Astc10x6RgbaUnorm,
+    ),
+    (
+        ktx2::Format::ASTC_10x6_SRGB_BLOCK,
+        TextureFormat::Astc10x6RgbaUnormSrgb,
+    ),
+    (
+        ktx2::Format::ASTC_10x8_UNORM_BLOCK,
+        TextureFormat::Astc10x8RgbaUnorm,
+    ),
+    (
+        ktx2::Format::ASTC_10x8_SRGB_BLOCK,
+        TextureFormat::Astc10x8RgbaUnormSrgb,
+    ),
+    (
+        ktx2::Format::ASTC_10x10_UNORM_BLOCK,
+        TextureFormat::Astc10x10RgbaUnorm,
+    ),
+    (
+        ktx2::Format::ASTC_10x10_SRGB_BLOCK,
+        TextureFormat::Astc10x10RgbaUnormSrgb,
+    ),
+    (
+        ktx2::Format::ASTC_12x10_UNORM_BLOCK,
+        TextureFormat::Astc12x10RgbaUnorm,
+    ),
+    (
+        ktx2::Format::ASTC_12x10_SRGB_BLOCK,
+        TextureFormat::Astc12x10RgbaUnormSrgb,
+    ),
+    (
+        ktx2::Format::ASTC_12x12_UNORM_BLOCK,
+        TextureFormat::Astc12x12RgbaUnorm,
+    ),
+    (
+        ktx2::Format::ASTC_12x12_SRGB_BLOCK,
+        TextureFormat::Astc12x12RgbaUnormSrgb,
+    ),
+];
+
+/// A `--texture` file read into memory, before there's a device to upload it to
+///
+/// Reading happens first so the device can be asked for any features the format needs,
+/// like texture compression.
+#[derive(Debug)]
+pub(super) struct TextureFile {
+    path: String,
+    filter: Filter,
+    format: TextureFormat,
+    // with a layer per image, for arrays, or a slice per image, for volumes
+    size: Extent3d,
+    // every mip level's data, largest first, with every layer of a level one after another
+    levels: Vec<Vec<u8>>,
+    // D2Array for --texture-array, so it's bound as an array even with only one layer,
+    // D3 for --volume, otherwise D2
+    dimension: TextureViewDimension,
+}
+
+impl TextureFile {
+    // a bad file gives an error rather than panicking, as reloading shouldn't stop the program
+    pub(super) fn try_read(input: &TextureInput) -> Result<Self, String> {
+        log::info!("Loading texture {}", input.path);
+        let (format, size, levels) = read_file(&input.path)?;
+        Ok(Self {
+            path: input.path.clone(),
+            filter: input.filter,
+            format,
+            size,
+            levels,
+            dimension: TextureViewDimension::D2,
+        })
+    }
+
+    // every file in a directory, in name order, as the layers of one texture
+    pub(super) fn read_array(input: &TextureInput) -> Result<Self, String> {
+        log::info!("Loading texture array {}", input.path);
+        Self::read_stack(input, "Texture array")
+    }
+
+    // a directory of same-sized slice images in name order, front to back, or a `.raw` file of
+    // one byte per voxel with its size at the end of its name, like `smoke_128x128x64.raw`,
+    // as a 3D texture
+    pub(super) fn read_volume(input: &TextureInput) -> Result<Self, String> {
+        log::info!("Loading volume {}", input.path);
+        let is_raw = Path::new(&input.path)
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("raw"));
+        if is_raw {
+            let (size, voxels) = read_raw_volume(&input.path)?;
+            return Ok(Self {
+                path: input.path.clone(),
+                filter: input.filter,
+                format: TextureFormat::R8Unorm,
+                size,
+                levels: vec![voxels],
+                dimension: TextureViewDimension::D3,
+            });
+        }
+        let mut volume = Self::read_stack(input, "Volume")?;
+        if volume.format.describe().block_dimensions != (1, 1) {
+            return Err(format!(
+                "Volume {} is block compressed as {:?}, which 3D textures can't be",
+                input.path, volume.format
+            ));
+        }
+        // 3D mip levels shrink in depth too, so the slices' own levels don't fit together
+        volume.levels.truncate(1);
+        // images are decoded as sRGB colours, but slices are densities and the like
+        if volume.format == TextureFormat::Rgba8UnormSrgb {
+            volume.format = TextureFormat::Rgba8Unorm;
+        }
+        volume.dimension = TextureViewDimension::D3;
+        Ok(volume)
+    }
+
+    // every file in a directory, in name order, stacked as the layers of one texture
+    // `kind` names it in errors
+    fn read_stack(input: &TextureInput, kind: &str) -> Result<Self, String> {
+        let mut paths: Vec<_> = fs::read_dir(&input.path)
+            .map_err(|error| format!("Failed reading directory {}: {}", input.path, error))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            // skip hidden files like `.DS_Store`
+            .filter(|path| {
+                path.is_file()
+                    && !path
+                        .file_name()
+                        .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+            })
+            .collect();
+        paths.sort();
+        let layers: Vec<_> = paths
+            .iter()
+            .map(|path| {
+                let path = path.to_string_lossy().into_owned();
+                let (format, size, levels) = read_file(&path)?;
+                Ok((path, format, size, levels))
+            })
+            .collect::<Result<_, String>>()?;
+        let (first_path, format, size, first_levels) = match layers.first() {
+            Some(first) => first,
+            None => return Err(format!("{} {} has no images in it", kind, input.path)),
+        };
+        // every layer has to fit the same texture, so check them all before uploading anything
+        for (path, layer_format, layer_size, levels) in &layers {
+            if layer_format != format || layer_size != size || levels.len() != first_levels.len() {
+                return Err(format!(
+                    "{} {} mixes {} ({}x{} {:?}, {} mip levels) with {} ({}x{} {:?}, {} mip levels)",
+                    kind,
+                    input.path,
+                    first_path,
+                    size.width,
+                    size.height,
+                    format,
+                    first_levels.len(),
+                    path,
+                    layer_size.width,
+                    layer_size.height,
+                    layer_format,
+                    levels.len()
+                ));
+            }
+        }
+        let levels = (0..first_levels.len())
+            .map(|level| {
+                layers
+                    .iter()
+                    .flat_map(|(_, _, _, levels)| levels[level].iter().copied())
+                    .collect()
+            })
+            .collect();
+        Ok(Self {
+            path: input.path.clone(),
+            filter: input.filter,
+            format: *format,
+            size: Extent3d {
+                depth_or_array_layers: layers.len() as u32,
+                ..*size
+            },
+            levels,
+            dimension: TextureViewDimension::D2Array,
+        })
+    }
+
+    // number of images, which only arrays and volumes have more than one of
+    pub(super) fn layers(&self) -> u32 {
+        self.size.depth_or_array_layers
+    }
+
+    pub(super) fn path(&self) -> &str {
+        &self.path
+    }
+
+    // width and height in pixels
+    pub(super) fn dimensions(&self) -> (u32, u32) {
+        (self.size.width, self.size.height)
+    }
+
+    pub(super) fn dimension(&self) -> TextureViewDimension {
+        self.dimension
+    }
+
+    pub(super) fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    // device features needed to sample this file's format, like BC compression
+    pub(super) fn required_features(&self) -> Features {
+        self.format.describe().required_features
+    }
+}
+
+// read any supported image file, by its extension
+fn read_file(path: &str) -> Result<(TextureFormat, Extent3d, Vec<Vec<u8>>), String> {
+    let has_extension = |wanted: &str| {
+        Path::new(path)
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case(wanted))
+    };
+    if has_extension("ktx2") {
+        read_ktx2(path)
+    } else if has_extension("exr") {
+        read_exr(path)
+    } else {
+        read_image(path)
+    }
+}
+
+// read a raw volume's bytes, with the size from the end of its name, as in `smoke_128x128x64.raw`
+fn read_raw_volume(path: &str) -> Result<(Extent3d, Vec<u8>), String> {
+    let stem = Path::new(path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let suffix = stem.rsplit(['_', '.', '-']).next().unwrap_or_default();
+    let lengths: Vec<u32> = suffix
+        .split('x')
+        .map(str::parse)
+        .collect::<Result<_, _>>()
+        .unwrap_or_default();
+    let size = match lengths[..] {
+        [width, height, depth] if width > 0 && height > 0 && depth > 0 => Extent3d {
+            width,
+            height,
+            depth_or_array_layers: depth,
+        },
+        _ => {
+            return Err(format!(
+                "Raw volume {} needs its size at the end of its name, like `smoke_128x128x64.raw`",
+                path
+            ))
+        }
+    };
+    let voxels =
+        fs::read(path).map_err(|error| format!("Failed loading volume {}: {}", path, error))?;
+    let expected = size.width as u64 * size.height as u64 * size.depth_or_array_layers as u64;
+    if voxels.len() as u64 != expected {
+        return Err(format!(
+            "Raw volume {} is {} bytes, but {}x{}x{} needs {}, one per voxel",
+            path,
+            voxels.len(),
+            size.width,
+            size.height,
+            size.depth_or_array_layers,
+            expected
+        ));
+    }
+    Ok((size, voxels))
+}
+
+// decode a PNG, JPEG or similar into plain RGBA
+fn read_image(path: &str) -> Result<(TextureFormat, Extent3d, Vec<Vec<u8>>), String> {
+    let image = image::open(path)
+        .map_err(|error| format!("Failed loading texture {}: {}", path, error))?
+        .into_rgba8();
+    let (width, height) = image.dimensions();
+    let size = Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+    };
+    // image files are almost always sRGB encoded,
+    // so this makes the shader see linear colours
+    Ok((TextureFormat::Rgba8UnormSrgb, size, vec![image.into_raw()]))
+}
+
+// read the first layer of an OpenEXR image as half floats, keeping HDR values above 1
+// 32-bit floats can't be filtered without adapter specific features, so they become halfs too
+fn read_exr(path: &str) -> Result<(TextureFormat, Extent3d, Vec<Vec<u8>>), String> {
+    use exr::prelude::{f16, read_first_rgba_layer_from_file, Vec2};
+
+    // four halfs per pixel
+    const PIXEL_BYTES: usize = 8;
+    let image = read_first_rgba_layer_from_file(
+        path,
+        |resolution: Vec2<usize>, _| {
+            let bytes = vec![0; resolution.width() * resolution.height() * PIXEL_BYTES];
+            (resolution, bytes)
+        },
+        // missing alpha is filled in as 1
+        |(resolution, bytes): &mut (Vec2<usize>, Vec<u8>),
+         position: Vec2<usize>,
+         (r, g, b, a): (f16, f16, f16, f16)| {
+            let start = (position.y() * resolution.width() + position.x()) * PIXEL_BYTES;
+            for (channel, value) in [r, g, b, a].into_iter().enumerate() {
+                let offset = start + channel * 2;
+                bytes[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+            }
+        },
+    )
+    .map_err(|error| format!("Failed loading EXR texture {}: {}", path, error))?;
+    let (resolution, bytes) = image.layer_data.channel_data.pixels;
+    let size = Extent3d {
+        width: resolution.width() as u32,
+        height: resolution.height() as u32,
+        depth_or_array_layers: 1,
+    };
+    // EXR is linear already, so there's no sRGB variant to pick
+    Ok((TextureFormat::Rgba16Float, size, vec![bytes]))
+}
+
+// read a KTX2 container, keeping its mip levels and (usually compressed) format as they are
+fn read_ktx2(path: &str) -> Result<(TextureFormat, Extent3d, Vec<Vec<u8>>), String> {
+    let bytes =
+        fs::read(path).map_err(|error| format!("Failed loading texture {}: {}", path, error))?;
+    let reader = ktx2::Reader::new(&bytes)
+        .map_err(|error| format!("Failed reading KTX2 texture {}: {}", path, error))?;
+    let header = reader.header();
+    if header.supercompression_scheme.is_some() {
+        return Err(format!(
+            "KTX2 texture {} is supercompressed, which isn't supported",
+            path
+        ));
+    }
+    if header.pixel_depth > 1 || header.layer_count > 1 || header.face_count > 1 {
+        return Err(format!(
+            "KTX2 texture {} isn't a single 2D image, which is all that's supported",
+            path
+        ));
+    }
+    let format = header
+        .format
+        .and_then(|format| {
+            KTX2_FORMATS
+                .iter()
+                .find(|&&(ktx2_format, _)| ktx2_format == format)
+        })
+        .map(|&(_, format)| format)
+        .ok_or_else(|| {
+            format!(
+                "KTX2 texture {} has format {:?}, which isn't supported",
+                path, header.format
+            )
+        })?;
+    let size = Extent3d {
+        width: header.pixel_width,
+        height: header.pixel_height.max(1),
+        depth_or_array_layers: 1,
+    };
+    let levels = reader.levels().map(<[u8]>::to_vec).collect();
+    Ok((format, size, levels))
+}
+
+/// How channels' samplers read their textures, apart from their own filters
+#[derive(Clone, Copy, Debug)]
+pub(super) struct Sampling {
+    pub(super) address_mode: AddressMode,
+    // from --anisotropy, None to leave it off
+    pub(super) anisotropy: Option<NonZeroU8>,
+}
+
+/// An image from `--texture`, or a stack of them from `--texture-array` or `--volume`,
+/// uploaded for the shader to sample
+#[derive(Debug)]
+pub(super) struct Channel {
+    view: TextureView,
+    // D2Array for --texture-array, D3 for --volume, otherwise D2
+    dimension: TextureViewDimension,
+    // each channel has its own, for its own filtering
+    sampler: Sampler,
+    // None for textures kept elsewhere, like --video's
+    upload: Option<Upload>,
+}
+
+/// A texture uploaded from a file, kept to rewrite in place when the file changes
+#[derive(Debug)]
+struct Upload {
+    texture: Texture,
+    format: TextureFormat,
+    size: Extent3d,
+    levels: usize,
+}
+
+// how often --texture files are checked for changes
+const TEXTURE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Notices --texture files changing on disk, so an image being edited shows up as it's saved
+#[derive(Debug)]
+pub(super) struct TextureWatcher {
+    // each file's modification time when last checked, or None if it couldn't be found
+    modified: Vec<Option<SystemTime>>,
+    last_check: Instant,
+}
+
+fn modified_time(path: &str) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+impl TextureWatcher {
+    pub(super) fn new(inputs: &[TextureInput]) -> Self {
+        Self {
+            modified: inputs
+                .iter()
+                .map(|input| modified_time(&input.path))
+                .collect(),
+            last_check: Instant::now(),
+        }
+    }
+
+    // indices of the files modified since the last check, checking at most once an interval
+    pub(super) fn changed(&mut self, inputs: &[TextureInput]) -> Vec<usize> {
+        if self.last_check.elapsed() < TEXTURE_CHECK_INTERVAL {
+            return Vec::new();
+        }
+        self.last_check = Instant::now();
+        inputs
+            .iter()
+            .zip(&mut self.modified)
+            .enumerate()
+            .filter_map(|(index, (input, modified))| {
+                let latest = modified_time(&input.path);
+                // a file briefly missing, as when an editor saves by replacing it, isn't a change
+                let changed = latest.is_some() && latest != *modified;
+                *modified = latest.or(*modified);
+                changed.then_some(index)
+            })
+            .collect()
+    }
+}
+
+// upload every mip level of a file into a texture of the same format and size
+fn write_levels(queue: &Queue, texture: &Texture, file: &TextureFile) {
+    let info = file.format.describe();
+    let (block_width, block_height) = info.block_dimensions;
+    for (level, data) in (0..).zip(&file.levels) {
+        // compressed formats are copied in whole blocks, even past the edge of small levels
+        let size = file
+            .size
+            .mip_level_size(level, file.dimension == TextureViewDimension::D3);
+        let physical_size = size.physical_size(file.format);
+        let blocks_wide = physical_size.width / u32::from(block_width);
+        let blocks_high = physical_size.height / u32::from(block_height);
+        queue.write_texture(
+            ImageCopyTexture {
+                texture,
+                mip_level: level,
+                origin: Origin3d::ZERO,
+                aspect: TextureAspect::All,
+            },
+            data,
+            ImageDataLayout {
+                offset: 0,
+                bytes_per_row: NonZeroU32::new(blocks_wide * u32::from(info.block_size)),
+                rows_per_image: NonZeroU32::new(blocks_high),
+            },
+            physical_size,
+        );
+    }
+}
+
+// whether the rest of a file's mip levels can be drawn from its first, for --mipmaps
+// only single images without levels of their own, in a format that's both renderable and
+// filterable
+fn can_generate_mips(file: &TextureFile) -> bool {
+    let info = file.format.describe();
+    let features = info.guaranteed_format_features;
+    file.levels.len() == 1
+        && file.dimension == TextureViewDimension::D2
+        && info.block_dimensions == (1, 1)
+        && features
+            .allowed_usages
+            .contains(TextureUsages::RENDER_ATTACHMENT)
+        && features.filterable
+}
+
+// the first level of a file, and every level after it drawn from that, with --mipmaps
+fn generate_levels(
+    queue: &Queue,
+    device: &Device,
+    texture: &Texture,
+    file: &TextureFile,
+    mipmaps: &MipmapGenerator,
+) {
+    write_levels(queue, texture, file);
+    let levels = mip_level_count(file.size.width, file.size.height);
+    mipmaps.generate_texture(device, queue, texture, file.format, levels);
+}
+
+impl Channel {
+    // with its mip levels generated by `mipmaps` if it has none of its own, for --mipmaps
+    pub(super) fn new(
+        device: &Device,
+        queue: &Queue,
+        file: &TextureFile,
+        sampling: Sampling,
+        mipmaps: Option<&MipmapGenerator>,
+    ) -> Self {
+        let mipmaps = mipmaps.filter(|_| can_generate_mips(file));
+        let (mip_level_count, usage) = match mipmaps {
+            Some(_) => (
+                mip_level_count(file.size.width, file.size.height),
+                // each level is drawn from the one before
+                TextureUsages::TEXTURE_BINDING
+                    | TextureUsages::COPY_DST
+                    | TextureUsages::RENDER_ATTACHMENT,
+            ),
+            None => (
+                file.levels.len() as u32,
+                TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            ),
+        };
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some(&file.path),
+            size: file.size,
+            mip_level_count,
+            sample_count: 1,
+            dimension: match file.dimension {
+                TextureViewDimension::D3 => TextureDimension::D3,
+                _ => TextureDimension::D2,
+            },
+            format: file.format,
+            usage,
+        });
+        match mipmaps {
+            Some(mipmaps) => generate_levels(queue, device, &texture, file, mipmaps),
+            None => write_levels(queue, &texture, file),
+        }
+        let mut channel =
+            Self::with_texture(device, &texture, file.dimension, file.filter, sampling);
+        channel.upload = Some(Upload {
+            texture,
+            format: file.format,
+            size: file.size,
+            levels: file.levels.len(),
+        });
+        channel
+    }
+
+    // upload a changed file, in place if it's still the same shape
+    // returns whether the texture had to be replaced, so bind groups using it need remaking
+    pub(super) fn reload(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        file: &TextureFile,
+        sampling: Sampling,
+        mipmaps: Option<&MipmapGenerator>,
+    ) -> bool {
+        match &self.upload {
+            Some(upload)
+                if upload.format == file.format
+                    && upload.size == file.size
+                    && upload.levels == file.levels.len() =>
+            {
+                match mipmaps.filter(|_| can_generate_mips(file)) {
+                    Some(mipmaps) => generate_levels(queue, device, &upload.texture, file, mipmaps),
+                    None => write_levels(queue, &upload.texture, file),
+                }
+                false
+            }
+            _ => {
+                *self = Self::new(device, queue, file, sampling, mipmaps);
+                true
+            }
+        }
+    }
+
+    // a channel for a texture that's already been made, and maybe gets rewritten, as for --video
+    pub(super) fn with_texture(
+        device: &Device,
+        texture: &Texture,
+        dimension: TextureViewDimension,
+        filter: Filter,
+        sampling: Sampling,
+    ) -> Self {
+        let view = texture.create_view(&TextureViewDescriptor {
+            dimension: Some(dimension),
+            ..Default::default()
+        });
+        let filter = filter.filter_mode();
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("Texture Sampler"),
+            address_mode_u: sampling.address_mode,
+            address_mode_v: sampling.address_mode,
+            address_mode_w: sampling.address_mode,
+            mag_filter: filter,
+            min_filter: filter,
+            mipmap_filter: filter,
+            // anisotropic filtering only goes with linear filtering
+            anisotropy_clamp: sampling.anisotropy.filter(|_| filter == FilterMode::Linear),
+            ..Default::default()
+        });
+        Self {
+            view,
+            dimension,
+            sampler,
+            upload: None,
+        }
+    }
+
+    pub(super) fn view(&self) -> &TextureView {
+        &self.view
+    }
+
+    pub(super) fn dimension(&self) -> TextureViewDimension {
+        self.dimension
+    }
+
+    pub(super) fn sampler(&self) -> &Sampler {
+        &self.sampler
+    }
+}
@@ -1,7 +1,216 @@
 use bytemuck::Pod;
+use std::{mem, num::NonZeroU64};
 use wgpu::{util::DeviceExt, *};
 
-use super::{MouseUniform, TimeUniform};
+use super::{
+    AdapterUniform, AudioUniform, CameraUniform, CustomUniform, DateUniform, DisplayUniform,
+    FrameTimesUniform, KeyboardUniform, LayerUniform, LodUniform, MouseUniform, OverlayUniform,
+    ResolutionUniform, RngUniform, TileUniform, TimeUniform, WindowUniform,
+};
+
+// layout for a bind group holding a single uniform buffer at binding 0
+pub(in crate::shader) fn uniform_bind_group_layout(
+    device: &Device,
+    label: &str,
+) -> BindGroupLayout {
+    uniforms_bind_group_layout(device, label, &[false], None)
+}
+
+/// Which stages write the --storage buffer bound alongside the uniforms
+#[derive(Debug, Clone, Copy)]
+pub(in crate::shader) enum StorageAccess {
+    // the fragment shader reads and writes it
+    Fragment,
+    // the --compute shader writes it, so the fragment shader can only read it
+    ReadOnly,
+    // the --compute shader's own bind group, where it's written
+    Compute,
+}
+
+// layout for a bind group holding several uniform buffers, one per binding from 0,
+// with a dynamic offset for each that's `true` in `dynamic`
+// plus a storage buffer at its binding, if there is one
+pub(in crate::shader) fn uniforms_bind_group_layout(
+    device: &Device,
+    label: &str,
+    dynamic: &[bool],
+    storage: Option<(u32, StorageAccess)>,
+) -> BindGroupLayout {
+    device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+        label: Some(label),
+        entries: &uniforms_layout_entries(dynamic, storage),
+    })
+}
+
+// the entries of uniforms_bind_group_layout, kept to describe the layout with
+// the uniforms are only visible to the compute stage in the --compute shader's group
+pub(in crate::shader) fn uniforms_layout_entries(
+    dynamic: &[bool],
+    storage: Option<(u32, StorageAccess)>,
+) -> Vec<BindGroupLayoutEntry> {
+    let visibility = match storage {
+        Some((_, StorageAccess::Compute)) => ShaderStages::COMPUTE,
+        _ => ShaderStages::VERTEX_FRAGMENT,
+    };
+    let mut entries: Vec<_> = dynamic
+        .iter()
+        .zip(0..)
+        .map(|(&has_dynamic_offset, binding)| BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Uniform,
+                has_dynamic_offset,
+                min_binding_size: None,
+            },
+            count: None,
+        })
+        .collect();
+    if let Some((binding, access)) = storage {
+        let (visibility, read_only) = match access {
+            // writing from vertex shaders needs an extra feature
+            StorageAccess::Fragment => (ShaderStages::FRAGMENT, false),
+            StorageAccess::ReadOnly => (ShaderStages::VERTEX_FRAGMENT, true),
+            StorageAccess::Compute => (ShaderStages::COMPUTE, false),
+        };
+        entries.push(BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        });
+    }
+    entries
+}
+
+// bind each buffer at the binding matching its position, for uniforms_bind_group_layout
+// and the storage buffer at its own binding
+pub(in crate::shader) fn uniforms_bind_group(
+    device: &Device,
+    label: &str,
+    layout: &BindGroupLayout,
+    buffers: Vec<BindingResource>,
+    storage: Option<(u32, &Buffer)>,
+) -> BindGroup {
+    let entries: Vec<_> = buffers
+        .into_iter()
+        .zip(0..)
+        .map(|(resource, binding)| BindGroupEntry { binding, resource })
+        .chain(storage.map(|(binding, buffer)| BindGroupEntry {
+            binding,
+            resource: buffer.as_entire_binding(),
+        }))
+        .collect();
+    device.create_bind_group(&BindGroupDescriptor {
+        label: Some(label),
+        layout,
+        entries: &entries,
+    })
+}
+
+/// A uniform and the buffer it gets written to, sharing a bind group with others
+#[derive(Debug)]
+pub(in crate::shader) struct UniformBuffer<T> {
+    uniform: T,
+    buffer: Buffer,
+    // bytes of the buffer, at least the uniform's size
+    size: u64,
+}
+
+impl<T> UniformBuffer<T> {
+    pub(in crate::shader) fn uniform(&self) -> &T {
+        &self.uniform
+    }
+
+    pub(in crate::shader) fn uniform_mut(&mut self) -> &mut T {
+        &mut self.uniform
+    }
+
+    pub(in crate::shader) fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    pub(in crate::shader) fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+impl<T: Uniform + Pod> UniformBuffer<T> {
+    // replace the buffer with one of at least `min_size` bytes, if it's smaller
+    // the new buffer needs binding again
+    pub(in crate::shader) fn grow(&mut self, device: &Device, min_size: u64) {
+        if min_size > self.size {
+            *self = self.uniform.make_buffer_sized(device, min_size);
+        }
+    }
+}
+
+// frames that can be in flight at once, each with its own region of a UniformRing
+const RING_LENGTH: u64 = 3;
+
+/// A uniform written every frame, with a region of its buffer for each frame in flight
+///
+/// Each write goes to the next region, which gets bound with a dynamic offset,
+/// so writing never waits on the GPU still reading what an earlier frame wrote.
+#[derive(Debug)]
+pub(in crate::shader) struct UniformRing<T> {
+    uniform: T,
+    buffer: Buffer,
+    // bytes from one region to the next, a multiple of the device's offset alignment
+    stride: u64,
+    // bytes bound for the shader, at least the uniform's size
+    size: u64,
+    // region of the latest write
+    current: u64,
+}
+
+impl<T: Pod> UniformRing<T> {
+    pub(in crate::shader) fn uniform(&self) -> &T {
+        &self.uniform
+    }
+
+    pub(in crate::shader) fn uniform_mut(&mut self) -> &mut T {
+        &mut self.uniform
+    }
+
+    // write the uniform into the next region, which the next draw should bind
+    pub(in crate::shader) fn write(&mut self, queue: &Queue) {
+        self.current = (self.current + 1) % RING_LENGTH;
+        queue.write_buffer(
+            &self.buffer,
+            self.current * self.stride,
+            bytemuck::bytes_of(&self.uniform),
+        );
+    }
+
+    // dynamic offset of the latest write
+    pub(in crate::shader) fn offset(&self) -> DynamicOffset {
+        (self.current * self.stride) as DynamicOffset
+    }
+
+    // one region's worth of the buffer, moved along it by the dynamic offset
+    pub(in crate::shader) fn binding(&self) -> BindingResource<'_> {
+        BindingResource::Buffer(BufferBinding {
+            buffer: &self.buffer,
+            offset: 0,
+            size: NonZeroU64::new(self.size),
+        })
+    }
+}
+
+impl<T: Uniform + Pod> UniformRing<T> {
+    // replace the ring with one of regions at least `min_size` bytes, if they're smaller
+    // the new buffer needs binding again
+    pub(in crate::shader) fn grow(&mut self, device: &Device, min_size: u64) {
+        if min_size > self.size {
+            *self = self.uniform.make_ring(device, min_size);
+        }
+    }
+}
 
 #[derive(Debug)]
 pub(in crate::shader) struct UniformBinding<T> {
@@ -28,9 +237,102 @@ impl<T> UniformBinding<T> {
     }
 }
 
+impl CustomUniform {
+    // like Uniform::make_buffer_sized, but sized by the number of slots rather than the type
+    pub(in crate::shader) fn make_buffer(
+        self,
+        device: &Device,
+        min_size: u64,
+    ) -> UniformBuffer<Self> {
+        let buffer = create_uniform_buffer(device, "Custom Buffer", self.as_bytes(), min_size);
+        UniformBuffer {
+            size: min_size.max(self.as_bytes().len() as u64),
+            uniform: self,
+            buffer,
+        }
+    }
+}
+
+// a buffer holding a uniform's bytes, zero-extended to at least `min_size`
+fn create_uniform_buffer(device: &Device, label: &str, bytes: &[u8], min_size: u64) -> Buffer {
+    device.create_buffer_init(&util::BufferInitDescriptor {
+        label: Some(label),
+        contents: &padded(bytes, min_size),
+        usage: BufferUsages::all(),
+    })
+}
+
+// zero-extend a uniform's bytes to at least `min_size`, for when the shader declares it bigger
+// the extra bytes are never written, but binding a buffer smaller than declared is an error
+fn padded(bytes: &[u8], min_size: u64) -> Vec<u8> {
+    let mut bytes = bytes.to_vec();
+    if (bytes.len() as u64) < min_size {
+        bytes.resize(min_size as usize, 0);
+    }
+    bytes
+}
+
+// offsets of a uniform's fields in order, leaving out the padding, which WGSL adds itself
+macro_rules! member_offsets {
+    ($uniform:ty: $($field:ident),*) => {
+        &[$(mem::offset_of!($uniform, $field) as u32),*]
+    };
+}
+
 pub(in crate::shader) trait Uniform {
     const BUFFER_LABEL: &'static str;
     const BIND_GROUP_LABEL: &'static str;
+    // where each field the shader declares starts, to check its layout against
+    const MEMBER_OFFSETS: &'static [u32];
+
+    fn make_buffer(self, device: &Device) -> UniformBuffer<Self>
+    where
+        Self: Sized + Pod,
+    {
+        self.make_buffer_sized(device, 0)
+    }
+
+    // a buffer of at least `min_size` bytes, as the shader may declare the uniform bigger
+    fn make_buffer_sized(self, device: &Device, min_size: u64) -> UniformBuffer<Self>
+    where
+        Self: Sized + Pod,
+    {
+        let buffer = create_uniform_buffer(
+            device,
+            Self::BUFFER_LABEL,
+            bytemuck::bytes_of(&self),
+            min_size,
+        );
+        UniformBuffer::<Self> {
+            uniform: self,
+            buffer,
+            size: min_size.max(mem::size_of::<Self>() as u64),
+        }
+    }
+
+    // a ring of buffer regions, at least `min_size` bytes each, for uniforms written every frame
+    fn make_ring(self, device: &Device, min_size: u64) -> UniformRing<Self>
+    where
+        Self: Sized + Pod,
+    {
+        let size = padded(bytemuck::bytes_of(&self), min_size).len() as u64;
+        let alignment = device.limits().min_uniform_buffer_offset_alignment as u64;
+        let stride = size.div_ceil(alignment) * alignment;
+        // every region starts with the initial value, so it doesn't matter which is bound first
+        let region = padded(bytemuck::bytes_of(&self), stride);
+        let buffer = device.create_buffer_init(&util::BufferInitDescriptor {
+            label: Some(Self::BUFFER_LABEL),
+            contents: &region.repeat(RING_LENGTH as usize),
+            usage: BufferUsages::all(),
+        });
+        UniformRing {
+            uniform: self,
+            buffer,
+            stride,
+            size,
+            current: 0,
+        }
+    }
 
     fn make_binding(
         self,
@@ -40,13 +342,11 @@ pub(in crate::shader) trait Uniform {
     where
         Self: Sized + Pod,
     {
-        let buffer = device.create_buffer_init(&util::BufferInitDescriptor {
-            label: Some("Time Buffer"),
-            contents: bytemuck::cast_slice(&[self]),
-            usage: BufferUsages::all(),
-        });
+        let UniformBuffer {
+            uniform, buffer, ..
+        } = self.make_buffer(device);
         let bind_group = device.create_bind_group(&BindGroupDescriptor {
-            label: Some("Time Bind Group"),
+            label: Some(Self::BIND_GROUP_LABEL),
             layout: bind_group_layout,
             entries: &[BindGroupEntry {
                 binding: 0,
@@ -54,7 +354,7 @@ pub(in crate::shader) trait Uniform {
             }],
         });
         UniformBinding::<Self> {
-            uniform: self,
+            uniform,
             buffer,
             bind_group,
         }
@@ -64,9 +364,97 @@ pub(in crate::shader) trait Uniform {
 impl Uniform for TimeUniform {
     const BIND_GROUP_LABEL: &'static str = "Time Bind Group";
     const BUFFER_LABEL: &'static str = "Time Buffer";
+    const MEMBER_OFFSETS: &'static [u32] = member_offsets!(TimeUniform: time, strobe, seconds, subsecond, just_reloaded, beat_phase, beat, frames_since_reload, samples, frame, delta);
 }
 
 impl Uniform for MouseUniform {
     const BIND_GROUP_LABEL: &'static str = "Mouse Bind Group";
     const BUFFER_LABEL: &'static str = "Mouse Buffer";
+    const MEMBER_OFFSETS: &'static [u32] = member_offsets!(MouseUniform: cursor_pos, physical, logical, idle_time, click_count, button_hold_time, buttons, zoom, path_length, click_pos, hovering);
+}
+
+impl Uniform for CameraUniform {
+    const BIND_GROUP_LABEL: &'static str = "Camera Bind Group";
+    const BUFFER_LABEL: &'static str = "Camera Buffer";
+    const MEMBER_OFFSETS: &'static [u32] = member_offsets!(CameraUniform: view_proj);
+}
+
+impl Uniform for FrameTimesUniform {
+    const BIND_GROUP_LABEL: &'static str = "Frame Times Bind Group";
+    const BUFFER_LABEL: &'static str = "Frame Times Buffer";
+    const MEMBER_OFFSETS: &'static [u32] = member_offsets!(FrameTimesUniform: deltas, newest);
+}
+
+impl Uniform for TileUniform {
+    const BIND_GROUP_LABEL: &'static str = "Tile Bind Group";
+    const BUFFER_LABEL: &'static str = "Tile Buffer";
+    const MEMBER_OFFSETS: &'static [u32] = member_offsets!(TileUniform: cell, grid, index);
+}
+
+impl Uniform for LodUniform {
+    const BIND_GROUP_LABEL: &'static str = "LOD Bind Group";
+    const BUFFER_LABEL: &'static str = "LOD Buffer";
+    const MEMBER_OFFSETS: &'static [u32] = member_offsets!(LodUniform: bias, levels);
+}
+
+impl Uniform for AdapterUniform {
+    const BIND_GROUP_LABEL: &'static str = "Adapter Bind Group";
+    const BUFFER_LABEL: &'static str = "Adapter Buffer";
+    const MEMBER_OFFSETS: &'static [u32] =
+        member_offsets!(AdapterUniform: backend, max_texture_dimension);
+}
+
+impl Uniform for RngUniform {
+    const BIND_GROUP_LABEL: &'static str = "RNG Bind Group";
+    const BUFFER_LABEL: &'static str = "RNG Buffer";
+    const MEMBER_OFFSETS: &'static [u32] = member_offsets!(RngUniform: rng, state);
+}
+
+impl Uniform for DisplayUniform {
+    const BIND_GROUP_LABEL: &'static str = "Display Bind Group";
+    const BUFFER_LABEL: &'static str = "Display Buffer";
+    const MEMBER_OFFSETS: &'static [u32] = member_offsets!(DisplayUniform: refresh_rate);
+}
+
+impl Uniform for WindowUniform {
+    const BIND_GROUP_LABEL: &'static str = "Window Bind Group";
+    const BUFFER_LABEL: &'static str = "Window Buffer";
+    const MEMBER_OFFSETS: &'static [u32] = member_offsets!(WindowUniform: position, desktop_size);
+}
+
+impl Uniform for ResolutionUniform {
+    const BIND_GROUP_LABEL: &'static str = "Resolution Bind Group";
+    const BUFFER_LABEL: &'static str = "Resolution Buffer";
+    const MEMBER_OFFSETS: &'static [u32] = member_offsets!(ResolutionUniform: size, res_px, scale);
+}
+
+impl Uniform for DateUniform {
+    const BIND_GROUP_LABEL: &'static str = "Date Bind Group";
+    const BUFFER_LABEL: &'static str = "Date Buffer";
+    const MEMBER_OFFSETS: &'static [u32] = member_offsets!(DateUniform: ymd, seconds);
+}
+
+impl Uniform for KeyboardUniform {
+    const BIND_GROUP_LABEL: &'static str = "Keyboard Bind Group";
+    const BUFFER_LABEL: &'static str = "Keyboard Buffer";
+    const MEMBER_OFFSETS: &'static [u32] = member_offsets!(KeyboardUniform: pressed);
+}
+
+impl Uniform for AudioUniform {
+    const BIND_GROUP_LABEL: &'static str = "Audio Bind Group";
+    const BUFFER_LABEL: &'static str = "Audio Buffer";
+    const MEMBER_OFFSETS: &'static [u32] = member_offsets!(AudioUniform: spectrum, waveform);
+}
+
+impl Uniform for OverlayUniform {
+    const BIND_GROUP_LABEL: &'static str = "Overlay Bind Group";
+    const BUFFER_LABEL: &'static str = "Overlay Buffer";
+    const MEMBER_OFFSETS: &'static [u32] =
+        member_offsets!(OverlayUniform: resolution, scale_factor, layers);
+}
+
+impl Uniform for LayerUniform {
+    const BIND_GROUP_LABEL: &'static str = "Layer Bind Group";
+    const BUFFER_LABEL: &'static str = "Layer Buffer";
+    const MEMBER_OFFSETS: &'static [u32] = member_offsets!(LayerUniform: opacity, mode);
 }
@@ -1,50 +1,633 @@
 use bytemuck::{Pod, Zeroable};
-use std::time::Instant;
+use cgmath::Matrix4;
+use chrono::{Datelike, Local, Timelike};
+use std::time::{Duration, Instant};
+use wgpu::Backend;
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
 pub(super) struct TimeUniform {
     time: u32,
+    // frame number modulo the strobe period
+    strobe: u32,
+    // whole seconds since program start, only wraps after ~136 years
+    seconds: u32,
+    // fraction of the current second, 0..1
+    subsecond: f32,
+    // 1 for the first frame drawn with a newly loaded shader, otherwise 0
+    just_reloaded: u32,
+    // fraction of the way through the current beat of the tempo, 0..1
+    beat_phase: f32,
+    // whole beats since the tempo's first beat
+    beat: u32,
+    // frames drawn since the shader was loaded, 0 on the first one
+    frames_since_reload: u32,
+    // frames averaged together by --accumulate, 1 on the first after starting over
+    samples: u32,
+    // frames drawn since time last started over, 0 on the first
+    frame: u32,
+    // seconds of time since the last frame, 0 while paused
+    delta: f32,
+    // uniform buffers are padded to 16 bytes
+    _padding: u32,
 }
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
 pub(super) struct MouseUniform {
+    // 0..1 across the window, with y going up like GPU coords
     cursor_pos: [f32; 2],
-    // click_time: [u32; 3],
-    // clicking: [u8; 3],
-    // cursor_over_window: u8,
+    // in physical pixels from the top left, with the fraction kept for subpixel accuracy
+    // the same units as a fragment's `[[builtin(position)]]`
+    physical: [f32; 2],
+    // in logical pixels from the top left, i.e. physical pixels divided by the scale factor
+    // for shaders laid out in points on HiDPI screens
+    logical: [f32; 2],
+    // seconds since the mouse last moved, clicked or scrolled
+    idle_time: f32,
+    // left clicks in quick succession, so 2 for a double click, or 0 once the run is over
+    click_count: u32,
+    // seconds each of the left, right and middle buttons has been held, or 0 if it's up
+    button_hold_time: [f32; 3],
+    // bits 0, 1 and 2 are set while the left, right and middle buttons are down
+    buttons: u32,
+    // multiplied by ZOOM_STEP per line scrolled up, divided per line down, starting at 1
+    zoom: f32,
+    // physical pixels the cursor has travelled inside the window, summed over every move
+    path_length: f32,
+    // where the last left click landed, in the same units as cursor_pos
+    click_pos: [f32; 2],
+    // 1 while the cursor is over the window, 0 once it's left
+    // cursor_pos keeps where it was last seen in the meantime
+    hovering: u32,
+    // uniform buffers are padded to 16 bytes
+    _padding: [u32; 3],
+}
+
+// how much one line of scrolling zooms by
+const ZOOM_STEP: f32 = 1.1;
+// f32 coordinates run out of precision not far past this much zoom
+const MAX_ZOOM: f32 = 1e6;
+const MIN_ZOOM: f32 = 1.0 / MAX_ZOOM;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub(super) struct CameraUniform {
+    // transforms world space into clip space
+    view_proj: [[f32; 4]; 4],
+}
+
+// number of recent frame times kept
+const FRAME_TIME_COUNT: usize = 64;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub(super) struct FrameTimesUniform {
+    // ring buffer of the last 64 frame times in seconds
+    // packed four to a vec4, because uniform arrays have a 16 byte stride
+    // frame time `i` is `deltas[i / 4][i % 4]`
+    deltas: [[f32; 4]; FRAME_TIME_COUNT / 4],
+    // index of the most recent frame time
+    newest: u32,
+    // uniform buffers are padded to 16 bytes
+    _padding: [u32; 3],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub(super) struct TileUniform {
+    // which cell of the --tile grid is being drawn, as column and row
+    cell: [u32; 2],
+    // number of columns and rows in the grid
+    grid: [u32; 2],
+    // cell number, counting along rows from the top left
+    index: u32,
+    // uniform buffers are padded to 16 bytes
+    _padding: [u32; 3],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub(super) struct LodUniform {
+    // from --lod-bias, for textureSampleBias
+    bias: f32,
+    // number of mip levels in pass outputs, 1 without --mipmaps
+    levels: u32,
+    // uniform buffers are padded to 16 bytes
+    _padding: [u32; 2],
+}
+
+/// Floats set by --param and by external inputs like OSC, named by --custom-uniforms
+///
+/// There are --params of them, so unlike the other uniforms its size is only known at runtime.
+#[derive(Clone, Debug)]
+pub(super) struct CustomUniform {
+    // packed four to a vec4, like the frame times
+    values: Vec<[f32; 4]>,
+    count: usize,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub(super) struct AdapterUniform {
+    // graphics API in use, for working around backend quirks
+    // 0 Vulkan, 1 Metal, 2 DX12, 3 OpenGL, 4 DX11, 5 browser WebGPU
+    backend: u32,
+    // widest or tallest 2D texture the GPU can make, in pixels
+    max_texture_dimension: u32,
+    // uniform buffers are padded to 16 bytes
+    _padding: [u32; 2],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub(super) struct RngUniform {
+    // a new random number every frame, the same sequence for the same --seed
+    rng: u32,
+    // PCG state, hashed to make rng
+    state: u32,
+    // uniform buffers are padded to 16 bytes
+    _padding: [u32; 2],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub(super) struct DisplayUniform {
+    // refresh rate of the window's monitor in Hz, 60 if it can't be told, or 0 without a window
+    refresh_rate: f32,
+    // uniform buffers are padded to 16 bytes
+    _padding: [u32; 3],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub(super) struct WindowUniform {
+    // top left of the window's contents on the virtual desktop, in physical pixels
+    // relative to the desktop's top left, so it's never negative
+    position: [f32; 2],
+    // size of the box around every monitor, in physical pixels
+    desktop_size: [f32; 2],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub(super) struct ResolutionUniform {
+    // size the shader renders at in physical pixels, the window's times the --scale
+    size: [f32; 2],
+    // the same, as exact integers for indexing pixel grids
+    res_px: [u32; 2],
+    // --scale, for turning window positions like the mouse's into rendered pixels
+    scale: f32,
+    // uniform buffers are padded to 16 bytes
+    _padding: [u32; 3],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub(super) struct DateUniform {
+    // local year, month from 0 and day of the month from 1, like Shadertoy's iDate
+    ymd: [f32; 3],
+    // local seconds since midnight, with the fraction for smooth second hands
+    seconds: f32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub(super) struct KeyboardUniform {
+    // bit `k % 32` of word `k / 32` is set while the key with winit key code `k` is held
+    // packed four words to a vec4, because uniform arrays have a 16 byte stride
+    pressed: [[u32; 4]; KEYBOARD_WORDS / 4],
+}
+
+// enough bits for every winit key code
+const KEYBOARD_WORDS: usize = 8;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub(super) struct AudioUniform {
+    // magnitude of each frequency from 0 up to half the sample rate, 0..1 from -80dB to 0dB
+    // packed four to a vec4, because uniform arrays have a 16 byte stride
+    spectrum: [[f32; 4]; SPECTRUM_BINS / 4],
+    // the latest samples, oldest first, from -1 to 1
+    waveform: [[f32; 4]; WAVEFORM_SAMPLES / 4],
+}
+
+// like Shadertoy's audio texture, 512 wide
+pub(super) const SPECTRUM_BINS: usize = 512;
+pub(super) const WAVEFORM_SAMPLES: usize = 512;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub(super) struct OverlayUniform {
+    resolution: [f32; 2],
+    scale_factor: f32,
+    // bits of the OverlayLayers to draw
+    layers: u32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub(super) struct LayerUniform {
+    // from --layer, multiplying the layer's alpha
+    opacity: f32,
+    // the layer's --blend mode, as its position in the list
+    mode: u32,
+    // uniform buffers are padded to 16 bytes
+    _padding: [u32; 2],
 }
 
 impl TimeUniform {
-    pub(super) fn new(start_time: Instant) -> Self {
-        Self {
-            time: start_time.elapsed().as_millis() as u32,
-        }
+    pub(super) fn new(elapsed: Duration) -> Self {
+        let mut uniform = Self::default();
+        uniform.update_time(elapsed);
+        uniform
     }
 
-    pub(super) fn update_time(&mut self, start_time: Instant) {
+    pub(super) fn update_time(&mut self, elapsed: Duration) {
         // update time to number of milliseconds since program start
-        self.time = start_time.elapsed().as_millis() as u32
+        // wraps after ~49 days
+        self.time = elapsed.as_millis() as u32;
+        // split into seconds and fraction so neither loses precision on long runs
+        self.seconds = elapsed.as_secs() as u32;
+        self.subsecond = elapsed.subsec_nanos() as f32 / 1e9;
+    }
+
+    // in seconds, as precise as the uniform has it
+    pub(super) fn seconds(&self) -> f64 {
+        self.seconds as f64 + self.subsecond as f64
+    }
+
+    pub(super) fn update_strobe(&mut self, frame: u64, period: u32) {
+        // wraps back to 0 every `period` frames
+        self.strobe = (frame % period as u64) as u32
+    }
+
+    pub(super) fn set_just_reloaded(&mut self, just_reloaded: bool) {
+        self.just_reloaded = just_reloaded as u32;
+    }
+
+    pub(super) fn set_frames_since_reload(&mut self, frames: u32) {
+        self.frames_since_reload = frames;
+    }
+
+    pub(super) fn set_samples(&mut self, samples: u32) {
+        self.samples = samples;
+    }
+
+    pub(super) fn update_frame(&mut self, frame: u64, delta: Duration) {
+        self.frame = frame as u32;
+        self.delta = delta.as_secs_f32();
+    }
+
+    pub(super) fn update_beat(&mut self, beat: u32, beat_phase: f32) {
+        self.beat = beat;
+        self.beat_phase = beat_phase;
     }
 }
 
 impl MouseUniform {
     pub(super) fn new() -> Self {
         Self {
-            cursor_pos: [0.0, 0.0],
+            zoom: 1.0,
+            ..Default::default()
         }
     }
 
-    pub(super) fn update_position(&mut self, x: f32, y: f32) {
+    // from the cursor's physical position and the window's physical size
+    pub(super) fn update_position(&mut self, x: f64, y: f64, size: [f64; 2], scale_factor: f64) {
         // update cursor position
         // y axis is reversed from GPU coords
-        self.cursor_pos = [x, 1.0 - y];
+        self.cursor_pos = [(x / size[0]) as f32, 1.0 - (y / size[1]) as f32];
+        self.physical = [x as f32, y as f32];
+        self.logical = [(x / scale_factor) as f32, (y / scale_factor) as f32];
+    }
+
+    pub(super) fn update_idle_time(&mut self, idle_time: f32) {
+        self.idle_time = idle_time;
+    }
+
+    // count a left click at the cursor, on top of the last one if it was recent enough
+    pub(super) fn click(&mut self, follows_last: bool) {
+        self.click_pos = self.cursor_pos;
+        self.click_count = if follows_last {
+            self.click_count + 1
+        } else {
+            1
+        };
+    }
+
+    pub(super) fn reset_clicks(&mut self) {
+        self.click_count = 0;
+    }
+
+    // from when each of the left, right and middle buttons went down, if they're still down
+    pub(super) fn update_buttons(&mut self, presses: [Option<Instant>; 3]) {
+        self.buttons = 0;
+        for (index, press) in presses.iter().enumerate() {
+            self.button_hold_time[index] = press.map_or(0.0, |press| press.elapsed().as_secs_f32());
+            if press.is_some() {
+                self.buttons |= 1 << index;
+            }
+        }
+    }
+
+    // zoom multiplicatively, so each line feels the same however far in it is
+    pub(super) fn scroll(&mut self, lines: f32) {
+        self.zoom = (self.zoom * ZOOM_STEP.powf(lines)).clamp(MIN_ZOOM, MAX_ZOOM);
+    }
+
+    pub(super) fn reset_zoom(&mut self) {
+        self.zoom = 1.0;
+    }
+
+    pub(super) fn add_path_length(&mut self, distance: f32) {
+        self.path_length += distance;
+    }
+
+    pub(super) fn reset_path_length(&mut self) {
+        self.path_length = 0.0;
+    }
+
+    // 0..1 across the window, with y going up
+    pub(super) fn position(&self) -> [f32; 2] {
+        self.cursor_pos
+    }
+
+    pub(super) fn update_hovering(&mut self, hovering_over_window: bool) {
+        self.hovering = hovering_over_window as u32;
+    }
+}
+
+impl CameraUniform {
+    pub(super) fn new(view_proj: Matrix4<f32>) -> Self {
+        Self {
+            view_proj: view_proj.into(),
+        }
     }
 
-    // fn update_hovering(&mut self, hovering_over_window: bool) {
-    //    todo!()
-    //}
+    pub(super) fn update_view_proj(&mut self, view_proj: Matrix4<f32>) {
+        self.view_proj = view_proj.into();
+    }
+}
+
+impl FrameTimesUniform {
+    pub(super) fn new() -> Self {
+        Self::default()
+    }
+
+    // overwrite the oldest frame time
+    pub(super) fn push(&mut self, delta: f32) {
+        let index = (self.newest as usize + 1) % FRAME_TIME_COUNT;
+        self.deltas[index / 4][index % 4] = delta;
+        self.newest = index as u32;
+    }
+}
+
+impl TileUniform {
+    pub(super) fn new(column: u32, row: u32, columns: u32, rows: u32) -> Self {
+        Self {
+            cell: [column, row],
+            grid: [columns, rows],
+            index: row * columns + column,
+            _padding: [0; 3],
+        }
+    }
+}
+
+impl LodUniform {
+    pub(super) fn new(bias: f32, levels: u32) -> Self {
+        Self {
+            bias,
+            levels,
+            _padding: [0; 2],
+        }
+    }
+
+    pub(super) fn update_levels(&mut self, levels: u32) {
+        self.levels = levels;
+    }
+}
+
+impl CustomUniform {
+    pub(super) fn new(count: usize) -> Self {
+        // rounded up to whole vec4s, and uniform buffers can't be empty
+        let vec4s = count.div_ceil(4).max(1);
+        Self {
+            values: vec![[0.0; 4]; vec4s],
+            count,
+        }
+    }
+
+    pub(super) fn len(&self) -> usize {
+        self.count
+    }
+
+    // slots past the end are ignored, as external inputs don't know how many there are
+    pub(super) fn set(&mut self, index: usize, value: f32) {
+        if index < self.count {
+            self.values[index / 4][index % 4] = value;
+        } else {
+            log::warn!(
+                "Ignoring custom uniform slot {}, as there are only {} (see --params)",
+                index,
+                self.count
+            );
+        }
+    }
+
+    // 0 for slots past the end, like they'd read in the shader
+    pub(super) fn get(&self, index: usize) -> f32 {
+        if index < self.count {
+            self.values[index / 4][index % 4]
+        } else {
+            0.0
+        }
+    }
+
+    // every slot's value in order, for saving a preset
+    pub(super) fn values(&self) -> Vec<f32> {
+        self.values
+            .iter()
+            .flatten()
+            .copied()
+            .take(self.count)
+            .collect()
+    }
+
+    pub(super) fn as_bytes(&self) -> &[u8] {
+        bytemuck::cast_slice(&self.values)
+    }
+}
+
+impl AdapterUniform {
+    pub(super) fn new(backend: Backend, max_texture_dimension: u32) -> Self {
+        let backend = match backend {
+            Backend::Vulkan => 0,
+            Backend::Metal => 1,
+            Backend::Dx12 => 2,
+            Backend::Gl => 3,
+            Backend::Dx11 => 4,
+            Backend::BrowserWebGpu => 5,
+            // only used for testing wgpu itself
+            Backend::Empty => u32::MAX,
+        };
+        Self {
+            backend,
+            max_texture_dimension,
+            _padding: [0; 2],
+        }
+    }
+}
+
+impl RngUniform {
+    pub(super) fn new(seed: u32) -> Self {
+        let mut rng = Self {
+            rng: 0,
+            state: seed,
+            _padding: [0; 2],
+        };
+        rng.step();
+        rng
+    }
+
+    // same PCG hash as the example shader's pcg32_hash
+    // from https://www.reedbeta.com/blog/hash-functions-for-gpu-rendering/
+    pub(super) fn step(&mut self) {
+        self.state = self.state.wrapping_mul(747796405).wrapping_add(2891336453);
+        let state = self.state;
+        let word = ((state >> ((state >> 28) + 4)) ^ state).wrapping_mul(277803737);
+        self.rng = (word >> 22) ^ word;
+    }
+}
+
+impl DisplayUniform {
+    pub(super) fn new(refresh_rate: f32) -> Self {
+        Self {
+            refresh_rate,
+            _padding: [0; 3],
+        }
+    }
+
+    pub(super) fn update_refresh_rate(&mut self, refresh_rate: f32) {
+        self.refresh_rate = refresh_rate;
+    }
+}
+
+impl WindowUniform {
+    pub(super) fn new(position: [f32; 2], desktop_size: [f32; 2]) -> Self {
+        Self {
+            position,
+            desktop_size,
+        }
+    }
+
+    pub(super) fn update(&mut self, position: [f32; 2], desktop_size: [f32; 2]) {
+        self.position = position;
+        self.desktop_size = desktop_size;
+    }
+}
+
+impl ResolutionUniform {
+    pub(super) fn new(width: u32, height: u32, scale: f32) -> Self {
+        let mut uniform = Self::default();
+        uniform.update_size(width, height, scale);
+        uniform
+    }
+
+    pub(super) fn update_size(&mut self, width: u32, height: u32, scale: f32) {
+        self.size = [width as f32, height as f32];
+        self.res_px = [width, height];
+        self.scale = scale;
+    }
+}
+
+impl DateUniform {
+    pub(super) fn new() -> Self {
+        let mut uniform = Self::default();
+        uniform.update();
+        uniform
+    }
+
+    // to the wall clock, which carries on while time is paused
+    pub(super) fn update(&mut self) {
+        let now = Local::now();
+        self.ymd = [now.year() as f32, now.month0() as f32, now.day() as f32];
+        self.seconds =
+            now.num_seconds_from_midnight() as f32 + now.nanosecond() as f32 / 1_000_000_000.0;
+    }
+}
+
+impl KeyboardUniform {
+    pub(super) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(super) fn set(&mut self, key: u32, pressed: bool) {
+        let word = key as usize / 32;
+        if word >= KEYBOARD_WORDS {
+            return;
+        }
+        let bit = 1 << (key % 32);
+        let packed = &mut self.pressed[word / 4][word % 4];
+        if pressed {
+            *packed |= bit;
+        } else {
+            *packed &= !bit;
+        }
+    }
+
+    // let go of every key, as when the window loses focus and won't hear them released
+    pub(super) fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+impl AudioUniform {
+    // silent until there's --audio
+    pub(super) fn new() -> Self {
+        Self::zeroed()
+    }
+
+    pub(super) fn update(
+        &mut self,
+        spectrum: impl Iterator<Item = f32>,
+        waveform: impl Iterator<Item = f32>,
+    ) {
+        for (index, magnitude) in spectrum.take(SPECTRUM_BINS).enumerate() {
+            self.spectrum[index / 4][index % 4] = magnitude;
+        }
+        for (index, sample) in waveform.take(WAVEFORM_SAMPLES).enumerate() {
+            self.waveform[index / 4][index % 4] = sample;
+        }
+    }
+}
+
+impl LayerUniform {
+    pub(super) fn new(opacity: f32, mode: u32) -> Self {
+        Self {
+            opacity,
+            mode,
+            ..Default::default()
+        }
+    }
+}
+
+impl OverlayUniform {
+    pub(super) fn new(width: u32, height: u32, scale_factor: f64, layers: u32) -> Self {
+        Self {
+            resolution: [width as f32, height as f32],
+            scale_factor: scale_factor as f32,
+            layers,
+        }
+    }
+
+    pub(super) fn layers(&self) -> u32 {
+        self.layers
+    }
+
+    pub(super) fn toggle_layers(&mut self, layers: u32) {
+        self.layers ^= layers;
+    }
 }
 
 pub(super) mod bindings;
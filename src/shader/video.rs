@@ -0,0 +1,273 @@
+use std::{
+    collections::HashMap,
+    io::Read,
+    num::NonZeroU32,
+    process::{Child, Command, Stdio},
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+    time::Duration,
+};
+use wgpu::*;
+
+use super::super::config::{Filter, TextureInput};
+use super::textures::{Channel, Sampling};
+
+// decoded frames waiting to be shown, so decoding runs a little ahead of rendering
+const BUFFERED_FRAMES: usize = 4;
+// for videos that don't say, or say something nonsensical
+const DEFAULT_FRAME_RATE: f64 = 30.0;
+
+/// What ffprobe says about a video's first stream
+#[derive(Debug)]
+struct VideoInfo {
+    width: u32,
+    height: u32,
+    frame_rate: f64,
+    // None for streams that don't know their length, which can't be seeked in
+    duration: Option<Duration>,
+}
+
+impl VideoInfo {
+    // ffprobe prints `key=value` lines for the entries asked for
+    fn probe(path: &str) -> Self {
+        let output = Command::new("ffprobe")
+            .args([
+                "-v",
+                "error",
+                "-select_streams",
+                "v:0",
+                "-show_entries",
+                "stream=width,height,avg_frame_rate:format=duration",
+                "-of",
+                "default=noprint_wrappers=1",
+                path,
+            ])
+            .output()
+            .unwrap_or_else(|e| {
+                panic!("Could not run ffprobe for --video, is it installed? {}", e)
+            });
+        if !output.status.success() {
+            panic!(
+                "ffprobe could not read video {}: {}",
+                path,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        let text = String::from_utf8_lossy(&output.stdout);
+        let entries: HashMap<_, _> = text
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .collect();
+        let dimension = |key| {
+            entries
+                .get(key)
+                .and_then(|value| value.parse().ok())
+                .unwrap_or_else(|| panic!("Video {} has no {}, is it a video?", path, key))
+        };
+        // a fraction like `30000/1001`
+        let frame_rate = entries
+            .get("avg_frame_rate")
+            .and_then(|rate| rate.split_once('/'))
+            .and_then(|(numerator, denominator)| {
+                Some(numerator.parse::<f64>().ok()? / denominator.parse::<f64>().ok()?)
+            })
+            .filter(|rate| rate.is_finite() && *rate > 0.0)
+            .unwrap_or_else(|| {
+                log::warn!(
+                    "Video {} has no frame rate, assuming {}",
+                    path,
+                    DEFAULT_FRAME_RATE
+                );
+                DEFAULT_FRAME_RATE
+            });
+        let duration = entries
+            .get("duration")
+            .and_then(|duration| duration.parse::<f64>().ok())
+            .filter(|duration| duration.is_finite() && *duration > 0.0)
+            .map(Duration::from_secs_f64);
+        Self {
+            width: dimension("width"),
+            height: dimension("height"),
+            frame_rate,
+            duration,
+        }
+    }
+
+    fn frame_bytes(&self) -> usize {
+        self.width as usize * self.height as usize * 4
+    }
+}
+
+/// An ffmpeg process decoding a video to raw frames, looping forever
+#[derive(Debug)]
+struct Decoder {
+    child: Child,
+    frames: Receiver<Vec<u8>>,
+}
+
+impl Decoder {
+    // start decoding from this far into the video
+    fn spawn(path: &str, info: &VideoInfo, start: Duration) -> Self {
+        let mut child = Command::new("ffmpeg")
+            .args(["-v", "error", "-stream_loop", "-1"])
+            .args(["-ss", &start.as_secs_f64().to_string()])
+            .args(["-i", path])
+            .args(["-f", "rawvideo", "-pix_fmt", "rgba", "-"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap_or_else(|e| panic!("Could not run ffmpeg for --video, is it installed? {}", e));
+        let mut stdout = child.stdout.take().expect("ffmpeg's stdout is piped");
+        let frame_bytes = info.frame_bytes();
+        // reading blocks until ffmpeg writes, so it's kept off the render thread
+        let (sender, frames) = mpsc::sync_channel(BUFFERED_FRAMES);
+        thread::spawn(move || loop {
+            let mut frame = vec![0; frame_bytes];
+            if stdout.read_exact(&mut frame).is_err() || sender.send(frame).is_err() {
+                break;
+            }
+        });
+        Self { child, frames }
+    }
+}
+
+impl Drop for Decoder {
+    fn drop(&mut self) {
+        // it would otherwise keep decoding into a closed pipe until it noticed
+        if let Err(e) = self.child.kill() {
+            log::debug!("Could not stop ffmpeg: {}", e);
+        }
+        let _ = self.child.wait();
+    }
+}
+
+/// A video file decoded into a texture, for --video
+/// Frames are picked by the time uniform, so they're repeated when rendering runs faster
+/// than the video, and skipped when it runs slower
+#[derive(Debug)]
+pub(super) struct VideoTexture {
+    path: String,
+    info: VideoInfo,
+    filter: Filter,
+    texture: Texture,
+    decoder: Decoder,
+    // frames taken from the decoder since it started
+    decoded: u64,
+    // the frame the decoder started at, counting from the start of the video
+    first_frame: u64,
+    // whether the decoder stopping has been reported, so it only happens once
+    stopped: bool,
+}
+
+impl VideoTexture {
+    pub(super) fn open(device: &Device, input: &TextureInput) -> Self {
+        let info = VideoInfo::probe(&input.path);
+        log::info!(
+            "Playing video {} at {}x{}, {:.2} frames per second",
+            input.path,
+            info.width,
+            info.height,
+            info.frame_rate
+        );
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some(&input.path),
+            size: Extent3d {
+                width: info.width,
+                height: info.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            // video is stored gamma encoded, like images
+            format: TextureFormat::Rgba8UnormSrgb,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+        });
+        let decoder = Decoder::spawn(&input.path, &info, Duration::ZERO);
+        Self {
+            path: input.path.clone(),
+            info,
+            filter: input.filter,
+            texture,
+            decoder,
+            decoded: 0,
+            first_frame: 0,
+            stopped: false,
+        }
+    }
+
+    pub(super) fn channel(&self, device: &Device, sampling: Sampling) -> Channel {
+        Channel::with_texture(
+            device,
+            &self.texture,
+            TextureViewDimension::D2,
+            self.filter,
+            sampling,
+        )
+    }
+
+    // show the frame for this shader time, decoding up to it
+    // doesn't wait for the decoder, so a slow one leaves the last frame up a little longer
+    pub(super) fn update(&mut self, queue: &Queue, time: Duration) {
+        let target = (time.as_secs_f64() * self.info.frame_rate) as u64;
+        // ffmpeg can only go forward, so going back in time, as on reload, starts a new one
+        if target < self.first_frame + self.decoded.saturating_sub(1) {
+            self.restart(target);
+        }
+        let mut latest = None;
+        while self.first_frame + self.decoded <= target {
+            match self.decoder.frames.try_recv() {
+                Ok(frame) => {
+                    latest = Some(frame);
+                    self.decoded += 1;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    if !self.stopped {
+                        log::error!("Video {} stopped decoding", self.path);
+                        self.stopped = true;
+                    }
+                    break;
+                }
+            }
+        }
+        if let Some(frame) = latest {
+            queue.write_texture(
+                ImageCopyTexture {
+                    texture: &self.texture,
+                    mip_level: 0,
+                    origin: Origin3d::ZERO,
+                    aspect: TextureAspect::All,
+                },
+                &frame,
+                ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(self.info.width * 4),
+                    rows_per_image: NonZeroU32::new(self.info.height),
+                },
+                Extent3d {
+                    width: self.info.width,
+                    height: self.info.height,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+    }
+
+    // start decoding again from a frame, seeking to it within the loop if the length is known
+    fn restart(&mut self, frame: u64) {
+        let start = match self.info.duration {
+            Some(duration) => {
+                let time = Duration::from_secs_f64(frame as f64 / self.info.frame_rate);
+                Duration::from_nanos((time.as_nanos() % duration.as_nanos()) as u64)
+            }
+            None => Duration::ZERO,
+        };
+        log::debug!("Restarting video {} at {:?}", self.path, start);
+        self.decoder = Decoder::spawn(&self.path, &self.info, start);
+        // without a known length it plays from the beginning, but still counts from here
+        self.first_frame = frame;
+        self.decoded = 0;
+        self.stopped = false;
+    }
+}
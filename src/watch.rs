@@ -0,0 +1,89 @@
+use std::{
+    collections::HashMap,
+    fs, thread,
+    time::{Duration, SystemTime},
+};
+use winit::event_loop::EventLoopProxy;
+
+// how often the shader's files are checked for changes
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+// how long the files have to stay the same before reloading, as editors often save in
+// several writes, and reloading halfway through would compile a half-written shader
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Sent to the event loop when --watch sees the shader's files change
+#[derive(Debug)]
+pub(crate) struct ShaderChanged;
+
+/// Watch files on a background thread for --watch, waking the event loop when they change
+///
+/// Checks their modification times, like --texture files, rather than asking the OS
+/// to report changes, so it works the same everywhere. The files are listed again on every
+/// check, so a --manifest that gains a pass, or a shader that gains an include, has it
+/// watched too.
+pub(crate) fn spawn_watcher<F>(list_paths: F, proxy: EventLoopProxy<ShaderChanged>)
+where
+    F: Fn() -> Vec<String> + Send + 'static,
+{
+    log::info!("Watching {} for changes", list_paths().join(", "));
+    thread::Builder::new()
+        .name("Shader Watcher".to_owned())
+        .spawn(move || watch(&list_paths, &proxy))
+        .expect("Could not start watching the shader");
+}
+
+// every file's modification time, or None if one couldn't be found
+fn modified_times(paths: &[String]) -> Option<HashMap<String, SystemTime>> {
+    paths
+        .iter()
+        .map(|path| {
+            let modified = fs::metadata(path).and_then(|metadata| metadata.modified());
+            modified.ok().map(|modified| (path.clone(), modified))
+        })
+        .collect()
+}
+
+fn watch(list_paths: &impl Fn() -> Vec<String>, proxy: &EventLoopProxy<ShaderChanged>) {
+    let mut paths = list_paths();
+    let mut modified = modified_times(&paths).unwrap_or_default();
+    loop {
+        thread::sleep(POLL_INTERVAL);
+        // the manifest may have gained passes, or a reload new includes, since last time
+        let listed = list_paths();
+        if listed != paths {
+            log::info!("Watching {} for changes", listed.join(", "));
+            paths = listed;
+        }
+        // a file briefly missing, as when an editor saves by replacing it, isn't a change
+        let mut latest = match modified_times(&paths) {
+            Some(latest) => latest,
+            None => continue,
+        };
+        // files only just listed have nothing to compare with, so they only start being watched
+        let changed = latest
+            .iter()
+            .any(|(path, time)| modified.get(path).is_some_and(|before| before != time));
+        if !changed {
+            modified = latest;
+            continue;
+        }
+        // wait for the writes to settle
+        let settled = loop {
+            thread::sleep(DEBOUNCE);
+            match modified_times(&paths) {
+                Some(settled) if settled == latest => break Some(settled),
+                Some(settled) => latest = settled,
+                // gone partway through, so check again next time
+                None => break None,
+            }
+        };
+        modified = match settled {
+            Some(settled) => settled,
+            None => continue,
+        };
+        // the event loop has closed, so there's nothing left to reload
+        if proxy.send_event(ShaderChanged).is_err() {
+            return;
+        }
+    }
+}